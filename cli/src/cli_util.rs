@@ -138,6 +138,13 @@ use jj_lib::workspace::WorkspaceLoader;
 use jj_lib::workspace::WorkspaceLoaderFactory;
 use jj_lib::workspace::default_working_copy_factories;
 use jj_lib::workspace::get_working_copy_factory;
+use jj_lib::workspace_store;
+use jj_lib::workspace_store::NormalizingPathCanonicalizer;
+use jj_lib::workspace_store::SimpleWorkspaceStore;
+use jj_lib::workspace_store::SimpleWorkspaceStoreOptions;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::load_workspace_store;
 use pollster::FutureExt as _;
 use tracing::instrument;
 use tracing_chrome::ChromeLayerBuilder;
@@ -411,6 +418,24 @@ impl CommandHelper {
             .map_err(Clone::clone)
     }
 
+    /// Whether the loaded repo is the repo of the workspace containing the
+    /// current directory. This is the case unless `-R` points to another repo.
+    pub fn is_cwd_repo(&self) -> Result<bool, CommandError> {
+        if self.global_args().repository.is_none() {
+            return Ok(true);
+        }
+        let loader = self.workspace_loader()?;
+        let Ok(cwd_loader) = self
+            .data
+            .workspace_loader_factory
+            .create(find_workspace_dir(self.cwd()))
+        else {
+            return Ok(false);
+        };
+        let canonicalize = |path: &Path| dunce::canonicalize(path).unwrap_or(path.to_owned());
+        Ok(canonicalize(loader.repo_path()) == canonicalize(cwd_loader.repo_path()))
+    }
+
     fn new_workspace_loader_at(
         &self,
         workspace_root: &Path,
@@ -1011,6 +1036,7 @@ pub struct WorkspaceCommandHelper {
     op_summary_template_text: String,
     may_update_working_copy: bool,
     working_copy_shared_with_git: bool,
+    workspace_store: OnceCell<Arc<dyn WorkspaceStore>>,
 }
 
 enum SnapshotWorkingCopyError {
@@ -1059,6 +1085,7 @@ impl WorkspaceCommandHelper {
             op_summary_template_text,
             may_update_working_copy,
             working_copy_shared_with_git,
+            workspace_store: OnceCell::new(),
         };
         // Parse commit_summary template early to report error before starting
         // mutable operation.
@@ -1229,6 +1256,107 @@ impl WorkspaceCommandHelper {
         self.workspace.repo_path()
     }
 
+    /// Returns the store of workspace roots, configured according to the
+    /// settings. See [`workspace_store_options()`].
+    ///
+    /// The store is loaded once with the implementation recorded in the repo.
+    /// Nothing is recorded by loading it. If the repo doesn't have a store
+    /// yet, e.g. because it was created before workspace roots were recorded,
+    /// an empty read-only store is returned, and the store is only created by
+    /// [`Self::init_workspace_store()`].
+    pub fn workspace_store(&self) -> Result<Arc<dyn WorkspaceStore>, CommandError> {
+        if let Some(store) = self.workspace_store.get() {
+            return Ok(store.clone());
+        }
+        let options = workspace_store_options(self.settings())?;
+        match load_workspace_store(self.repo_path(), options) {
+            Ok(store) => Ok(self.workspace_store.get_or_init(|| store.into()).clone()),
+            // Not cached, so that the store can still be created.
+            Err(WorkspaceStoreError::NotInitialized(_)) => Ok(Arc::new(
+                SimpleWorkspaceStore::load_readonly(self.repo_path())?,
+            )),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Like [`Self::workspace_store()`], but creates the store if the repo
+    /// doesn't have one yet. Commands that record or remove workspace roots
+    /// use this. If the current workspace has no entry, its root is recorded
+    /// when a transaction is finished.
+    pub fn init_workspace_store(&self) -> Result<Arc<dyn WorkspaceStore>, CommandError> {
+        if let Some(store) = self.workspace_store.get() {
+            return Ok(store.clone());
+        }
+        let options = workspace_store_options(self.settings())?;
+        let store: Arc<dyn WorkspaceStore> =
+            match load_workspace_store(self.repo_path(), options.clone()) {
+                Ok(store) => store.into(),
+                // The repo was created before workspace roots were recorded
+                Err(WorkspaceStoreError::NotInitialized(_)) => Arc::new(
+                    SimpleWorkspaceStore::init_with_options(self.repo_path(), options)?,
+                ),
+                Err(err) => return Err(err.into()),
+            };
+        Ok(self.workspace_store.get_or_init(|| store).clone())
+    }
+
+    /// Restores the tombstoned store entries of the workspaces that were
+    /// brought back since `old_repo`, e.g. by `jj undo` after `jj workspace
+    /// forget`. Like backfilling, failures are only logged.
+    fn restore_workspace_store_entries(&self, old_repo: &ReadonlyRepo) {
+        let old_view = old_repo.view();
+        let new_view = self.repo().view();
+        if new_view
+            .wc_commit_ids()
+            .keys()
+            .all(|name| old_view.get_wc_commit_id(name).is_some())
+        {
+            return;
+        }
+        let store = match self.workspace_store() {
+            Ok(store) if !store.is_read_only() => store,
+            Ok(_) => return,
+            Err(err) => {
+                tracing::warn!(?err, "failed to load the workspace store");
+                return;
+            }
+        };
+        match workspace_store::restore_entries(store.as_ref(), old_view, new_view) {
+            Ok(names) => {
+                for name in names {
+                    tracing::info!(?name, "restored the root of the workspace");
+                }
+            }
+            Err(err) => tracing::warn!(?err, "failed to restore the roots of workspaces"),
+        }
+    }
+
+    /// Records the root of the current workspace if it has no entry in the
+    /// store. Failures are only logged since nothing depends on the entry
+    /// being written right away.
+    fn backfill_workspace_store(&self, store: &dyn WorkspaceStore) {
+        let name = self.workspace_name();
+        // A forgotten workspace shouldn't be recorded again.
+        if store.is_read_only() || self.get_wc_commit_id().is_none() {
+            return;
+        }
+        let result = match store.exists(name) {
+            Ok(true) => return,
+            Ok(false) => store.create_path(name, self.workspace_root()),
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(()) => tracing::info!(?name, "recorded the root of the current workspace"),
+            // Recorded by another process in the meantime
+            Err(WorkspaceStoreError::AlreadyExists(_)) => {}
+            Err(err) => tracing::warn!(
+                ?name,
+                ?err,
+                "failed to record the root of the current workspace"
+            ),
+        }
+    }
+
     pub fn workspace(&self) -> &Workspace {
         &self.workspace
     }
@@ -2102,6 +2230,11 @@ See https://jj-vcs.github.io/jj/latest/working-copy/#stale-working-copy \
         }
 
         self.user_repo = ReadonlyUserRepo::new(tx.commit(description)?);
+        self.restore_workspace_store_entries(&old_repo);
+        // Only commands that use the store record the current workspace.
+        if let Some(store) = self.workspace_store.get() {
+            self.backfill_workspace_store(store.as_ref());
+        }
 
         // Update working copy before reporting repo changes, so that
         // potential errors while reporting changes (broken pipe, etc)
@@ -2421,6 +2554,10 @@ impl WorkspaceCommandTransaction<'_> {
         self.tx.repo_mut()
     }
 
+    pub fn set_tag(&mut self, key: String, value: String) {
+        self.tx.set_tag(key, value);
+    }
+
     pub fn check_out(&mut self, commit: &Commit) -> Result<Commit, CheckOutCommitError> {
         let name = self.helper.workspace_name().to_owned();
         self.id_prefix_context.take(); // invalidate
@@ -2899,6 +3036,25 @@ pub fn has_tracked_remote_bookmarks(repo: &dyn Repo, bookmark: &RefName) -> bool
         .any(|(_, remote_ref)| remote_ref.is_tracked())
 }
 
+/// Returns the options of the store of workspace roots according to the
+/// `workspace.canonicalize-paths`, `workspace.index-dir`,
+/// `workspace.tombstones`, and `workspace.store-compress` settings.
+pub fn workspace_store_options(
+    settings: &UserSettings,
+) -> Result<SimpleWorkspaceStoreOptions, ConfigGetError> {
+    let mut options = SimpleWorkspaceStoreOptions::default();
+    if !settings.get_bool("workspace.canonicalize-paths")? {
+        options.canonicalizer = Arc::new(NormalizingPathCanonicalizer);
+    }
+    options.index_dir = settings
+        .get_string("workspace.index-dir")
+        .optional()?
+        .map(|dir| jj_lib::file_util::expand_home_path(&dir));
+    options.tombstones = settings.get_bool("workspace.tombstones")?;
+    options.compress = settings.get_bool("workspace.store-compress")?;
+    Ok(options)
+}
+
 pub fn load_template_aliases(
     ui: &Ui,
     stacked_config: &StackedConfig,
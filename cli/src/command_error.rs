@@ -41,6 +41,7 @@ use jj_lib::op_heads_store::OpHeadsStoreError;
 use jj_lib::op_store::OpStoreError;
 use jj_lib::op_walk::OpsetEvaluationError;
 use jj_lib::op_walk::OpsetResolutionError;
+use jj_lib::ref_name::WorkspaceName;
 use jj_lib::repo::CheckOutCommitError;
 use jj_lib::repo::EditCommitError;
 use jj_lib::repo::RepoLoaderError;
@@ -61,6 +62,8 @@ use jj_lib::working_copy::ResetError;
 use jj_lib::working_copy::SnapshotError;
 use jj_lib::working_copy::WorkingCopyStateError;
 use jj_lib::workspace::WorkspaceInitError;
+use jj_lib::workspace_store::WorkspaceOverviewError;
+use jj_lib::workspace_store::WorkspaceStoreError;
 use thiserror::Error;
 
 use crate::cli_util::short_operation_hash;
@@ -178,6 +181,53 @@ impl ErrorWithMessage {
     }
 }
 
+/// Error of a command whose output is JSON. It's printed to stderr as a JSON
+/// object rather than as a human-readable message.
+#[derive(Debug, Error, serde::Serialize)]
+#[error("{error}")]
+struct JsonError {
+    error: String,
+    code: &'static str,
+    workspace: Option<String>,
+}
+
+/// Returns a user error that is printed as a JSON object with `error`, `code`,
+/// and `workspace` keys. `code` should be stable since scripts may match on it.
+pub fn json_error(
+    code: &'static str,
+    message: impl Into<String>,
+    workspace: Option<&WorkspaceName>,
+) -> CommandError {
+    user_error(JsonError {
+        error: message.into(),
+        code,
+        workspace: workspace.map(|name| name.as_str().to_owned()),
+    })
+}
+
+/// Like [`json_error()`], but for errors of the workspace store, which provide
+/// their own codes.
+pub fn workspace_store_json_error(err: &WorkspaceStoreError) -> CommandError {
+    json_error(err.code(), err.to_string(), err.workspace_name())
+}
+
+/// User error that exits with a code chosen by the command rather than 1.
+#[derive(Debug, Error)]
+#[error("{message}")]
+struct ExitCodeError {
+    message: String,
+    exit_code: u8,
+}
+
+/// Returns a user error that exits with `exit_code`. Scripts may rely on the
+/// code, so it should be documented by the command and stay stable.
+pub fn user_error_with_exit_code(message: impl Into<String>, exit_code: u8) -> CommandError {
+    user_error(ExitCodeError {
+        message: message.into(),
+        exit_code,
+    })
+}
+
 pub fn user_error(err: impl Into<Box<dyn error::Error + Send + Sync>>) -> CommandError {
     CommandError::new(CommandErrorKind::User, err)
 }
@@ -365,6 +415,32 @@ impl From<WorkspaceInitError> for CommandError {
             }
             WorkspaceInitError::SignInit(err) => user_error(err),
             WorkspaceInitError::TransactionCommit(err) => err.into(),
+            WorkspaceInitError::WorkspaceStore(err) => err.into(),
+        }
+    }
+}
+
+impl From<WorkspaceOverviewError> for CommandError {
+    fn from(err: WorkspaceOverviewError) -> Self {
+        match err {
+            WorkspaceOverviewError::Store(err) => err.into(),
+            WorkspaceOverviewError::Backend(err) => err.into(),
+        }
+    }
+}
+
+impl From<WorkspaceStoreError> for CommandError {
+    fn from(err: WorkspaceStoreError) -> Self {
+        match err {
+            WorkspaceStoreError::NotFound(_)
+            | WorkspaceStoreError::AlreadyExists(_)
+            | WorkspaceStoreError::InvalidName(_)
+            | WorkspaceStoreError::InvalidPath { .. }
+            | WorkspaceStoreError::DuplicatePath { .. }
+            | WorkspaceStoreError::WrongType { .. }
+            | WorkspaceStoreError::UnknownType { .. }
+            | WorkspaceStoreError::ReadOnly(_) => user_error(err),
+            _ => internal_error_with_message("Failed to access the workspace store", err),
         }
     }
 }
@@ -993,8 +1069,16 @@ fn try_handle_command_result(ui: &mut Ui, result: Result<(), CommandError>) -> i
     let hints = &cmd_err.hints;
     match cmd_err.kind {
         CommandErrorKind::User => {
-            print_error(ui, "Error: ", err, hints)?;
-            Ok(1)
+            if let Some(err) = err.downcast_ref::<JsonError>() {
+                let json = serde_json::to_string(err).map_err(io::Error::other)?;
+                writeln!(ui.stderr(), "{json}")?;
+            } else {
+                print_error(ui, "Error: ", err, hints)?;
+            }
+            match err.downcast_ref::<ExitCodeError>() {
+                Some(err) => Ok(err.exit_code),
+                None => Ok(1),
+            }
         }
         CommandErrorKind::Config => {
             print_error(ui, "Config error: ", err, hints)?;
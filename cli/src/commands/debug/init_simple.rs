@@ -19,6 +19,7 @@ use jj_lib::workspace::Workspace;
 use tracing::instrument;
 
 use crate::cli_util::CommandHelper;
+use crate::cli_util::workspace_store_options;
 use crate::command_error::CommandError;
 use crate::command_error::cli_error;
 use crate::command_error::user_error_with_message;
@@ -57,7 +58,12 @@ pub(crate) fn cmd_debug_init_simple(
         .and_then(|_| dunce::canonicalize(wc_path))
         .map_err(|e| user_error_with_message("Failed to create workspace", e))?;
 
-    Workspace::init_simple(&command.settings_for_new_workspace(&wc_path)?, &wc_path)?;
+    let settings = command.settings_for_new_workspace(&wc_path)?;
+    Workspace::init_simple_with_workspace_store_options(
+        &settings,
+        &wc_path,
+        &workspace_store_options(&settings)?,
+    )?;
 
     let relative_wc_path = file_util::relative_path(cwd, &wc_path);
     writeln!(
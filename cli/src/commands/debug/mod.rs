@@ -26,6 +26,7 @@ mod template;
 mod tree;
 mod watchman;
 mod working_copy;
+mod workspace_store;
 
 use clap::Subcommand;
 use jj_lib::local_working_copy::LocalWorkingCopy;
@@ -59,6 +60,8 @@ use self::watchman::DebugWatchmanCommand;
 use self::watchman::cmd_debug_watchman;
 use self::working_copy::DebugWorkingCopyArgs;
 use self::working_copy::cmd_debug_working_copy;
+use self::workspace_store::DebugWorkspaceStoreArgs;
+use self::workspace_store::cmd_debug_workspace_store;
 use crate::cli_util::CommandHelper;
 use crate::command_error::CommandError;
 use crate::command_error::user_error;
@@ -84,6 +87,7 @@ pub enum DebugCommand {
     #[command(subcommand)]
     Watchman(DebugWatchmanCommand),
     WorkingCopy(DebugWorkingCopyArgs),
+    WorkspaceStore(DebugWorkspaceStoreArgs),
 }
 
 pub fn cmd_debug(
@@ -106,6 +110,7 @@ pub fn cmd_debug(
         DebugCommand::Tree(args) => cmd_debug_tree(ui, command, args),
         DebugCommand::Watchman(args) => cmd_debug_watchman(ui, command, args),
         DebugCommand::WorkingCopy(args) => cmd_debug_working_copy(ui, command, args),
+        DebugCommand::WorkspaceStore(args) => cmd_debug_workspace_store(ui, command, args),
     }
 }
 
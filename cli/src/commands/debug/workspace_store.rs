@@ -0,0 +1,58 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use jj_lib::ref_name::WorkspaceNameBuf;
+
+use crate::cli_util::CommandHelper;
+use crate::command_error::CommandError;
+use crate::ui::Ui;
+
+/// Show information about the store of workspace roots
+#[derive(clap::Args, Clone, Debug)]
+pub struct DebugWorkspaceStoreArgs {
+    /// Also show the files the entries of these workspaces are stored in
+    workspaces: Vec<WorkspaceNameBuf>,
+}
+
+pub fn cmd_debug_workspace_store(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &DebugWorkspaceStoreArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let store = workspace_command.workspace_store()?;
+    writeln!(ui.stdout(), "Backend: {}", store.name())?;
+    if let Some(dir) = store.store_dir() {
+        writeln!(ui.stdout(), "Directory: {}", dir.display())?;
+    }
+    writeln!(ui.stdout(), "Entries: {}", store.len()?)?;
+    for name in &args.workspaces {
+        let exists = store.exists(name)?;
+        match store.entry_file(name)? {
+            Some(file) if exists => {
+                writeln!(ui.stdout(), "{}: {}", name.as_symbol(), file.display())?;
+            }
+            Some(file) => writeln!(
+                ui.stdout(),
+                "{}: {} (no entry)",
+                name.as_symbol(),
+                file.display()
+            )?,
+            None => writeln!(ui.stdout(), "{}: (not stored in a file)", name.as_symbol())?,
+        }
+    }
+    Ok(())
+}
@@ -32,6 +32,7 @@ use jj_lib::workspace::Workspace;
 use super::write_repository_level_trunk_alias;
 use crate::cli_util::CommandHelper;
 use crate::cli_util::WorkspaceCommandHelper;
+use crate::cli_util::workspace_store_options;
 use crate::command_error::CommandError;
 use crate::command_error::cli_error;
 use crate::command_error::user_error;
@@ -256,10 +257,19 @@ fn init_workspace(
     colocate: bool,
 ) -> Result<WorkspaceCommandHelper, CommandError> {
     let settings = command.settings_for_new_workspace(wc_path)?;
+    let workspace_store_options = workspace_store_options(&settings)?;
     let (workspace, repo) = if colocate {
-        Workspace::init_colocated_git(&settings, wc_path)?
+        Workspace::init_colocated_git_with_workspace_store_options(
+            &settings,
+            wc_path,
+            &workspace_store_options,
+        )?
     } else {
-        Workspace::init_internal_git(&settings, wc_path)?
+        Workspace::init_internal_git_with_workspace_store_options(
+            &settings,
+            wc_path,
+            &workspace_store_options,
+        )?
     };
     let workspace_command = command.for_workable_repo(ui, workspace, repo)?;
     maybe_add_gitignore(&workspace_command)?;
@@ -33,6 +33,7 @@ use super::write_repository_level_trunk_alias;
 use crate::cli_util::CommandHelper;
 use crate::cli_util::WorkspaceCommandHelper;
 use crate::cli_util::start_repo_transaction;
+use crate::cli_util::workspace_store_options;
 use crate::command_error::CommandError;
 use crate::command_error::cli_error;
 use crate::command_error::internal_error;
@@ -185,15 +186,24 @@ fn do_init(
     };
 
     let settings = command.settings_for_new_workspace(workspace_root)?;
+    let workspace_store_options = workspace_store_options(&settings)?;
     match &init_mode {
         GitInitMode::Colocate => {
-            let (workspace, repo) = Workspace::init_colocated_git(&settings, workspace_root)?;
+            let (workspace, repo) = Workspace::init_colocated_git_with_workspace_store_options(
+                &settings,
+                workspace_root,
+                &workspace_store_options,
+            )?;
             let workspace_command = command.for_workable_repo(ui, workspace, repo)?;
             maybe_add_gitignore(&workspace_command)?;
         }
         GitInitMode::External(git_repo_path) => {
-            let (workspace, repo) =
-                Workspace::init_external_git(&settings, workspace_root, git_repo_path)?;
+            let (workspace, repo) = Workspace::init_external_git_with_workspace_store_options(
+                &settings,
+                workspace_root,
+                git_repo_path,
+                &workspace_store_options,
+            )?;
             // Import refs first so all the reachable commits are indexed in
             // chronological order.
             let colocated = is_colocated_git_workspace(&workspace, &repo);
@@ -216,7 +226,11 @@ fn do_init(
             print_trackable_remote_bookmarks(ui, workspace_command.repo().view())?;
         }
         GitInitMode::Internal => {
-            Workspace::init_internal_git(&settings, workspace_root)?;
+            Workspace::init_internal_git_with_workspace_store_options(
+                &settings,
+                workspace_root,
+                &workspace_store_options,
+            )?;
         }
     }
     Ok(())
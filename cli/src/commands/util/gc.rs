@@ -17,16 +17,25 @@ use std::time::Duration;
 use std::time::SystemTime;
 
 use jj_lib::repo::Repo as _;
+use jj_lib::workspace_store;
+use jj_lib::workspace_store::MaintenanceOptions;
 
 use crate::cli_util::CommandHelper;
 use crate::command_error::CommandError;
 use crate::command_error::user_error;
+use crate::commands::workspace;
 use crate::ui::Ui;
 
 /// Run backend-dependent garbage collection.
 ///
 /// To garbage-collect old operations and the commits/objects referenced by
 /// them, run `jj op abandon ..<some old operation>` before `jj util gc`.
+///
+/// The recorded workspace roots are cleaned up too. Unreadable entries are
+/// moved out of the way, and the roots of workspaces that were removed from
+/// the repo and from disk are forgotten. Tombstones of forgotten workspaces
+/// (see the `workspace.tombstones` setting) older than the time threshold are
+/// deleted.
 #[derive(clap::Args, Clone, Debug)]
 pub struct UtilGcArgs {
     /// Time threshold
@@ -62,5 +71,22 @@ pub fn cmd_util_gc(
     repo.op_store()
         .gc(slice::from_ref(repo.op_id()), keep_newer)?;
     repo.store().gc(repo.index(), keep_newer)?;
+
+    let workspace_store = workspace_command.workspace_store()?;
+    if !workspace_store.is_read_only() {
+        let options = MaintenanceOptions {
+            repair: true,
+            prune: true,
+            remove_temp_files: true,
+            backfill: Some((
+                workspace_command.workspace_name().to_owned(),
+                workspace_command.workspace_root().to_owned(),
+            )),
+            finish_renames: true,
+            purge_tombstones: Some(keep_newer),
+        };
+        let report = workspace_store::maintain(workspace_store.as_ref(), repo.view(), &options)?;
+        workspace::write_maintenance_report(ui, &report)?;
+    }
     Ok(())
 }
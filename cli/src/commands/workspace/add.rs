@@ -22,11 +22,13 @@ use jj_lib::ref_name::WorkspaceNameBuf;
 use jj_lib::repo::Repo as _;
 use jj_lib::rewrite::merge_commit_trees;
 use jj_lib::workspace::Workspace;
+use jj_lib::workspace_store::WorkspaceStoreError;
 use pollster::FutureExt as _;
 use tracing::instrument;
 
 use crate::cli_util::CommandHelper;
 use crate::cli_util::RevisionArg;
+use crate::cli_util::workspace_store_options;
 use crate::command_error::CommandError;
 use crate::command_error::internal_error_with_message;
 use crate::command_error::user_error;
@@ -58,6 +60,13 @@ pub struct WorkspaceAddArgs {
     /// directory.
     #[arg(long)]
     name: Option<WorkspaceNameBuf>,
+    /// Name the workspace after the basename of the destination directory,
+    /// and fail if a workspace with that name is already recorded in the repo
+    ///
+    /// Without `--name`, the basename is used anyway, but a stale recorded
+    /// path of a forgotten workspace with the same name is silently replaced.
+    #[arg(long, conflicts_with = "name")]
+    name_from_dir: bool,
     /// A list of parent revisions for the working-copy commit of the newly
     /// created workspace. You may specify nothing, or any number of parents.
     ///
@@ -87,18 +96,28 @@ pub fn cmd_workspace_add(
     let destination_path = command.cwd().join(&args.destination);
     if destination_path.exists() {
         return Err(user_error("Workspace already exists"));
-    } else {
-        fs::create_dir(&destination_path).context(&destination_path)?;
     }
-    let workspace_name = if let Some(name) = &args.name {
+    let workspace_name: WorkspaceNameBuf = if let Some(name) = &args.name {
         name.to_owned()
     } else {
-        let file_name = destination_path.file_name().unwrap();
+        let file_name = destination_path.file_name().ok_or_else(|| {
+            user_error(format!(
+                "Cannot derive a workspace name from \"{}\"",
+                args.destination
+            ))
+        })?;
         file_name
             .to_str()
             .ok_or_else(|| user_error("Destination path is not valid UTF-8"))?
             .into()
     };
+    if args.name_from_dir {
+        // Fails if the name can't be stored
+        let workspace_store = old_workspace_command.workspace_store()?;
+        if workspace_store.exists(&workspace_name)? {
+            return Err(WorkspaceStoreError::AlreadyExists(workspace_name).into());
+        }
+    }
     let repo = old_workspace_command.repo();
     if repo.view().get_wc_commit_id(&workspace_name).is_some() {
         return Err(user_error(format!(
@@ -106,18 +125,21 @@ pub fn cmd_workspace_add(
             name = workspace_name.as_symbol()
         )));
     }
+    fs::create_dir(&destination_path).context(&destination_path)?;
 
     let working_copy_factory = command.get_working_copy_factory()?;
     let repo_path = old_workspace_command.repo_path();
     // If we add per-workspace configuration, we'll need to reload settings for
     // the new workspace.
-    let (new_workspace, repo) = Workspace::init_workspace_with_existing_repo(
-        &destination_path,
-        repo_path,
-        repo,
-        working_copy_factory,
-        workspace_name.clone(),
-    )?;
+    let (new_workspace, repo) =
+        Workspace::init_workspace_with_existing_repo_with_workspace_store_options(
+            &destination_path,
+            repo_path,
+            repo,
+            working_copy_factory,
+            workspace_name.clone(),
+            &workspace_store_options(old_workspace_command.settings())?,
+        )?;
     writeln!(
         ui.status(),
         "Created workspace in \"{}\"",
@@ -199,5 +221,18 @@ pub fn cmd_workspace_add(
             name = workspace_name.as_symbol()
         ),
     )?;
+    // The root was recorded before the operation adding the workspace existed.
+    // The operation id is only kept for forensics, so failing to record it
+    // isn't an error.
+    let op_id = new_workspace_command.repo().op_id();
+    if let Err(err) = new_workspace_command
+        .init_workspace_store()
+        .and_then(|store| Ok(store.set_created_at_op_id(&workspace_name, op_id)?))
+    {
+        tracing::warn!(
+            ?err,
+            "failed to record the operation that added the workspace"
+        );
+    }
     Ok(())
 }
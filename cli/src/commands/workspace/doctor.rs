@@ -0,0 +1,186 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use itertools::Itertools as _;
+use jj_lib::workspace_store;
+use jj_lib::workspace_store::Diagnosis;
+use jj_lib::workspace_store::MaintenanceOptions;
+use jj_lib::workspace_store::Workspaces;
+use tracing::instrument;
+
+use super::ProblemClass;
+use crate::cli_util::CommandHelper;
+use crate::command_error::CommandError;
+use crate::command_error::user_error;
+use crate::command_error::user_error_with_hint;
+use crate::formatter::FormatterExt as _;
+use crate::ui::Ui;
+
+/// Check the recorded workspace roots for problems
+///
+/// Reports entries that can't be read, interrupted renames, roots that no
+/// longer exist or aren't workspaces of this repo, workspaces without a
+/// recorded root or without a working-copy commit, roots recorded for more than
+/// one workspace, and names that differ only in case. Fails if any problems
+/// are found.
+#[derive(clap::Args, Clone, Debug)]
+pub struct WorkspaceDoctorArgs {
+    /// Apply the repairs that are safe
+    ///
+    /// Unreadable entries are moved out of the way, interrupted renames are
+    /// finished, and the root of the current workspace is recorded. The roots
+    /// of workspaces that have no working-copy commit and whose directory is
+    /// gone are forgotten after confirmation. The remaining problems are
+    /// reported as without this flag.
+    #[arg(long)]
+    fix: bool,
+    /// Don't ask for confirmation before forgetting roots
+    #[arg(long, requires = "fix")]
+    yes: bool,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_workspace_doctor(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &WorkspaceDoctorArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let workspace_store = if args.fix {
+        workspace_command.init_workspace_store()?
+    } else {
+        workspace_command.workspace_store()?
+    };
+    let view = workspace_command.repo().view();
+    let mut diagnosis = workspace_store.diagnose(view)?;
+
+    if args.fix && !diagnosis.is_empty() {
+        let prunable = diagnosis
+            .missing
+            .iter()
+            .filter(|name| diagnosis.orphans.contains(name))
+            .collect_vec();
+        let prune = if prunable.is_empty() || args.yes {
+            !prunable.is_empty()
+        } else {
+            writeln!(
+                ui.stderr(),
+                "The following workspaces have no working-copy commit and their roots are gone:"
+            )?;
+            for name in &prunable {
+                writeln!(ui.stderr(), "  {}", name.as_symbol())?;
+            }
+            ui.prompt_yes_no("Forget their roots?", None)?
+        };
+        let options = MaintenanceOptions {
+            repair: true,
+            prune,
+            remove_temp_files: true,
+            backfill: Some((
+                workspace_command.workspace_name().to_owned(),
+                workspace_command.workspace_root().to_owned(),
+            )),
+            finish_renames: true,
+            purge_tombstones: None,
+        };
+        let report = workspace_store::maintain(workspace_store.as_ref(), view, &options)?;
+        super::write_maintenance_report(ui, &report)?;
+        if !report.backfilled.is_empty() {
+            writeln!(
+                ui.status(),
+                "Recorded the roots of workspaces: {}",
+                report
+                    .backfilled
+                    .iter()
+                    .map(|name| name.as_symbol())
+                    .join(", ")
+            )?;
+        }
+        diagnosis = workspace_store.diagnose(view)?;
+    }
+
+    if diagnosis.is_empty() {
+        writeln!(ui.status(), "No problems found.")?;
+        return Ok(());
+    }
+    let workspaces = Workspaces::new(workspace_store.as_ref(), view);
+    write_diagnosis(ui, &workspaces, &diagnosis)?;
+    let message = match diagnosis.len() {
+        1 => "Found 1 problem".to_owned(),
+        n => format!("Found {n} problems"),
+    };
+    if args.fix {
+        Err(user_error(message))
+    } else {
+        Err(user_error_with_hint(
+            message,
+            "Run `jj workspace doctor --fix` to apply the safe repairs.",
+        ))
+    }
+}
+
+fn write_diagnosis(
+    ui: &Ui,
+    workspaces: &Workspaces,
+    diagnosis: &Diagnosis,
+) -> Result<(), CommandError> {
+    let mut formatter = ui.stdout_formatter();
+    let mut formatter = formatter.labeled("workspace_doctor");
+    for (class, names) in super::diagnosed_names(diagnosis) {
+        if names.is_empty() {
+            continue;
+        }
+        writeln!(formatter, "{}:", class.heading())?;
+        for name in names {
+            let entry = workspaces.get(name).ok().flatten();
+            match &entry {
+                Some(entry) => writeln!(formatter, "  {entry}")?,
+                None => writeln!(formatter, "  {}", name.as_symbol())?,
+            }
+            // Only orphans can be resolved without knowing more, so the
+            // commands that resolve them are suggested.
+            if class == ProblemClass::Orphan
+                && let Some(path) = entry.and_then(|entry| entry.path)
+            {
+                for suggestion in super::orphan_suggestions(name, &path) {
+                    writeln!(formatter.labeled("hint"), "    {suggestion}")?;
+                }
+            }
+        }
+    }
+    if !diagnosis.duplicate_paths.is_empty() {
+        writeln!(formatter, "{}:", ProblemClass::DuplicatePath.heading())?;
+        for (path, names) in &diagnosis.duplicate_paths {
+            writeln!(
+                formatter,
+                "  {}: {}",
+                path.display(),
+                names.iter().map(|name| name.as_symbol()).join(", ")
+            )?;
+        }
+    }
+    if !diagnosis.case_collisions.is_empty() {
+        writeln!(formatter, "{}:", ProblemClass::CaseCollision.heading())?;
+        for names in &diagnosis.case_collisions {
+            writeln!(
+                formatter,
+                "  {}",
+                names.iter().map(|name| name.as_symbol()).join(", ")
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -12,27 +12,104 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::iter;
+use std::path::Path;
+use std::path::PathBuf;
+
 use clap_complete::ArgValueCandidates;
 use itertools::Itertools as _;
+use jj_lib::backend::CommitId;
 use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::repo::Repo;
+use jj_lib::settings::HumanByteSize;
+use jj_lib::workspace_store::WorkspaceEntry;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
 use tracing::instrument;
 
 use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandTransaction;
+use crate::cli_util::print_updated_commits;
 use crate::command_error::CommandError;
 use crate::command_error::user_error;
+use crate::command_error::user_error_with_message;
 use crate::complete;
 use crate::ui::Ui;
 
 /// Stop tracking a workspace's working-copy commit in the repo
 ///
-/// The workspace will not be touched on disk. It can be deleted from disk
-/// before or after running this command.
+/// The workspace will not be touched on disk unless `--and-delete` is given. It
+/// can be deleted from disk before or after running this command. The recorded
+/// path of the workspace is removed from the repo. If that fails, e.g. because
+/// the repo is on a read-only mount, the workspace is still forgotten and a
+/// warning is shown.
+///
+/// Forgetting another workspace whose directory still exists and whose
+/// working-copy commit has changes requires confirmation, since the changes
+/// can no longer be reached from that directory.
+///
+/// The working-copy commits of the forgotten workspaces are abandoned if
+/// they're empty and have no description, unless `--keep-commits` is given.
+/// `--abandon-commits` abandons them even if they have changes.
+///
+/// Orphans, whose path is recorded but which have no working-copy commit, e.g.
+/// because the operation that added them was undone, can be forgotten too.
+/// Only their recorded path is removed.
 #[derive(clap::Args, Clone, Debug)]
 pub struct WorkspaceForgetArgs {
     /// Names of the workspaces to forget. By default, forgets only the current
     /// workspace.
     #[arg(add = ArgValueCandidates::new(complete::workspaces))]
     workspaces: Vec<WorkspaceNameBuf>,
+    /// Also delete the directories of the workspaces
+    ///
+    /// The current workspace and workspaces containing the repo can't be
+    /// deleted.
+    #[arg(long)]
+    and_delete: bool,
+    /// With `--and-delete`, report about how much disk space was freed
+    ///
+    /// The sizes of the files in each directory are added up before it's
+    /// deleted, which takes a while for large directories. Only the first
+    /// 100,000 files and directories of each directory are counted.
+    #[arg(long, requires = "and_delete")]
+    report_size: bool,
+    /// Don't ask for confirmation before deleting directories or forgetting
+    /// workspaces with changes
+    #[arg(long)]
+    yes: bool,
+    /// Skip unknown workspaces instead of forgetting none of the workspaces
+    ///
+    /// The command still fails if any workspaces were skipped.
+    #[arg(long)]
+    keep_going: bool,
+    /// Also forget the workspaces listed in the given file, or stdin if `-`
+    ///
+    /// The file lists one workspace name per line. Blank lines and lines
+    /// starting with `#` are ignored.
+    #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    from_file: Option<PathBuf>,
+    /// Abandon the working-copy commits of the forgotten workspaces even if
+    /// they have changes
+    ///
+    /// Commits with descendants, and commits that are the working-copy commit
+    /// of another workspace or the target of a bookmark, are kept.
+    #[arg(long, conflicts_with = "keep_commits")]
+    abandon_commits: bool,
+    /// Keep the working-copy commits of the forgotten workspaces even if
+    /// they're empty and have no description
+    #[arg(long)]
+    keep_commits: bool,
+    /// Report the workspaces that had no recorded root, e.g. because they
+    /// were added before roots were recorded
+    #[arg(long)]
+    verbose: bool,
 }
 
 #[instrument(skip_all)]
@@ -43,37 +120,349 @@ pub fn cmd_workspace_forget(
 ) -> Result<(), CommandError> {
     let mut workspace_command = command.workspace_helper(ui)?;
 
-    let wss = if args.workspaces.is_empty() {
-        vec![workspace_command.workspace_name().to_owned()]
-    } else {
-        args.workspaces.clone()
-    };
+    let mut requested_wss = args.workspaces.clone();
+    if let Some(path) = &args.from_file {
+        let text = if path.as_os_str() == "-" {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        } else {
+            fs::read_to_string(path).map_err(|err| {
+                user_error_with_message(format!("Failed to read {}", path.display()), err)
+            })?
+        };
+        requested_wss.extend(parse_workspace_names(&text));
+        if requested_wss.is_empty() {
+            return Err(user_error("No workspaces to forget"));
+        }
+    }
+    if requested_wss.is_empty() {
+        requested_wss.push(workspace_command.workspace_name().to_owned());
+    }
 
-    for ws in &wss {
-        if workspace_command
-            .repo()
-            .view()
-            .get_wc_commit_id(ws)
-            .is_none()
+    let workspace_store = workspace_command.init_workspace_store()?;
+    let workspaces = Workspaces::new(workspace_store.as_ref(), workspace_command.repo().view())
+        .with_current(
+            workspace_command.workspace_name(),
+            workspace_command.workspace_root(),
+        );
+    let repo_path = dunce::canonicalize(workspace_command.repo_path())?;
+    let current_root = dunce::canonicalize(workspace_command.workspace_root())?;
+    let mut wss = vec![];
+    let mut orphans = vec![];
+    let mut skipped_wss = vec![];
+    let mut dirs_to_delete = vec![];
+    let mut changed_entries = vec![];
+    for (ws, entry) in iter::zip(&requested_wss, workspaces.get_many(&requested_wss)?) {
+        let entry = match entry {
+            Some(entry) if entry.wc_commit_id.is_some() || workspace_store.exists(ws)? => entry,
+            _ if args.keep_going => {
+                writeln!(
+                    ui.warning_default(),
+                    "No such workspace: {}",
+                    ws.as_symbol()
+                )?;
+                skipped_wss.push(ws);
+                continue;
+            }
+            _ => return Err(super::no_such_workspace_error(ws)),
+        };
+        if entry.wc_commit_id.is_some() {
+            wss.push(ws.clone());
+        } else {
+            orphans.push(ws.clone());
+        }
+        if args.and_delete {
+            let path = entry
+                .path
+                .as_ref()
+                .ok_or_else(|| WorkspaceStoreError::NotFound(ws.clone()))?;
+            // The recorded root may go through symlinks, which must not hide
+            // that it's the current workspace or contains the repo.
+            let path = match dunce::canonicalize(path) {
+                Ok(path) => path,
+                // Nothing to delete
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(user_error_with_message(
+                        format!(
+                            "Refusing to delete workspace {entry} since its root can't be resolved"
+                        ),
+                        err,
+                    ));
+                }
+            };
+            if path == current_root {
+                return Err(user_error(format!(
+                    "Refusing to delete the current workspace {entry}"
+                )));
+            }
+            if current_root.starts_with(&path) {
+                return Err(user_error(format!(
+                    "Refusing to delete workspace {entry} since it contains the current workspace"
+                )));
+            }
+            if repo_path.starts_with(&path) {
+                return Err(user_error(format!(
+                    "Refusing to delete workspace {entry} since it contains the repo"
+                )));
+            }
+            dirs_to_delete.push(path);
+        } else if ws != workspace_command.workspace_name()
+            && has_live_changes(workspace_command.repo().as_ref(), &entry)?
         {
-            return Err(user_error(format!("No such workspace: {}", ws.as_symbol())));
+            changed_entries.push(entry);
+        }
+    }
+    if !changed_entries.is_empty() && !args.yes {
+        writeln!(
+            ui.stderr(),
+            "The following workspaces have changes in their working copies:"
+        )?;
+        for entry in &changed_entries {
+            writeln!(ui.stderr(), "  {entry}")?;
+        }
+        if !ui.prompt_yes_no("Forget them anyway?", None)? {
+            return Err(user_error("Aborted"));
+        }
+    }
+    if !dirs_to_delete.is_empty() && !args.yes {
+        writeln!(ui.stderr(), "The following directories will be deleted:")?;
+        for path in &dirs_to_delete {
+            writeln!(ui.stderr(), "  {}", path.display())?;
+        }
+        if !ui.prompt_yes_no("Continue?", None)? {
+            return Err(user_error("Aborted"));
         }
     }
 
+    if wss.is_empty() && orphans.is_empty() {
+        return Err(skipped_error(&skipped_wss));
+    }
+
     // bundle every workspace forget into a single transaction, so that e.g.
     // undo correctly restores all of them at once.
-    let mut tx = workspace_command.start_transaction();
-    wss.iter()
-        .try_for_each(|ws| tx.repo_mut().remove_wc_commit(ws))?;
-    let description = if let [ws] = wss.as_slice() {
-        format!("forget workspace {}", ws.as_symbol())
-    } else {
-        format!(
-            "forget workspaces {}",
-            wss.iter().map(|ws| ws.as_symbol()).join(", ")
+    if !wss.is_empty() {
+        let mut tx = workspace_command.start_transaction();
+        if args.abandon_commits || args.keep_commits {
+            for ws in &wss {
+                tx.repo_mut().remove_wc_commit_without_abandoning(ws);
+            }
+        } else {
+            wss.iter()
+                .try_for_each(|ws| tx.repo_mut().remove_wc_commit(ws))?;
+        }
+        if args.abandon_commits {
+            abandon_wc_commits(ui, &mut tx, &wss)?;
+        }
+        super::finish_forget_transaction(ui, tx, &wss)?;
+    }
+
+    // The workspaces are forgotten once the view is updated, so failing to
+    // remove their roots, e.g. because the store is read-only, only leaves
+    // orphaned entries behind. Orphans have nothing else to forget, though.
+    match remove_store_entries(workspace_store.as_ref(), wss.iter().chain(&orphans)) {
+        Ok(absent) => {
+            if args.verbose {
+                for ws in absent {
+                    writeln!(
+                        ui.status(),
+                        "{}: store entry already absent",
+                        ws.as_symbol()
+                    )?;
+                }
+            }
+        }
+        Err(err) if !orphans.is_empty() => return Err(err.into()),
+        Err(err) => {
+            writeln!(
+                ui.warning_default(),
+                "Failed to remove the recorded roots of the forgotten workspaces: {err}"
+            )?;
+            writeln!(
+                ui.hint_default(),
+                "They are listed by `jj workspace list --orphans-only` until they're removed."
+            )?;
+        }
+    }
+
+    let mut freed = DirSize::default();
+    for path in &dirs_to_delete {
+        let size = args.report_size.then(|| dir_size(path));
+        match fs::remove_dir_all(path) {
+            Ok(()) => match size {
+                Some(size) => {
+                    writeln!(ui.status(), "Deleted {} ({size})", path.display())?;
+                    freed.bytes += size.bytes;
+                    freed.complete &= size.complete;
+                }
+                None => writeln!(ui.status(), "Deleted {}", path.display())?,
+            },
+            // Already deleted by the user
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(user_error_with_message(
+                    format!("Failed to delete {}", path.display()),
+                    err,
+                ));
+            }
+        }
+    }
+    if args.report_size {
+        writeln!(ui.status(), "Freed {freed} in total")?;
+    }
+    if !skipped_wss.is_empty() {
+        return Err(skipped_error(&skipped_wss));
+    }
+    Ok(())
+}
+
+/// Maximum number of files and directories [`dir_size()`] looks at.
+const MAX_DIR_SIZE_ENTRIES: usize = 100_000;
+
+/// Approximate disk usage of a directory.
+#[derive(Clone, Copy, Debug)]
+struct DirSize {
+    /// Sum of the sizes of the files.
+    bytes: u64,
+    /// False if some files weren't counted.
+    complete: bool,
+}
+
+impl Default for DirSize {
+    fn default() -> Self {
+        Self {
+            bytes: 0,
+            complete: true,
+        }
+    }
+}
+
+impl std::fmt::Display for DirSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let qualifier = if self.complete { "about" } else { "at least" };
+        write!(f, "{qualifier} {}", HumanByteSize(self.bytes))
+    }
+}
+
+/// Adds up the sizes of the files in `dir` without following symlinks.
+/// Entries that can't be read are skipped.
+fn dir_size(dir: &Path) -> DirSize {
+    let mut size = DirSize::default();
+    let mut visited = 0;
+    let mut pending_dirs = vec![dir.to_owned()];
+    while let Some(dir) = pending_dirs.pop() {
+        let Ok(dir_entries) = dir.read_dir() else {
+            continue;
+        };
+        for dir_entry in dir_entries.flatten() {
+            if visited == MAX_DIR_SIZE_ENTRIES {
+                size.complete = false;
+                return size;
+            }
+            visited += 1;
+            match dir_entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => pending_dirs.push(dir_entry.path()),
+                Ok(metadata) => size.bytes += metadata.len(),
+                Err(_) => {}
+            }
+        }
+    }
+    size
+}
+
+/// Abandons the working-copy commits of the forgotten workspaces unless they
+/// have descendants or are still referenced by another workspace or a bookmark.
+fn abandon_wc_commits(
+    ui: &Ui,
+    tx: &mut WorkspaceCommandTransaction,
+    names: &[WorkspaceNameBuf],
+) -> Result<(), CommandError> {
+    let base_view = tx.base_repo().view();
+    let view = tx.repo().view();
+    let referenced: HashSet<&CommitId> = view
+        .wc_commit_ids()
+        .values()
+        .chain(
+            view.local_bookmarks()
+                .flat_map(|(_, target)| target.added_ids()),
         )
+        .collect();
+    let to_abandon = names
+        .iter()
+        .filter_map(|name| base_view.get_wc_commit_id(name))
+        .filter(|id| !referenced.contains(id) && view.heads().contains(*id))
+        .unique()
+        .cloned()
+        .collect_vec();
+    if to_abandon.is_empty() {
+        return Ok(());
+    }
+    tx.base_workspace_helper().check_rewritable(&to_abandon)?;
+    let abandoned_commits: Vec<_> = to_abandon
+        .iter()
+        .map(|id| tx.base_repo().store().get_commit(id))
+        .try_collect()?;
+    for commit in &abandoned_commits {
+        tx.repo_mut().record_abandoned_commit(commit);
+    }
+    if let Some(mut formatter) = ui.status_formatter() {
+        writeln!(formatter, "Abandoned {} commits:", abandoned_commits.len())?;
+        print_updated_commits(
+            formatter.as_mut(),
+            &tx.base_workspace_helper().commit_summary_template(),
+            &abandoned_commits,
+        )?;
+    }
+    Ok(())
+}
+
+/// Removes the recorded roots of the workspaces. Returns the workspaces that
+/// had none.
+fn remove_store_entries<'a>(
+    workspace_store: &dyn WorkspaceStore,
+    names: impl IntoIterator<Item = &'a WorkspaceNameBuf>,
+) -> Result<Vec<&'a WorkspaceNameBuf>, WorkspaceStoreError> {
+    let mut store_txn = workspace_store.begin()?;
+    let mut absent = vec![];
+    for ws in names {
+        if !store_txn.remove_path(ws)? {
+            // The workspace was probably created before paths were recorded.
+            tracing::info!(?ws, "workspace store entry already absent");
+            absent.push(ws);
+        }
+    }
+    store_txn.finish()?;
+    workspace_store.flush()?;
+    Ok(absent)
+}
+
+/// Returns true if the directory of the workspace is still a checkout and its
+/// working-copy commit has changes. Changes that weren't snapshotted yet can't
+/// be seen from here.
+fn has_live_changes(repo: &dyn Repo, entry: &WorkspaceEntry) -> Result<bool, CommandError> {
+    let (Some(path), Some(wc_commit_id)) = (&entry.path, &entry.wc_commit_id) else {
+        return Ok(false);
     };
+    if !path.join(".jj").join("working_copy").is_dir() {
+        return Ok(false);
+    }
+    let commit = repo.store().get_commit(wc_commit_id)?;
+    Ok(!commit.is_empty(repo)?)
+}
 
-    tx.finish(ui, description)?;
-    Ok(())
+/// Parses a list of workspace names, one per line. Blank lines and comments
+/// are skipped.
+fn parse_workspace_names(text: &str) -> impl Iterator<Item = WorkspaceNameBuf> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(WorkspaceNameBuf::from)
+}
+
+fn skipped_error(skipped_wss: &[&WorkspaceNameBuf]) -> CommandError {
+    user_error(format!(
+        "Skipped unknown workspaces: {}",
+        skipped_wss.iter().map(|ws| ws.as_symbol()).join(", ")
+    ))
 }
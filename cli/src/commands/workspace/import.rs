@@ -0,0 +1,306 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use itertools::Itertools as _;
+use jj_lib::file_util;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use tracing::instrument;
+
+use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandHelper;
+use crate::command_error::CommandError;
+use crate::command_error::user_error;
+use crate::command_error::user_error_with_hint;
+use crate::ui::Ui;
+
+/// Record the roots of existing workspaces in the repo
+///
+/// Each directory is recorded as the root of the workspace named after its
+/// basename, as with `jj workspace add --name-from-dir`. Only the recorded
+/// roots are changed; workspaces that have no working-copy commit in the repo
+/// are orphans until they're added again. Unlike other orphans, their roots
+/// aren't pruned by `jj util gc` or reported by `jj workspace doctor`.
+///
+/// Directories are read from stdin, one per line, e.g.
+/// `find . -name .jj -printf '%h\0' | jj workspace import --stdin-paths -0`.
+/// Relative paths are resolved against the current directory.
+///
+/// With `--from-git-worktrees`, the linked worktrees of the Git repo are
+/// recorded instead, e.g. when migrating a Git repo with several worktrees to
+/// a colocated jj repo. `jj workspace doctor` doesn't report them as foreign
+/// even though they aren't jj workspaces.
+#[derive(clap::Args, Clone, Debug)]
+#[command(group(clap::ArgGroup::new("source").required(true)))]
+pub struct WorkspaceImportArgs {
+    /// Read the directories of the workspaces from stdin
+    #[arg(long, group = "source")]
+    stdin_paths: bool,
+    /// Record the directories of the linked worktrees of the Git repo
+    ///
+    /// The main worktree is skipped. So are worktrees whose directory is gone
+    /// and those whose directory name can't be used as a workspace name, with
+    /// a warning.
+    #[arg(long, group = "source")]
+    from_git_worktrees: bool,
+    /// Expect the directories to be terminated by a NUL character instead of
+    /// a newline
+    #[arg(long, short = '0', requires = "stdin_paths")]
+    null: bool,
+    /// Replace the recorded roots of workspaces that already have a different
+    /// one
+    ///
+    /// By default, nothing is recorded if any workspace already has a
+    /// different root.
+    #[arg(long)]
+    overwrite: bool,
+    /// Only print the workspaces whose roots would be recorded
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_workspace_import(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &WorkspaceImportArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    // A dry run only reads the store.
+    let workspace_store = if args.dry_run {
+        workspace_command.workspace_store()?
+    } else {
+        workspace_command.init_workspace_store()?
+    };
+    let dirs = if args.from_git_worktrees {
+        git_worktree_dirs(ui, &workspace_command)?
+    } else {
+        stdin_dirs(command, args)?
+    };
+
+    let mut dirs_by_name: BTreeMap<WorkspaceNameBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in dirs {
+        let name = match name_from_dir(&path).and_then(|name| {
+            workspace_store.validate_name(&name)?;
+            Ok(name)
+        }) {
+            Ok(name) => name,
+            Err(err) if args.from_git_worktrees => {
+                writeln!(
+                    ui.warning_default(),
+                    "Skipping Git worktree {}: {}",
+                    path.display(),
+                    err.error
+                )?;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        dirs_by_name.entry(name).or_default().push(path);
+    }
+    if dirs_by_name.is_empty() {
+        return Err(user_error("No workspaces to import"));
+    }
+    let duplicates = dirs_by_name
+        .iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect_vec();
+    if !duplicates.is_empty() {
+        for (name, paths) in &duplicates {
+            writeln!(
+                ui.warning_default(),
+                "Directories named after workspace {}:",
+                name.as_symbol()
+            )?;
+            for path in *paths {
+                writeln!(ui.stderr(), "  {}", path.display())?;
+            }
+        }
+        return Err(user_error(
+            "Some directories would be recorded for the same workspace",
+        ));
+    }
+
+    let mut collisions = vec![];
+    let mut unchanged = vec![];
+    for (name, paths) in &dirs_by_name {
+        let path = &paths[0];
+        match workspace_store.try_get_path(name)? {
+            Some(recorded) if is_same_dir(&recorded, path) => unchanged.push(name),
+            Some(recorded) => collisions.push((name, recorded)),
+            None => {}
+        }
+    }
+    if !collisions.is_empty() {
+        let mut stderr = ui.stderr();
+        writeln!(
+            stderr,
+            "The following workspaces already have a different root:"
+        )?;
+        for (name, recorded) in &collisions {
+            writeln!(stderr, "  {}: {}", name.as_symbol(), recorded.display())?;
+        }
+        if !args.overwrite {
+            return Err(user_error_with_hint(
+                "Refusing to replace the recorded roots",
+                "Use --overwrite to replace them.",
+            ));
+        }
+    }
+
+    let names = dirs_by_name
+        .keys()
+        .filter(|name| !unchanged.contains(name))
+        .collect_vec();
+    if args.dry_run {
+        if names.is_empty() {
+            writeln!(ui.status(), "Nothing changed.")?;
+        } else {
+            writeln!(ui.status(), "Would record the roots of workspaces:")?;
+            for name in &names {
+                writeln!(
+                    ui.status(),
+                    "  {}: {}",
+                    name.as_symbol(),
+                    dirs_by_name[*name][0].display()
+                )?;
+            }
+        }
+        return Ok(());
+    }
+    // Workspaces that aren't in the repo are orphans, whose roots are marked
+    // as imported so that `jj util gc` doesn't prune them once their
+    // directories are gone. Git worktrees aren't workspaces of the repo even
+    // if one is named after a workspace, so they're all marked as imported
+    // for `jj workspace doctor` to expect them.
+    let view = workspace_command.repo().view();
+    let orphans = names
+        .iter()
+        .filter(|name| view.get_wc_commit_id(name).is_none())
+        .collect_vec();
+    let mut txn = workspace_store.begin()?;
+    for name in &names {
+        let dir = &dirs_by_name[*name][0];
+        if args.from_git_worktrees || orphans.contains(&name) {
+            txn.set_imported_path(name, dir)?;
+        } else {
+            txn.set_path(name, dir)?;
+        }
+    }
+    txn.finish()?;
+    workspace_store.flush()?;
+    if names.is_empty() {
+        writeln!(ui.status(), "Nothing changed.")?;
+    } else {
+        writeln!(
+            ui.status(),
+            "Recorded the roots of workspaces: {}",
+            names.iter().map(|name| name.as_symbol()).join(", ")
+        )?;
+    }
+    for name in orphans {
+        writeln!(
+            ui.warning_default(),
+            "Workspace {} has no working-copy commit, so it's an orphan",
+            name.as_symbol()
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads the directories given by `--stdin-paths`.
+fn stdin_dirs(
+    command: &CommandHelper,
+    args: &WorkspaceImportArgs,
+) -> Result<Vec<PathBuf>, CommandError> {
+    let mut input = vec![];
+    io::stdin().read_to_end(&mut input)?;
+    let terminator = if args.null { b'\0' } else { b'\n' };
+    let mut dirs = vec![];
+    for bytes in input.split(|&b| b == terminator).filter(|b| !b.is_empty()) {
+        let path = file_util::path_from_bytes(bytes).map_err(user_error)?;
+        let path = file_util::normalize_path(&command.cwd().join(path));
+        if !path.is_dir() {
+            return Err(user_error(format!("Not a directory: {}", path.display())));
+        }
+        dirs.push(path);
+    }
+    Ok(dirs)
+}
+
+/// Returns the directories of the linked worktrees of the Git repo backing the
+/// repo. The main worktree isn't linked, so it isn't included.
+#[cfg(feature = "git")]
+fn git_worktree_dirs(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+) -> Result<Vec<PathBuf>, CommandError> {
+    use jj_lib::repo::Repo as _;
+
+    let git_repo = jj_lib::git::get_git_repo(workspace_command.repo().store())?;
+    let mut dirs = vec![];
+    for worktree in git_repo.worktrees()? {
+        let dir = match worktree.base() {
+            Ok(dir) if dir.is_dir() => dir,
+            _ => {
+                writeln!(
+                    ui.warning_default(),
+                    "Skipping Git worktree {} since its directory is gone",
+                    worktree.id()
+                )?;
+                continue;
+            }
+        };
+        dirs.push(dir);
+    }
+    Ok(dirs)
+}
+
+#[cfg(not(feature = "git"))]
+fn git_worktree_dirs(
+    _ui: &Ui,
+    _workspace_command: &WorkspaceCommandHelper,
+) -> Result<Vec<PathBuf>, CommandError> {
+    Err(user_error("jj was built without Git support"))
+}
+
+/// Derives the name of a workspace from the basename of its directory.
+fn name_from_dir(path: &Path) -> Result<WorkspaceNameBuf, CommandError> {
+    let file_name = path.file_name().ok_or_else(|| {
+        user_error(format!(
+            "Cannot derive a workspace name from \"{}\"",
+            path.display()
+        ))
+    })?;
+    let name = file_name.to_str().ok_or_else(|| {
+        user_error(format!(
+            "Directory name is not valid UTF-8: {}",
+            path.display()
+        ))
+    })?;
+    Ok(name.into())
+}
+
+/// Whether the paths point to the same directory. Paths that can't be
+/// canonicalized, e.g. because they no longer exist, are compared as they are.
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    let canonicalize = |path: &Path| dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    canonicalize(a) == canonicalize(b)
+}
@@ -0,0 +1,200 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use chrono::DateTime;
+use chrono::Local;
+use clap_complete::ArgValueCandidates;
+use jj_lib::backend::Timestamp;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::repo::Repo as _;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
+use tracing::instrument;
+
+use crate::cli_util::CommandHelper;
+use crate::cli_util::short_change_hash;
+use crate::cli_util::short_commit_hash;
+use crate::cli_util::short_operation_hash;
+use crate::command_error::CommandError;
+use crate::command_error::internal_error;
+use crate::command_error::user_error;
+use crate::complete;
+use crate::time_util;
+use crate::ui::Ui;
+
+/// Show everything known about a workspace
+///
+/// Shows the recorded root of the workspace and whether it exists, when and by
+/// whom the root was recorded, the operation that added the workspace, and the
+/// working-copy commit of the workspace.
+#[derive(clap::Args, Clone, Debug)]
+pub struct WorkspaceInfoArgs {
+    /// The workspace to show. By default, shows the current workspace.
+    #[arg(add = ArgValueCandidates::new(complete::workspaces))]
+    workspace: Option<WorkspaceNameBuf>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = InfoFormat::Text)]
+    format: InfoFormat,
+}
+
+/// Format for the `--format` argument option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum InfoFormat {
+    /// One `Key: value` line per field
+    Text,
+    /// A JSON object. Unknown fields are `null`.
+    Json,
+}
+
+/// JSON representation of a workspace. Scripts rely on this schema, so only add
+/// fields to it.
+#[derive(serde::Serialize)]
+struct WorkspaceInfoJson {
+    name: String,
+    path: Option<String>,
+    exists: bool,
+    path_recorded: Option<String>,
+    recorded_by_version: Option<String>,
+    owner_uid: Option<u32>,
+    created_at_op_id: Option<String>,
+    working_copy: Option<WorkingCopyJson>,
+}
+
+#[derive(serde::Serialize)]
+struct WorkingCopyJson {
+    change_id: String,
+    commit_id: String,
+    description: String,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_workspace_info(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &WorkspaceInfoArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let repo = workspace_command.repo();
+    let workspace_store = workspace_command.workspace_store()?;
+    let mut workspaces = Workspaces::new(workspace_store.as_ref(), repo.view());
+    if command.is_cwd_repo()? {
+        workspaces = workspaces.with_current(
+            workspace_command.workspace_name(),
+            workspace_command.workspace_root(),
+        );
+    }
+    let name = args
+        .workspace
+        .as_deref()
+        .unwrap_or(workspace_command.workspace_name());
+    let overview = workspaces
+        .overview(name, repo.store())?
+        .ok_or_else(|| user_error(format!("No such workspace: {}", name.as_symbol())))?;
+
+    let exists = overview.exists;
+    let path_recorded = match workspace_store.entry_mtime(name) {
+        Ok(mtime) => Some(DateTime::<Local>::from(mtime)),
+        // The workspace predates the store
+        Err(WorkspaceStoreError::NotFound(_)) => None,
+        Err(err) => return Err(err.into()),
+    };
+    let (recorded_by_version, owner_uid, created_at_op_id) =
+        match workspace_store.created_by_version(name) {
+            Ok(version) => (
+                version,
+                workspace_store.created_by_uid(name)?,
+                workspace_store.created_at_op_id(name)?,
+            ),
+            Err(WorkspaceStoreError::NotFound(_)) => (None, None, None),
+            Err(err) => return Err(err.into()),
+        };
+    let wc_commit = overview
+        .wc_commit_id
+        .as_ref()
+        .map(|id| repo.store().get_commit(id))
+        .transpose()?;
+
+    if args.format == InfoFormat::Json {
+        let info = WorkspaceInfoJson {
+            name: name.as_str().to_owned(),
+            path: overview
+                .path
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            exists,
+            path_recorded: path_recorded.map(|time| time.to_rfc3339()),
+            recorded_by_version,
+            owner_uid,
+            created_at_op_id: created_at_op_id.as_ref().map(|id| id.hex()),
+            working_copy: wc_commit.map(|commit| WorkingCopyJson {
+                change_id: commit.change_id().reverse_hex(),
+                commit_id: commit.id().hex(),
+                description: commit.description().to_owned(),
+            }),
+        };
+        let text = serde_json::to_string_pretty(&info).map_err(internal_error)?;
+        writeln!(ui.stdout(), "{text}")?;
+        return Ok(());
+    }
+
+    let mut formatter = ui.stdout_formatter();
+    writeln!(formatter, "Name: {}", name.as_symbol())?;
+    match &overview.path {
+        Some(path) => writeln!(formatter, "Path: {}", path.display())?,
+        None => writeln!(formatter, "Path: (unknown)")?,
+    }
+    writeln!(formatter, "Exists: {}", if exists { "yes" } else { "no" })?;
+    if let Some(time) = path_recorded {
+        let timestamp = Timestamp::from_datetime(time);
+        writeln!(
+            formatter,
+            "Path recorded: {}",
+            time_util::format_absolute_timestamp(&timestamp).map_err(internal_error)?
+        )?;
+    }
+    if let Some(version) = &recorded_by_version {
+        writeln!(formatter, "Recorded by: jj {version}")?;
+    }
+    if let Some(uid) = owner_uid {
+        writeln!(formatter, "Owner: uid {uid}")?;
+    }
+    if let Some(op_id) = &created_at_op_id {
+        writeln!(
+            formatter,
+            "Added at operation: {}",
+            short_operation_hash(op_id)
+        )?;
+    }
+    match &wc_commit {
+        Some(commit) => {
+            writeln!(
+                formatter,
+                "Working copy: {} {}",
+                short_change_hash(commit.change_id()),
+                short_commit_hash(commit.id())
+            )?;
+            let description = commit.description().lines().next().unwrap_or_default();
+            if description.is_empty() {
+                writeln!(formatter, "Description: (no description set)")?;
+            } else {
+                writeln!(formatter, "Description: {description}")?;
+            }
+        }
+        None => writeln!(formatter, "Working copy: (no working-copy commit)")?,
+    }
+    Ok(())
+}
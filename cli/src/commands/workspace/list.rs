@@ -12,20 +12,82 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io;
+use std::io::Write as _;
+use std::iter;
+use std::path::Path;
+use std::slice;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use chrono::DateTime;
+use chrono::Local;
 use clap_complete::ArgValueCandidates;
-use jj_lib::repo::Repo as _;
+use itertools::Itertools as _;
+use jj_lib::backend::Timestamp;
+use jj_lib::config::ConfigSource;
+use jj_lib::file_util;
+use jj_lib::op_store::OperationId;
+use jj_lib::op_walk;
+use jj_lib::ref_name::WorkspaceName;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::repo::Repo;
+use jj_lib::settings::UserSettings;
+use jj_lib::store::Store;
+use jj_lib::str_util::StringPattern;
+use jj_lib::time_util::DatePattern;
+use jj_lib::workspace_store::PathStatus;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
 use tracing::instrument;
+use unicode_width::UnicodeWidthStr as _;
 
+use super::ProblemClass;
 use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandHelper;
 use crate::command_error::CommandError;
+use crate::command_error::internal_error;
+use crate::command_error::user_error;
+use crate::command_error::user_error_with_exit_code;
 use crate::commit_templater::WorkspaceRef;
 use crate::complete;
+use crate::formatter::Formatter;
+use crate::formatter::FormatterExt as _;
 use crate::templater::TemplateRenderer;
+use crate::text_util;
+use crate::time_util;
 use crate::ui::Ui;
 
 /// List workspaces
+///
+/// By default, all workspaces with a working-copy commit are listed, along with
+/// orphans: workspaces whose path is recorded in the repo but which have no
+/// working-copy commit, e.g. because the operation that added them was undone.
+/// `--no-orphans` leaves the orphans out. `--exists-only` keeps only the
+/// workspaces whose directory exists, and `--missing` only those whose
+/// directory is gone.
+///
+/// If names are given, only the workspaces whose name matches one of them are
+/// listed. The names are glob patterns, e.g. `jj workspace list 'feature-*'`.
+///
+/// When the output is a terminal, workspaces are shown as a table by default,
+/// unless `templates.workspace_list` is configured. Otherwise, each workspace
+/// is rendered using the `templates.workspace_list` template.
 #[derive(clap::Args, Clone, Debug)]
 pub struct WorkspaceListArgs {
+    /// Only list workspaces whose name matches one of the given [wildcard
+    /// patterns]
+    ///
+    /// [wildcard patterns]:
+    ///     https://jj-vcs.github.io/jj/latest/revsets/#string-patterns
+    #[arg(value_parser = StringPattern::glob, add = ArgValueCandidates::new(complete::workspaces))]
+    names: Vec<StringPattern>,
     /// Render each workspace using the given template
     ///
     /// All 0-argument methods of the [`WorkspaceRef` type] are available as
@@ -39,6 +101,317 @@ pub struct WorkspaceListArgs {
     ///     https://jj-vcs.github.io/jj/latest/templates/
     #[arg(long, short = 'T', add = ArgValueCandidates::new(complete::template_aliases))]
     template: Option<String>,
+    /// Output format
+    ///
+    /// `table` shows the name, path, state, and last use of each workspace in
+    /// aligned columns that fit the terminal width. `plain` renders each
+    /// workspace using the template, which is better suited for scripts. The
+    /// default is `table` if the output is a terminal or the pager, or is
+    /// colored with `--color=always`, and `plain` otherwise.
+    ///
+    /// `shell` prints a `JJ_WS_<name>='<path>'` assignment for each workspace
+    /// with a known root, so that `eval "$(jj workspace list --format=shell)"`
+    /// lets you `cd "$JJ_WS_feature"`. Characters of the name other than ASCII
+    /// letters, digits, and `_` are replaced with `_`. If that makes the
+    /// variable names of workspaces collide, `_2`, `_3`, etc. is appended to
+    /// all but the first, in the order the workspaces are listed.
+    #[arg(long, value_enum, conflicts_with_all = ["template", "long"])]
+    format: Option<ListFormat>,
+    /// Print the workspaces as a JSON document
+    ///
+    /// The document is an object with a `schema` key, currently `1`, and an
+    /// `entries` key holding an array of objects with `name`, `path`,
+    /// `exists`, and `orphan` keys. `path` is `null` if the root is unknown.
+    /// Scripts can rely on this schema: fields are only added to it, and other
+    /// changes increase `schema`.
+    #[arg(long, conflicts_with_all = ["template", "format", "long", "group_by"])]
+    json: bool,
+    /// With `--json`, also print a `stats` object with the `total`, `missing`,
+    /// and `orphaned` number of workspaces
+    ///
+    /// The numbers count all listed workspaces, including those skipped by
+    /// `--offset` or `--limit`.
+    #[arg(long, requires = "json")]
+    stats: bool,
+    /// Limit number of workspaces to show
+    ///
+    /// Applied after workspaces are sorted and `--offset` workspaces are
+    /// skipped.
+    #[arg(long, short = 'n')]
+    limit: Option<usize>,
+    /// Skip the given number of workspaces
+    #[arg(long, default_value_t = 0)]
+    offset: usize,
+    /// Also show when the recorded path of each workspace was last changed,
+    /// and by which version of jj and which user
+    #[arg(long)]
+    long: bool,
+    /// Only list workspaces whose directory exists
+    #[arg(long)]
+    exists_only: bool,
+    /// Only list workspaces whose recorded directory no longer exists
+    #[arg(long, conflicts_with = "exists_only")]
+    missing: bool,
+    /// Forget the listed workspaces, like `jj workspace forget`
+    ///
+    /// All listed workspaces are forgotten in one operation, and their
+    /// recorded paths are removed. The recorded paths of listed orphans are
+    /// removed too, like `jj util gc` does. Requires `--missing` and `--yes`.
+    #[arg(long, visible_alias = "forget", requires = "missing", requires = "yes")]
+    delete: bool,
+    /// Don't ask for confirmation. Required by `--delete`.
+    #[arg(long)]
+    yes: bool,
+    /// List workspaces that have a recorded path but no working-copy commit
+    ///
+    /// This is the default. It can be given to override an earlier
+    /// `--no-orphans`.
+    #[arg(long, overrides_with = "no_orphans")]
+    include_orphans: bool,
+    /// Don't list workspaces that have a recorded path but no working-copy
+    /// commit
+    #[arg(long, overrides_with = "include_orphans")]
+    no_orphans: bool,
+    /// Only list workspaces that were added after the given operation
+    ///
+    /// Workspaces added by an operation that isn't an ancestor of the given
+    /// one are listed. For workspaces whose adding operation wasn't recorded,
+    /// the time their path was recorded is compared with the time of the
+    /// operation instead. Workspaces without a recorded path aren't listed.
+    #[arg(long, value_name = "OPERATION", add = ArgValueCandidates::new(complete::operations))]
+    since: Option<String>,
+    /// Only list workspaces that haven't been used for the given duration,
+    /// e.g. `30d` or `2 weeks`
+    ///
+    /// A workspace was last used when its working copy was last updated, or,
+    /// if that can't be determined, e.g. because its directory is gone, when
+    /// its path was recorded. Workspaces without either aren't listed. The
+    /// time since the last use is shown for each workspace. Instead of a
+    /// duration, a [date] such as `2024-01-01` can be given to list the
+    /// workspaces that haven't been used since then.
+    ///
+    /// [date]: https://jj-vcs.github.io/jj/latest/revsets/#date-patterns
+    #[arg(long, value_name = "DURATION", value_parser = super::parse_last_used_before)]
+    dead_for: Option<DatePattern>,
+    /// Sort workspaces by the given key
+    ///
+    /// `mtime` lists the workspaces whose path was recorded most recently
+    /// first, and workspaces without a recorded path last. Workspaces with the
+    /// same time are sorted by name. This looks up the time of each workspace,
+    /// which takes longer when there are many workspaces.
+    #[arg(long, value_name = "SORT_KEY", value_enum, default_value_t = SortKey::Name)]
+    sort: SortKey,
+    /// Group workspaces by the given key
+    ///
+    /// `fs` groups workspaces by the file system their directory is on: the
+    /// device on Unix, and the drive on Windows. Workspaces whose file system
+    /// can't be determined, e.g. because their directory doesn't exist, are
+    /// listed last. Within each group, workspaces are sorted as usual.
+    #[arg(long, value_name = "KEY", value_enum)]
+    group_by: Option<GroupBy>,
+    /// Only print the recorded paths of the workspaces, one per line
+    ///
+    /// All workspaces with a recorded path are considered, including orphans,
+    /// sorted by name. Workspaces whose directory no longer exists are skipped
+    /// unless `--include-missing` is given. This is meant for scripts, e.g.
+    /// `for dir in $(jj workspace list --paths-only); do ...; done`.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "template",
+            "format",
+            "long",
+            "exists_only",
+            "missing",
+            "delete",
+            "include_orphans",
+            "no_orphans",
+            "sort",
+            "group_by",
+            "since",
+            "dead_for",
+            "json",
+        ],
+    )]
+    paths_only: bool,
+    /// With `--paths-only`, also print the paths of workspaces whose directory
+    /// no longer exists
+    #[arg(long, requires = "paths_only")]
+    include_missing: bool,
+    /// With `--paths-only`, terminate each path with a NUL character instead
+    /// of a newline
+    #[arg(long, requires = "paths_only")]
+    null: bool,
+    /// Only list orphans, along with the commands that resolve them
+    ///
+    /// Orphans are workspaces whose path is recorded but which have no
+    /// working-copy commit. Each one can be forgotten, and one whose directory
+    /// is gone can be added again.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "template",
+            "format",
+            "long",
+            "exists_only",
+            "missing",
+            "delete",
+            "include_orphans",
+            "no_orphans",
+            "sort",
+            "group_by",
+            "paths_only",
+            "since",
+            "dead_for",
+            "json",
+        ],
+    )]
+    orphans_only: bool,
+    /// Check the workspace store for problems, for use in CI
+    ///
+    /// Finds the same problems as `jj workspace doctor`, and prints each
+    /// affected workspace as a line of tab-separated fields: the class of the
+    /// problem and the workspace name. `duplicate-path` lines have the
+    /// recorded root as third field. The classes, with the exit code used if
+    /// it's the first class found, are:
+    ///
+    /// * `corrupt` (10): the entry can't be read
+    ///
+    /// * `interrupted-rename` (11): a rename of the workspace was interrupted
+    ///
+    /// * `missing` (12): the recorded root no longer exists
+    ///
+    /// * `foreign` (13): the recorded root isn't a workspace of this repo
+    ///
+    /// * `unrecorded` (14): the workspace has no recorded root
+    ///
+    /// * `orphan` (15): the workspace has no working-copy commit
+    ///
+    /// * `duplicate-path` (16): the root is recorded for more than one
+    ///   workspace
+    ///
+    /// * `case-collision` (17): the name differs from another only in case
+    ///
+    /// The exit code is 0 if no problems are found.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "names",
+            "template",
+            "format",
+            "long",
+            "exists_only",
+            "missing",
+            "delete",
+            "include_orphans",
+            "no_orphans",
+            "sort",
+            "group_by",
+            "paths_only",
+            "orphans_only",
+            "since",
+            "dead_for",
+            "json",
+            "limit",
+        ],
+    )]
+    check: bool,
+}
+
+impl WorkspaceListArgs {
+    /// Whether the workspace is selected by the name patterns, if any.
+    fn is_selected(&self, name: &WorkspaceName) -> bool {
+        self.names.is_empty()
+            || self
+                .names
+                .iter()
+                .any(|pattern| pattern.is_match(name.as_str()))
+    }
+
+    /// Whether `--offset` and `--limit` can be applied to the sorted names
+    /// right away, so that only the shown workspaces are looked up. Filters
+    /// and orderings that depend on the entries, and `--stats`, which counts
+    /// all listed workspaces, need every workspace to be looked up first.
+    fn pages_by_name(&self) -> bool {
+        self.since.is_none()
+            && self.dead_for.is_none()
+            && !self.exists_only
+            && !self.missing
+            && self.sort == SortKey::Name
+            && self.group_by.is_none()
+            && !self.stats
+    }
+
+    /// Skips `--offset` items and takes up to `--limit` of the rest.
+    fn page<T>(&self, items: impl IntoIterator<Item = T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Like [`Self::page()`], unless the names were already paged when the
+    /// workspaces were selected.
+    fn page_selected<T>(&self, items: impl IntoIterator<Item = T>) -> Vec<T> {
+        if self.pages_by_name() {
+            items.into_iter().collect()
+        } else {
+            self.page(items)
+        }
+    }
+}
+
+/// Sort key for the `--sort` argument option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum SortKey {
+    /// Sort by workspace name
+    Name,
+    /// Sort by the time the workspace path was last recorded, newest first
+    Mtime,
+}
+
+/// Format for the `--format` argument option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum ListFormat {
+    /// Render each workspace using the template
+    Plain,
+    /// Show workspaces in aligned columns
+    Table,
+    /// Print shell variable assignments
+    Shell,
+}
+
+/// JSON document printed by `--json`. Scripts rely on this schema, so only add
+/// fields to it, or increase `schema` otherwise.
+#[derive(serde::Serialize)]
+struct WorkspaceListJson {
+    schema: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<WorkspaceStatsJson>,
+    entries: Vec<WorkspaceEntryJson>,
+}
+
+#[derive(serde::Serialize)]
+struct WorkspaceStatsJson {
+    total: usize,
+    missing: usize,
+    orphaned: usize,
+}
+
+#[derive(serde::Serialize)]
+struct WorkspaceEntryJson {
+    name: String,
+    path: Option<String>,
+    exists: bool,
+    orphan: bool,
+}
+
+/// Key for the `--group-by` argument option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum GroupBy {
+    /// Group by the file system of the workspace directory
+    Fs,
 }
 
 #[instrument(skip_all)]
@@ -47,7 +420,36 @@ pub fn cmd_workspace_list(
     command: &CommandHelper,
     args: &WorkspaceListArgs,
 ) -> Result<(), CommandError> {
-    let workspace_command = command.workspace_helper(ui)?;
+    if args.check {
+        return check_workspaces(ui, command);
+    }
+    let mut workspace_command = command.workspace_helper(ui)?;
+    if !args.names.is_empty() {
+        let workspace_store = workspace_command.workspace_store()?;
+        warn_unmatched_patterns(
+            ui,
+            &workspace_command,
+            workspace_store.as_ref(),
+            &args.names,
+        )?;
+    }
+    if args.paths_only {
+        let workspace_store = workspace_command.workspace_store()?;
+        // The root of the current workspace is known even if it isn't recorded.
+        let current = (command.is_cwd_repo()? && workspace_command.get_wc_commit_id().is_some())
+            .then(|| {
+                (
+                    workspace_command.workspace_name(),
+                    workspace_command.workspace_root(),
+                )
+            });
+        return write_paths(ui, workspace_store.as_ref(), current, args);
+    }
+    if args.orphans_only {
+        let workspace_store = workspace_command.workspace_store()?;
+        let workspaces = Workspaces::new(workspace_store.as_ref(), workspace_command.repo().view());
+        return write_orphans(ui, &workspaces, args);
+    }
 
     let template: TemplateRenderer<WorkspaceRef> = {
         let language = workspace_command.commit_template_language();
@@ -64,15 +466,647 @@ pub fn cmd_workspace_list(
             .labeled(["workspace_list"])
     };
 
+    let repo = workspace_command.repo().clone();
+    let workspace_store = if args.delete {
+        workspace_command.init_workspace_store()?
+    } else {
+        workspace_command.workspace_store()?
+    };
+    let mut workspaces = Workspaces::new(workspace_store.as_ref(), repo.view());
+    // The root of the current workspace is known even if it isn't recorded.
+    if command.is_cwd_repo()? {
+        workspaces = workspaces.with_current(
+            workspace_command.workspace_name(),
+            workspace_command.workspace_root(),
+        );
+    }
+    let (names, last_used_times) = select_workspaces(
+        &workspace_command,
+        workspace_store.as_ref(),
+        &workspaces,
+        args,
+    )?;
+    let shown_names = if args.json {
+        write_json(ui, repo.store(), &workspaces, &names, args)?
+    } else {
+        let (group_labels, names) = if args.group_by == Some(GroupBy::Fs) {
+            group_by_file_system(&workspaces, names)?
+        } else {
+            (vec![None; names.len()], names)
+        };
+        let shown = args.page_selected(iter::zip(names, group_labels));
+        let format = args.format.unwrap_or_else(|| {
+            if args.template.is_none()
+                && !args.long
+                && !has_configured_template(workspace_command.settings())
+                && ui.stdout_is_interactive()
+            {
+                ListFormat::Table
+            } else {
+                ListFormat::Plain
+            }
+        });
+        match format {
+            ListFormat::Shell => write_shell(ui, &workspaces, shown)?,
+            ListFormat::Table => {
+                write_overview_table(ui, repo.store(), &workspaces, shown, &last_used_times)?
+            }
+            ListFormat::Plain => write_templated(
+                ui,
+                repo.as_ref(),
+                &template,
+                workspace_store.as_ref(),
+                &workspaces,
+                shown,
+                &last_used_times,
+                args,
+            )?,
+        }
+    };
+    drop(template);
+
+    let forgotten_names = if args.delete { shown_names } else { vec![] };
+    super::forget_workspaces(
+        ui,
+        &mut workspace_command,
+        workspace_store.as_ref(),
+        &forgotten_names,
+    )
+}
+
+/// Whether `templates.workspace_list` is set in a layer other than the default
+/// config.
+fn has_configured_template(settings: &UserSettings) -> bool {
+    settings.config().layers().iter().any(|layer| {
+        layer.source != ConfigSource::Default
+            && matches!(layer.look_up_item("templates.workspace_list"), Ok(Some(_)))
+    })
+}
+
+/// Returns the names of the workspaces selected by the arguments, in the order
+/// they're listed, and the last use times of those that were selected by
+/// `--dead-for`. If the arguments allow, only the page of names selected by
+/// `--offset` and `--limit` is returned, see
+/// [`WorkspaceListArgs::pages_by_name()`].
+fn select_workspaces(
+    workspace_command: &WorkspaceCommandHelper,
+    workspace_store: &dyn WorkspaceStore,
+    workspaces: &Workspaces,
+    args: &WorkspaceListArgs,
+) -> Result<(Vec<WorkspaceNameBuf>, HashMap<WorkspaceNameBuf, SystemTime>), CommandError> {
     let repo = workspace_command.repo();
+    let mut names = repo.view().wc_commit_ids().keys().cloned().collect_vec();
+    if !args.no_orphans {
+        names.extend(workspaces.orphans()?);
+        names.sort();
+    }
+    names.retain(|name| args.is_selected(name));
+    if args.pages_by_name() {
+        return Ok((args.page(names), HashMap::new()));
+    }
+    if let Some(op_str) = &args.since {
+        let since_op = workspace_command.resolve_single_op(op_str)?;
+        let later_op_ids: HashSet<OperationId> = op_walk::walk_ancestors_range(
+            slice::from_ref(repo.operation()),
+            slice::from_ref(&since_op),
+        )
+        .map_ok(|op| op.id().clone())
+        .try_collect()?;
+        let since_millis = since_op.metadata().time.end.timestamp.0;
+        let since_time = UNIX_EPOCH + Duration::from_millis(since_millis.try_into().unwrap_or(0));
+        let mut added_names = vec![];
+        for name in names {
+            let is_added = match workspace_store.created_at_op_id(&name) {
+                Ok(Some(op_id)) => later_op_ids.contains(&op_id),
+                Ok(None) => workspace_store.entry_mtime(&name)? > since_time,
+                Err(WorkspaceStoreError::NotFound(_)) => false,
+                Err(err) => return Err(err.into()),
+            };
+            if is_added {
+                added_names.push(name);
+            }
+        }
+        names = added_names;
+    }
+    let mut last_used_times = HashMap::new();
+    if let Some(dead_for) = args.dead_for {
+        for name in &names {
+            if let Some(time) = super::last_activity_time(workspaces, workspace_store, name)?
+                && dead_for.matches(&Timestamp::from_datetime(DateTime::<Local>::from(time)))
+            {
+                last_used_times.insert(name.clone(), time);
+            }
+        }
+        names.retain(|name| last_used_times.contains_key(name));
+    }
+    if args.exists_only {
+        let mut existing_names = vec![];
+        for name in names {
+            if workspaces.path_status(&name)? == PathStatus::Exists {
+                existing_names.push(name);
+            }
+        }
+        names = existing_names;
+    }
+    if args.missing {
+        let missing_names: HashSet<_> = workspaces.missing()?.into_iter().collect();
+        names.retain(|name| missing_names.contains(name));
+    }
+    if args.sort == SortKey::Mtime {
+        let mut mtimes = HashMap::new();
+        for name in &names {
+            match workspace_store.entry_mtime(name) {
+                Ok(mtime) => {
+                    mtimes.insert(name.clone(), mtime);
+                }
+                Err(WorkspaceStoreError::NotFound(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        // Workspaces without a recorded path come last
+        names.sort_by(|a, b| {
+            let a_key = (Reverse(mtimes.get(a)), a);
+            let b_key = (Reverse(mtimes.get(b)), b);
+            a_key.cmp(&b_key)
+        });
+    }
+    Ok((names, last_used_times))
+}
+
+/// Orders the workspaces by the file system of their directories for
+/// `--group-by=fs`. Returns the label of the file system of each workspace
+/// along with the reordered names.
+fn group_by_file_system(
+    workspaces: &Workspaces,
+    names: Vec<WorkspaceNameBuf>,
+) -> Result<(Vec<Option<String>>, Vec<WorkspaceNameBuf>), CommandError> {
+    let mut labeled_names = vec![];
+    for name in names {
+        let path = workspaces.get(&name)?.and_then(|entry| entry.path);
+        labeled_names.push((path.as_deref().and_then(file_system_label), name));
+    }
+    // Without any known file system, there's nothing to group by.
+    if labeled_names.iter().all(|(label, _)| label.is_none()) {
+        return Ok(labeled_names.into_iter().unzip());
+    }
+    labeled_names.sort_by_key(|(label, _)| (label.is_none(), label.clone()));
+    Ok(labeled_names
+        .into_iter()
+        .map(|(label, name)| {
+            let label = label.unwrap_or_else(|| "an unknown file system".to_owned());
+            (Some(label), name)
+        })
+        .unzip())
+}
+
+/// Prints shell variable assignments for `--format=shell`. Returns the names
+/// of the shown workspaces.
+fn write_shell(
+    ui: &Ui,
+    workspaces: &Workspaces,
+    shown: Vec<(WorkspaceNameBuf, Option<String>)>,
+) -> Result<Vec<WorkspaceNameBuf>, CommandError> {
+    let mut var_names = HashSet::new();
+    let mut shown_names = vec![];
+    for (name, _) in shown {
+        if let Some(path) = workspaces.get(&name)?.and_then(|entry| entry.path) {
+            let path = path.to_str().ok_or_else(|| {
+                user_error(format!(
+                    "Root of workspace {} is not valid UTF-8",
+                    name.as_symbol()
+                ))
+            })?;
+            let var_name = shell_var_name(&name, &var_names);
+            writeln!(ui.stdout(), "{var_name}={}", super::shell_quote(path))?;
+            var_names.insert(var_name);
+        }
+        shown_names.push(name);
+    }
+    Ok(shown_names)
+}
+
+/// Prints the workspaces as a table for `--format=table`. Returns the names of
+/// the shown workspaces.
+fn write_overview_table(
+    ui: &Ui,
+    store: &Arc<Store>,
+    workspaces: &Workspaces,
+    shown: Vec<(WorkspaceNameBuf, Option<String>)>,
+    last_used_times: &HashMap<WorkspaceNameBuf, SystemTime>,
+) -> Result<Vec<WorkspaceNameBuf>, CommandError> {
+    let mut rows = vec![];
+    let mut shown_names = vec![];
+    for (name, group_label) in shown {
+        // Removed from the store since it was listed
+        if let Some(overview) = workspaces.overview(&name, store)? {
+            let state = if overview.wc_commit_id.is_none() {
+                "orphan"
+            } else if overview.exists {
+                "present"
+            } else if overview.path.is_some() {
+                "missing"
+            } else {
+                "unknown"
+            };
+            let path = overview.path;
+            let time = last_used_times
+                .get(&name)
+                .copied()
+                .or_else(|| path.as_deref().and_then(super::last_used_time));
+            let last_used = match time {
+                Some(time) => super::format_age(time)?,
+                None => "-".to_owned(),
+            };
+            let row = TableRow {
+                name: name.as_symbol().to_string(),
+                path: path.map_or_else(|| "-".to_owned(), |path| path.display().to_string()),
+                state,
+                last_used,
+            };
+            rows.push((row, group_label));
+        }
+        shown_names.push(name);
+    }
+    let term_width = ui.term_width();
+    write_table(ui.stdout_formatter().as_mut(), &rows, term_width)?;
+    Ok(shown_names)
+}
+
+/// Renders each workspace using the template for `--format=plain`. Returns the
+/// names of the shown workspaces.
+#[expect(clippy::too_many_arguments)]
+fn write_templated(
+    ui: &Ui,
+    repo: &dyn Repo,
+    template: &TemplateRenderer<WorkspaceRef>,
+    workspace_store: &dyn WorkspaceStore,
+    workspaces: &Workspaces,
+    shown: Vec<(WorkspaceNameBuf, Option<String>)>,
+    last_used_times: &HashMap<WorkspaceNameBuf, SystemTime>,
+    args: &WorkspaceListArgs,
+) -> Result<Vec<WorkspaceNameBuf>, CommandError> {
     let mut formatter = ui.stdout_formatter();
+    let mut shown_names = vec![];
+
+    // Only the commits of the shown workspaces are looked up
+    let mut last_group_label = None;
+    for (name, group_label) in shown {
+        if let Some(label) = &group_label
+            && group_label != last_group_label
+        {
+            writeln!(
+                formatter.labeled("workspace_list").labeled("heading"),
+                "Workspaces on {label}:"
+            )?;
+            last_group_label = group_label;
+        }
+        if let Some(wc_commit_id) = repo.view().get_wc_commit_id(&name) {
+            let commit = repo.store().get_commit(wc_commit_id)?;
+            let ws_ref = WorkspaceRef::new(name.clone(), commit);
+            template.format(&ws_ref, formatter.as_mut())?;
+        } else if let Some(entry) = workspaces.get(&name)? {
+            // An orphan, whose path is recorded but whose working-copy commit
+            // isn't
+            let mut formatter = formatter.labeled("workspace_list");
+            write!(formatter, "{entry}: ")?;
+            writeln!(formatter.labeled("placeholder"), "(no working-copy commit)")?;
+        }
+        if let Some(&time) = last_used_times.get(&name) {
+            let mut formatter = formatter.labeled("workspace_list");
+            write!(formatter, "  Last used: ")?;
+            writeln!(
+                formatter.labeled("timestamp"),
+                "{}",
+                super::format_age(time)?
+            )?;
+        }
+        if args.long {
+            write_recorded_details(formatter.as_mut(), workspace_store, &name)?;
+        }
+        shown_names.push(name);
+    }
+    Ok(shown_names)
+}
+
+/// Prints what the store recorded about the workspace for `--long`.
+fn write_recorded_details(
+    formatter: &mut dyn Formatter,
+    workspace_store: &dyn WorkspaceStore,
+    name: &WorkspaceName,
+) -> Result<(), CommandError> {
+    let mut formatter = formatter.labeled("workspace_list");
+    match workspace_store.entry_mtime(name) {
+        Ok(mtime) => {
+            let timestamp = Timestamp::from_datetime(DateTime::<Local>::from(mtime));
+            write!(formatter, "  Path recorded: ")?;
+            writeln!(
+                formatter.labeled("timestamp"),
+                "{}",
+                time_util::format_absolute_timestamp(&timestamp).map_err(internal_error)?
+            )?;
+        }
+        // The workspace predates the store
+        Err(WorkspaceStoreError::NotFound(_)) => {}
+        Err(err) => return Err(err.into()),
+    }
+    match workspace_store.created_by_version(name) {
+        Ok(Some(version)) => writeln!(formatter, "  Recorded by: jj {version}")?,
+        Ok(None) | Err(WorkspaceStoreError::NotFound(_)) => {}
+        Err(err) => return Err(err.into()),
+    }
+    match workspace_store.created_by_uid(name) {
+        Ok(Some(uid)) => writeln!(formatter, "  Owner: uid {uid}")?,
+        Ok(None) | Err(WorkspaceStoreError::NotFound(_)) => {}
+        Err(err) => return Err(err.into()),
+    }
+    Ok(())
+}
+
+/// Prints the workspaces as a JSON document for `--json`. Returns the names of
+/// the workspaces in the `entries` array.
+fn write_json(
+    ui: &Ui,
+    store: &Arc<Store>,
+    workspaces: &Workspaces,
+    names: &[WorkspaceNameBuf],
+    args: &WorkspaceListArgs,
+) -> Result<Vec<WorkspaceNameBuf>, CommandError> {
+    let mut overviews = vec![];
+    for name in names {
+        // Removed from the store since it was listed
+        if let Some(overview) = workspaces.overview(name, store)? {
+            overviews.push(overview);
+        }
+    }
+    let stats = args.stats.then(|| WorkspaceStatsJson {
+        total: overviews.len(),
+        missing: overviews
+            .iter()
+            .filter(|overview| overview.path.is_some() && !overview.exists)
+            .count(),
+        orphaned: overviews
+            .iter()
+            .filter(|overview| overview.wc_commit_id.is_none())
+            .count(),
+    });
+    let shown = args.page_selected(overviews);
+    let document = WorkspaceListJson {
+        schema: 1,
+        stats,
+        entries: shown
+            .iter()
+            .map(|overview| WorkspaceEntryJson {
+                name: overview.name.as_str().to_owned(),
+                path: overview
+                    .path
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned()),
+                exists: overview.exists,
+                orphan: overview.wc_commit_id.is_none(),
+            })
+            .collect(),
+    };
+    let text = serde_json::to_string_pretty(&document).map_err(internal_error)?;
+    writeln!(ui.stdout(), "{text}")?;
+    Ok(shown.into_iter().map(|overview| overview.name).collect())
+}
+
+/// Returns the shell variable name for the workspace, which must not be one of
+/// `taken`.
+fn shell_var_name(name: &WorkspaceName, taken: &HashSet<String>) -> String {
+    let sanitized: String = name
+        .as_str()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let var_name = format!("JJ_WS_{sanitized}");
+    if !taken.contains(&var_name) {
+        return var_name;
+    }
+    (2..)
+        .map(|n| format!("{var_name}_{n}"))
+        .find(|var_name| !taken.contains(var_name))
+        .unwrap()
+}
+
+/// Prints the recorded paths of the workspaces for `--paths-only`.
+fn write_paths(
+    ui: &Ui,
+    workspace_store: &dyn WorkspaceStore,
+    current: Option<(&WorkspaceName, &Path)>,
+    args: &WorkspaceListArgs,
+) -> Result<(), CommandError> {
+    let terminator = if args.null { b'\0' } else { b'\n' };
+    let mut paths = workspace_store.get_all_paths()?;
+    if let Some((name, root)) = current
+        && let Err(index) = paths.binary_search_by(|(other, _)| (**other).cmp(name))
+    {
+        paths.insert(index, (name.to_owned(), root.to_owned()));
+    }
+    let mut stdout = ui.stdout();
+    for (_, path) in paths
+        .into_iter()
+        .filter(|(name, _)| args.is_selected(name))
+        .filter(|(_, path)| args.include_missing || path.is_dir())
+        .skip(args.offset)
+        .take(args.limit.unwrap_or(usize::MAX))
+    {
+        stdout.write_all(file_util::path_to_bytes(&path).map_err(user_error)?)?;
+        stdout.write_all(&[terminator])?;
+    }
+    Ok(())
+}
 
-    for (name, wc_commit_id) in repo.view().wc_commit_ids() {
-        let commit = repo.store().get_commit(wc_commit_id)?;
-        let ws_ref = WorkspaceRef::new(name.clone(), commit);
+/// Warns about the name patterns that match neither a workspace in the view
+/// nor a recorded root.
+fn warn_unmatched_patterns(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    workspace_store: &dyn WorkspaceStore,
+    patterns: &[StringPattern],
+) -> Result<(), CommandError> {
+    let view = workspace_command.repo().view();
+    let mut names = view.wc_commit_ids().keys().cloned().collect_vec();
+    names.extend(workspace_store.list()?);
+    let unmatched = patterns
+        .iter()
+        .filter(|pattern| !names.iter().any(|name| pattern.is_match(name.as_str())))
+        .collect_vec();
+    if !unmatched.is_empty() {
+        writeln!(
+            ui.warning_default(),
+            "No matching workspaces for patterns: {}",
+            unmatched.iter().join(", ")
+        )?;
+    }
+    Ok(())
+}
 
-        template.format(&ws_ref, formatter.as_mut())?;
+/// Prints the orphans and the commands resolving them for `--orphans-only`.
+fn write_orphans(
+    ui: &Ui,
+    workspaces: &Workspaces,
+    args: &WorkspaceListArgs,
+) -> Result<(), CommandError> {
+    let mut formatter = ui.stdout_formatter();
+    let mut formatter = formatter.labeled("workspace_list");
+    for name in workspaces
+        .orphans()?
+        .into_iter()
+        .filter(|name| args.is_selected(name))
+        .skip(args.offset)
+        .take(args.limit.unwrap_or(usize::MAX))
+    {
+        // Removed since it was listed
+        let Some(entry) = workspaces.get(&name)? else {
+            continue;
+        };
+        write!(formatter, "{entry}: ")?;
+        writeln!(formatter.labeled("placeholder"), "(no working-copy commit)")?;
+        if let Some(path) = &entry.path {
+            for suggestion in super::orphan_suggestions(&name, path) {
+                writeln!(formatter.labeled("hint"), "  {suggestion}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_workspaces(ui: &Ui, command: &CommandHelper) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let workspace_store = workspace_command.workspace_store()?;
+    let diagnosis = workspace_store.diagnose(workspace_command.repo().view())?;
+    let Some(first_class) = super::first_problem_class(&diagnosis) else {
+        writeln!(ui.status(), "No problems found.")?;
+        return Ok(());
+    };
+    let mut formatter = ui.stdout_formatter();
+    for (class, names) in super::diagnosed_names(&diagnosis) {
+        for name in names {
+            writeln!(formatter, "{}\t{}", class.key(), name.as_str())?;
+        }
     }
+    for (path, names) in &diagnosis.duplicate_paths {
+        for name in names {
+            writeln!(
+                formatter,
+                "{}\t{}\t{}",
+                ProblemClass::DuplicatePath.key(),
+                name.as_str(),
+                path.display()
+            )?;
+        }
+    }
+    for names in &diagnosis.case_collisions {
+        for name in names {
+            writeln!(
+                formatter,
+                "{}\t{}",
+                ProblemClass::CaseCollision.key(),
+                name.as_str()
+            )?;
+        }
+    }
+    drop(formatter);
+    let message = match diagnosis.len() {
+        1 => "Found 1 problem".to_owned(),
+        n => format!("Found {n} problems"),
+    };
+    Err(user_error_with_exit_code(message, first_class.exit_code()))
+}
 
+struct TableRow {
+    name: String,
+    path: String,
+    state: &'static str,
+    last_used: String,
+}
+
+/// Writes the rows in aligned columns. The path column is shortened in the
+/// middle so that the lines fit in `term_width` if possible.
+fn write_table(
+    formatter: &mut dyn Formatter,
+    rows: &[(TableRow, Option<String>)],
+    term_width: usize,
+) -> io::Result<()> {
+    const HEADER: [&str; 4] = ["Name", "Path", "State", "Last used"];
+    const SEPARATOR: &str = "  ";
+    let column_width = |header: &str, cell: fn(&TableRow) -> &str| {
+        rows.iter()
+            .map(|(row, _)| cell(row).width())
+            .chain([header.len()])
+            .max()
+            .unwrap()
+    };
+    let name_width = column_width(HEADER[0], |row| &row.name);
+    let state_width = column_width(HEADER[2], |row| row.state);
+    let last_used_width = column_width(HEADER[3], |row| &row.last_used);
+    let other_width = name_width + state_width + last_used_width + 3 * SEPARATOR.len();
+    let path_width = column_width(HEADER[1], |row| &row.path)
+        .min(term_width.saturating_sub(other_width))
+        .max(HEADER[1].len());
+
+    let format_row = |[name, path, state, last_used]: [&str; 4]| {
+        let (path, _) = text_util::elide_middle(path, "…", path_width);
+        [
+            (name, name_width),
+            (&path, path_width),
+            (state, state_width),
+        ]
+        .into_iter()
+        .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.width())))
+        .chain([last_used.to_owned()])
+        .join(SEPARATOR)
+    };
+    writeln!(
+        formatter.labeled("workspace_list").labeled("heading"),
+        "{}",
+        format_row(HEADER)
+    )?;
+    let mut last_group_label = None;
+    for (row, group_label) in rows {
+        if group_label.is_some() && group_label.as_ref() != last_group_label {
+            let label = group_label.as_ref().unwrap();
+            writeln!(
+                formatter.labeled("workspace_list").labeled("heading"),
+                "Workspaces on {label}:"
+            )?;
+            last_group_label = group_label.as_ref();
+        }
+        writeln!(
+            formatter.labeled("workspace_list"),
+            "{}",
+            format_row([&row.name, &row.path, row.state, &row.last_used])
+        )?;
+    }
     Ok(())
 }
+
+/// Returns a description of the file system containing `path`, or `None` if it
+/// can't be determined.
+#[cfg(unix)]
+fn file_system_label(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt as _;
+    let metadata = path.metadata().ok()?;
+    Some(format!("device {}", metadata.dev()))
+}
+
+#[cfg(windows)]
+fn file_system_label(path: &Path) -> Option<String> {
+    use std::path::Component;
+    use std::path::Prefix;
+    match path.components().next()? {
+        Component::Prefix(prefix) => match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+                Some(format!("drive {}", char::from(letter)))
+            }
+            _ => Some(prefix.as_os_str().to_string_lossy().into_owned()),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn file_system_label(_path: &Path) -> Option<String> {
+    None
+}
@@ -13,29 +13,68 @@
 // limitations under the License.
 
 mod add;
+mod doctor;
 mod forget;
+mod import;
+mod info;
 mod list;
+mod prune;
 mod rename;
 mod root;
 mod update_stale;
+mod whose_dir;
 
+use std::borrow::Cow;
+use std::io;
+use std::io::Write as _;
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::DateTime;
+use chrono::Local;
 use clap::Subcommand;
+use itertools::Itertools as _;
+use jj_lib::backend::Timestamp;
+use jj_lib::ref_name::WorkspaceName;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::time_util::DatePattern;
+use jj_lib::time_util::DatePatternParseError;
+use jj_lib::workspace_store::Diagnosis;
+use jj_lib::workspace_store::MaintenanceReport;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
 use tracing::instrument;
 
 use self::add::WorkspaceAddArgs;
 use self::add::cmd_workspace_add;
+use self::doctor::WorkspaceDoctorArgs;
+use self::doctor::cmd_workspace_doctor;
 use self::forget::WorkspaceForgetArgs;
 use self::forget::cmd_workspace_forget;
+use self::import::WorkspaceImportArgs;
+use self::import::cmd_workspace_import;
+use self::info::WorkspaceInfoArgs;
+use self::info::cmd_workspace_info;
 use self::list::WorkspaceListArgs;
 use self::list::cmd_workspace_list;
+use self::prune::WorkspacePruneArgs;
+use self::prune::cmd_workspace_prune;
 use self::rename::WorkspaceRenameArgs;
 use self::rename::cmd_workspace_rename;
 use self::root::WorkspaceRootArgs;
 use self::root::cmd_workspace_root;
 use self::update_stale::WorkspaceUpdateStaleArgs;
 use self::update_stale::cmd_workspace_update_stale;
+use self::whose_dir::WorkspaceWhoseDirArgs;
+use self::whose_dir::cmd_workspace_whose_dir;
 use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandHelper;
+use crate::cli_util::WorkspaceCommandTransaction;
 use crate::command_error::CommandError;
+use crate::command_error::internal_error;
+use crate::command_error::user_error;
+use crate::time_util;
 use crate::ui::Ui;
 
 /// Commands for working with workspaces
@@ -52,11 +91,16 @@ use crate::ui::Ui;
 #[derive(Subcommand, Clone, Debug)]
 pub(crate) enum WorkspaceCommand {
     Add(WorkspaceAddArgs),
+    Doctor(WorkspaceDoctorArgs),
     Forget(WorkspaceForgetArgs),
+    Import(WorkspaceImportArgs),
+    Info(WorkspaceInfoArgs),
     List(WorkspaceListArgs),
+    Prune(WorkspacePruneArgs),
     Rename(WorkspaceRenameArgs),
     Root(WorkspaceRootArgs),
     UpdateStale(WorkspaceUpdateStaleArgs),
+    WhoseDir(WorkspaceWhoseDirArgs),
 }
 
 #[instrument(skip_all)]
@@ -67,10 +111,299 @@ pub(crate) fn cmd_workspace(
 ) -> Result<(), CommandError> {
     match subcommand {
         WorkspaceCommand::Add(args) => cmd_workspace_add(ui, command, args),
+        WorkspaceCommand::Doctor(args) => cmd_workspace_doctor(ui, command, args),
         WorkspaceCommand::Forget(args) => cmd_workspace_forget(ui, command, args),
+        WorkspaceCommand::Import(args) => cmd_workspace_import(ui, command, args),
+        WorkspaceCommand::Info(args) => cmd_workspace_info(ui, command, args),
         WorkspaceCommand::List(args) => cmd_workspace_list(ui, command, args),
+        WorkspaceCommand::Prune(args) => cmd_workspace_prune(ui, command, args),
         WorkspaceCommand::Rename(args) => cmd_workspace_rename(ui, command, args),
         WorkspaceCommand::Root(args) => cmd_workspace_root(ui, command, args),
         WorkspaceCommand::UpdateStale(args) => cmd_workspace_update_stale(ui, command, args),
+        WorkspaceCommand::WhoseDir(args) => cmd_workspace_whose_dir(ui, command, args),
+    }
+}
+
+/// Hint for errors about workspaces that neither the view nor the workspace
+/// store knows.
+const NO_SUCH_WORKSPACE_HINT: &str = "Run `jj workspace list` to see the available workspaces.";
+
+/// Returns the error for a workspace that neither the view nor the workspace
+/// store knows.
+fn no_such_workspace_error(name: &WorkspaceName) -> CommandError {
+    user_error(format!("No such workspace: {}", name.as_symbol())).hinted(NO_SUCH_WORKSPACE_HINT)
+}
+
+/// Maximum number of workspace names listed in a forget operation description.
+const MAX_FORGET_DESCRIPTION_NAMES: usize = 3;
+
+/// Finishes a transaction forgetting the given workspaces.
+///
+/// The description lists at most a few names so that `jj op log` stays
+/// readable after forgetting many workspaces. The full list is recorded in the
+/// operation's `workspaces` tag when the description is truncated.
+fn finish_forget_transaction(
+    ui: &Ui,
+    mut tx: WorkspaceCommandTransaction,
+    names: &[WorkspaceNameBuf],
+) -> Result<(), CommandError> {
+    if names.len() > MAX_FORGET_DESCRIPTION_NAMES {
+        let all_names = names.iter().map(|name| name.as_symbol()).join(", ");
+        tx.set_tag("workspaces".to_owned(), all_names);
+    }
+    tx.finish(ui, forget_description(names))
+}
+
+fn forget_description(names: &[WorkspaceNameBuf]) -> String {
+    match names {
+        [name] => format!("forget workspace {}", name.as_symbol()),
+        _ if names.len() <= MAX_FORGET_DESCRIPTION_NAMES => format!(
+            "forget workspaces {}",
+            names.iter().map(|name| name.as_symbol()).join(", ")
+        ),
+        _ => format!(
+            "forget {} workspaces ({}, …)",
+            names.len(),
+            names
+                .iter()
+                .take(MAX_FORGET_DESCRIPTION_NAMES)
+                .map(|name| name.as_symbol())
+                .join(", ")
+        ),
+    }
+}
+
+/// Prints the repairs done by
+/// [`maintain()`](jj_lib::workspace_store::maintain).
+pub(crate) fn write_maintenance_report(ui: &Ui, report: &MaintenanceReport) -> io::Result<()> {
+    if !report.repaired.is_empty() {
+        writeln!(
+            ui.status(),
+            "Moved unreadable workspace store entries of: {}",
+            report
+                .repaired
+                .iter()
+                .map(|name| name.as_symbol())
+                .join(", ")
+        )?;
+    }
+    if !report.finished_renames.is_empty() {
+        writeln!(
+            ui.status(),
+            "Finished interrupted renames of workspaces: {}",
+            report
+                .finished_renames
+                .iter()
+                .map(|name| name.as_symbol())
+                .join(", ")
+        )?;
+    }
+    if !report.pruned.is_empty() {
+        writeln!(
+            ui.status(),
+            "Forgot the roots of removed workspaces: {}",
+            report.pruned.iter().map(|name| name.as_symbol()).join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+/// Class of problems found by
+/// [`WorkspaceStore::diagnose()`](jj_lib::workspace_store::WorkspaceStore::diagnose),
+/// in the order they're reported by `jj workspace doctor` and
+/// `jj workspace list --check`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ProblemClass {
+    Corrupt,
+    InterruptedRename,
+    Missing,
+    Foreign,
+    Unrecorded,
+    Orphan,
+    DuplicatePath,
+    CaseCollision,
+}
+
+impl ProblemClass {
+    /// Heading of the problems in the output of `jj workspace doctor`.
+    fn heading(self) -> &'static str {
+        match self {
+            Self::Corrupt => "Unreadable entries",
+            Self::InterruptedRename => "Interrupted renames",
+            Self::Missing => "Missing roots",
+            Self::Foreign => "Roots that aren't workspaces of this repo",
+            Self::Unrecorded => "Workspaces without a recorded root",
+            Self::Orphan => "Workspaces without a working-copy commit",
+            Self::DuplicatePath => "Roots recorded for more than one workspace",
+            Self::CaseCollision => "Names that differ only in case",
+        }
+    }
+
+    /// Key of the problems in the output of `jj workspace list --check`.
+    fn key(self) -> &'static str {
+        match self {
+            Self::Corrupt => "corrupt",
+            Self::InterruptedRename => "interrupted-rename",
+            Self::Missing => "missing",
+            Self::Foreign => "foreign",
+            Self::Unrecorded => "unrecorded",
+            Self::Orphan => "orphan",
+            Self::DuplicatePath => "duplicate-path",
+            Self::CaseCollision => "case-collision",
+        }
     }
+
+    /// Exit code of `jj workspace list --check` if this is the first class of
+    /// problems found. Documented there, so these must not change.
+    fn exit_code(self) -> u8 {
+        match self {
+            Self::Corrupt => 10,
+            Self::InterruptedRename => 11,
+            Self::Missing => 12,
+            Self::Foreign => 13,
+            Self::Unrecorded => 14,
+            Self::Orphan => 15,
+            Self::DuplicatePath => 16,
+            Self::CaseCollision => 17,
+        }
+    }
+}
+
+/// Returns the workspaces of each class of problems that affect single
+/// workspaces, in the order they're reported.
+fn diagnosed_names(diagnosis: &Diagnosis) -> [(ProblemClass, &[WorkspaceNameBuf]); 6] {
+    [
+        (ProblemClass::Corrupt, &diagnosis.corrupt),
+        (
+            ProblemClass::InterruptedRename,
+            &diagnosis.interrupted_renames,
+        ),
+        (ProblemClass::Missing, &diagnosis.missing),
+        (ProblemClass::Foreign, &diagnosis.foreign),
+        (ProblemClass::Unrecorded, &diagnosis.unrecorded),
+        (ProblemClass::Orphan, &diagnosis.orphans),
+    ]
+}
+
+/// Returns the first class of problems in the diagnosis, if any.
+fn first_problem_class(diagnosis: &Diagnosis) -> Option<ProblemClass> {
+    diagnosed_names(diagnosis)
+        .into_iter()
+        .find(|(_, names)| !names.is_empty())
+        .map(|(class, _)| class)
+        .or_else(|| (!diagnosis.duplicate_paths.is_empty()).then_some(ProblemClass::DuplicatePath))
+        .or_else(|| (!diagnosis.case_collisions.is_empty()).then_some(ProblemClass::CaseCollision))
+}
+
+/// Returns the commands that resolve an orphan, a workspace whose path is
+/// recorded but which has no working-copy commit. `path` is the recorded root
+/// of the workspace.
+fn orphan_suggestions(name: &WorkspaceName, path: &Path) -> Vec<String> {
+    let name = shell_quote(name.as_str());
+    let mut commands = vec![format!("jj workspace forget {name}")];
+    // A workspace can only be added in a directory that doesn't exist yet.
+    if !path.exists() {
+        let path = path.to_string_lossy();
+        commands.push(format!(
+            "jj workspace add --name {name} {}",
+            shell_quote(&path)
+        ));
+    }
+    commands
+}
+
+/// Quotes `arg` for POSIX shells if it contains special characters.
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+/// Parses the time before which workspaces must have been last used, for the
+/// argument options that select workspaces by age. A duration such as `30d` or
+/// `2 weeks` means that long ago. Anything else is parsed like the dates of the
+/// `committer_date()` revset function, e.g. `2 weeks ago` or `2024-01-01`.
+fn parse_last_used_before(s: &str) -> Result<DatePattern, DatePatternParseError> {
+    let now = Local::now();
+    DatePattern::from_str_kind(&format!("{s} ago"), "before", now)
+        .or_else(|_| DatePattern::from_str_kind(s, "before", now))
+}
+
+/// Forgets the workspaces in one operation and removes their recorded paths.
+fn forget_workspaces(
+    ui: &mut Ui,
+    workspace_command: &mut WorkspaceCommandHelper,
+    workspace_store: &dyn WorkspaceStore,
+    names: &[WorkspaceNameBuf],
+) -> Result<(), CommandError> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    // Orphans only have a recorded path
+    let view = workspace_command.repo().view();
+    let tracked_names = names
+        .iter()
+        .filter(|name| view.get_wc_commit_id(name).is_some())
+        .cloned()
+        .collect_vec();
+    if !tracked_names.is_empty() {
+        let mut tx = workspace_command.start_transaction();
+        for name in &tracked_names {
+            tx.repo_mut().remove_wc_commit(name)?;
+        }
+        finish_forget_transaction(ui, tx, &tracked_names)?;
+    }
+
+    let mut store_txn = workspace_store.begin()?;
+    for name in names {
+        store_txn.remove_path(name)?;
+    }
+    store_txn.finish()?;
+    workspace_store.flush()?;
+    writeln!(
+        ui.status(),
+        "Forgot {} workspace{}",
+        names.len(),
+        if names.len() == 1 { "" } else { "s" }
+    )?;
+    Ok(())
+}
+
+/// Returns when the working copy of the workspace at `root` was last updated,
+/// or `None` if it can't be determined.
+fn last_used_time(root: &Path) -> Option<SystemTime> {
+    root.join(".jj")
+        .join("working_copy")
+        .read_dir()
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}
+
+/// Returns when the workspace was last used, or when its path was recorded if
+/// that can't be determined.
+fn last_activity_time(
+    workspaces: &Workspaces,
+    workspace_store: &dyn WorkspaceStore,
+    name: &WorkspaceName,
+) -> Result<Option<SystemTime>, CommandError> {
+    let path = workspaces.get(name)?.and_then(|entry| entry.path);
+    if let Some(time) = path.as_deref().and_then(last_used_time) {
+        return Ok(Some(time));
+    }
+    match workspace_store.entry_mtime(name) {
+        Ok(mtime) => Ok(Some(mtime)),
+        Err(WorkspaceStoreError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Formats the time relative to now, e.g. "3 weeks ago".
+fn format_age(time: SystemTime) -> Result<String, CommandError> {
+    let timestamp = Timestamp::from_datetime(DateTime::<Local>::from(time));
+    time_util::format_duration(&timestamp, &Timestamp::now(), &timeago::Formatter::new())
+        .map_err(internal_error)
 }
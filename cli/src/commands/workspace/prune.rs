@@ -0,0 +1,190 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::io;
+use std::io::Write as _;
+
+use chrono::DateTime;
+use chrono::Local;
+use itertools::Itertools as _;
+use jj_lib::backend::Timestamp;
+use jj_lib::ref_name::WorkspaceName;
+use jj_lib::time_util::DatePattern;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
+use tracing::instrument;
+
+use crate::cli_util::CommandHelper;
+use crate::command_error::CommandError;
+use crate::command_error::user_error;
+#[cfg(unix)]
+use crate::command_error::user_error_with_message;
+use crate::ui::Ui;
+
+/// Forget workspaces whose directories are gone or that are no longer used
+///
+/// Workspaces whose recorded root no longer exists are forgotten. With
+/// `--older-than`, workspaces that haven't been used for the given duration
+/// are forgotten too. A workspace was last used when its working copy was last
+/// updated, or when its root was recorded if that can't be determined. Since
+/// the directories of these workspaces may still exist, forgetting them
+/// requires confirmation.
+///
+/// The current workspace is never pruned, and no directories are deleted. All
+/// the workspaces are forgotten in one operation, so `jj undo` restores them.
+#[derive(clap::Args, Clone, Debug)]
+pub struct WorkspacePruneArgs {
+    /// Also forget workspaces that haven't been used for the given duration,
+    /// e.g. `30d` or `2 weeks`, or since the given [date]
+    ///
+    /// [date]: https://jj-vcs.github.io/jj/latest/revsets/#date-patterns
+    #[arg(long, value_name = "DURATION", value_parser = super::parse_last_used_before)]
+    older_than: Option<DatePattern>,
+    /// Only forget workspaces whose root was recorded by the given user
+    ///
+    /// The user is given by name or numeric user id. Workspaces whose owner
+    /// isn't recorded are kept. This is only supported on Unix.
+    #[arg(long, value_name = "USER")]
+    owner: Option<String>,
+    /// Don't ask for confirmation before forgetting workspaces by age
+    #[arg(long)]
+    yes: bool,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_workspace_prune(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &WorkspacePruneArgs,
+) -> Result<(), CommandError> {
+    let owner_uid = args.owner.as_deref().map(look_up_uid).transpose()?;
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let workspace_store = workspace_command.init_workspace_store()?;
+    let is_selected = |name: &WorkspaceName| -> Result<bool, WorkspaceStoreError> {
+        let Some(uid) = owner_uid else {
+            return Ok(true);
+        };
+        match workspace_store.created_by_uid(name) {
+            Ok(created_by_uid) => Ok(created_by_uid == Some(uid)),
+            Err(WorkspaceStoreError::NotFound(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    };
+    let repo = workspace_command.repo().clone();
+    let current_name = workspace_command.workspace_name().to_owned();
+    let workspaces = Workspaces::new(workspace_store.as_ref(), repo.view())
+        .with_current(&current_name, workspace_command.workspace_root());
+    let mut missing = vec![];
+    for name in workspaces.missing()? {
+        if name != current_name && is_selected(&name)? {
+            missing.push(name);
+        }
+    }
+    let mut aged = vec![];
+    if let Some(older_than) = args.older_than {
+        for name in workspaces.all()? {
+            if name == current_name || missing.contains(&name) || !is_selected(&name)? {
+                continue;
+            }
+            if let Some(time) =
+                super::last_activity_time(&workspaces, workspace_store.as_ref(), &name)?
+                && older_than.matches(&Timestamp::from_datetime(DateTime::<Local>::from(time)))
+            {
+                aged.push((name, super::format_age(time)?));
+            }
+        }
+    }
+    if missing.is_empty() && aged.is_empty() {
+        writeln!(ui.status(), "No workspaces to prune.")?;
+        return Ok(());
+    }
+
+    if !aged.is_empty() && !args.yes {
+        writeln!(
+            ui.stderr(),
+            "The following workspaces haven't been used recently:"
+        )?;
+        for (name, age) in &aged {
+            writeln!(ui.stderr(), "  {} (last used {age})", name.as_symbol())?;
+        }
+        if !ui.prompt_yes_no("Forget them?", None)? {
+            return Err(user_error("Aborted"));
+        }
+    }
+
+    let names = missing
+        .iter()
+        .chain(aged.iter().map(|(name, _)| name))
+        .cloned()
+        .collect_vec();
+    super::forget_workspaces(ui, &mut workspace_command, workspace_store.as_ref(), &names)?;
+    for name in &missing {
+        writeln!(ui.status(), "  {}: directory is missing", name.as_symbol())?;
+    }
+    for (name, age) in &aged {
+        writeln!(ui.status(), "  {}: last used {age}", name.as_symbol())?;
+    }
+    Ok(())
+}
+
+/// Looks up the id of the user with the given name, or parses the numeric id.
+#[cfg(unix)]
+fn look_up_uid(user: &str) -> Result<u32, CommandError> {
+    let c_user =
+        CString::new(user).map_err(|_| user_error(format!("Invalid user name: {user}")))?;
+    // Safety: `passwd` is plain data, which getpwnam_r() fills in.
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    let mut result = std::ptr::null_mut();
+    loop {
+        // Safety: the name is NUL-terminated, and the strings of `passwd` are
+        // written to `buf`, which outlives them.
+        let rc = unsafe {
+            libc::getpwnam_r(
+                c_user.as_ptr(),
+                &mut passwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match rc {
+            0 => break,
+            libc::ERANGE => buf.resize(buf.len() * 2, 0),
+            errno => {
+                return Err(user_error_with_message(
+                    format!("Failed to look up user {user}"),
+                    io::Error::from_raw_os_error(errno),
+                ));
+            }
+        }
+    }
+    if !result.is_null() {
+        Ok(passwd.pw_uid)
+    } else if let Ok(uid) = user.parse() {
+        Ok(uid)
+    } else {
+        Err(user_error(format!("No such user: {user}")))
+    }
+}
+
+#[cfg(not(unix))]
+fn look_up_uid(_user: &str) -> Result<u32, CommandError> {
+    Err(user_error(
+        "Workspace owners are only recorded on Unix, so --owner isn't supported",
+    ))
+}
@@ -12,12 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use jj_lib::ref_name::WorkspaceName;
 use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreError;
 use tracing::instrument;
 
 use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandHelper;
 use crate::command_error::CommandError;
 use crate::command_error::user_error;
+use crate::command_error::user_error_with_message;
 use crate::ui::Ui;
 
 /// Renames the current workspace
@@ -25,6 +35,20 @@ use crate::ui::Ui;
 pub struct WorkspaceRenameArgs {
     /// The name of the workspace to update to.
     new_workspace_name: WorkspaceNameBuf,
+    /// Also rename the workspace directory if it's named after the workspace
+    ///
+    /// The directory is renamed within its parent directory and its recorded
+    /// root is updated. If the directory can't be renamed, the workspace keeps
+    /// its old name. Workspaces containing the repo can't be moved, since the
+    /// other workspaces refer to the repo by its path.
+    #[arg(long)]
+    update_dir: bool,
+    /// Replace the recorded path of a workspace of the new name
+    ///
+    /// Renaming fails if a path is recorded for the new name, e.g. because the
+    /// operation that added a workspace of that name was undone.
+    #[arg(long)]
+    force: bool,
 }
 
 #[instrument(skip_all)]
@@ -58,19 +82,201 @@ pub fn cmd_workspace_rename(
         )));
     }
 
+    // Loaded before the rename, since the root of the current workspace is
+    // recorded under its loaded name if it has no entry.
+    let workspace_store = workspace_command.init_workspace_store()?;
+    let old_root = workspace_command.workspace_root().to_owned();
+    let new_root = if args.update_dir {
+        let new_root = new_workspace_dir(&workspace_command, &old_name, new_name)?;
+        if new_root.is_none() {
+            writeln!(
+                ui.warning_default(),
+                "Not moving {} since it isn't named after the workspace",
+                old_root.display()
+            )?;
+        }
+        new_root
+    } else {
+        None
+    };
+
+    if let Some(new_root) = &new_root {
+        move_dir(&old_root, new_root).map_err(|err| {
+            user_error_with_message(
+                format!(
+                    "Failed to move {} to {}",
+                    old_root.display(),
+                    new_root.display()
+                ),
+                err,
+            )
+        })?;
+        // The working copy is renamed where it was moved to
+        let settings = workspace_command.settings().clone();
+        let repo = workspace_command.repo().clone();
+        match command
+            .load_workspace_at(new_root, &settings)
+            .and_then(|workspace| command.for_workable_repo(ui, workspace, repo))
+        {
+            Ok(moved_workspace_command) => workspace_command = moved_workspace_command,
+            Err(err) => return Err(move_back(err, &old_root, new_root)),
+        }
+    }
+
+    let result = rename_workspace(
+        &mut workspace_command,
+        workspace_store.as_ref(),
+        &old_name,
+        new_name,
+        args.update_dir.then_some(&old_root),
+        args.force,
+    );
+    if let Err(err) = result {
+        return Err(match &new_root {
+            Some(new_root) => move_back(err, &old_root, new_root),
+            None => err,
+        });
+    }
+    if let Some(new_root) = &new_root {
+        writeln!(ui.status(), "Moved workspace to {}", new_root.display())?;
+    }
+
+    Ok(())
+}
+
+/// Renames the workspace in the view, the working copy, and the workspace
+/// store. If the workspace was moved from `old_root`, its new root is recorded
+/// too. The operation is only committed once the store is updated, so the
+/// workspace keeps its name if that fails.
+fn rename_workspace(
+    workspace_command: &mut WorkspaceCommandHelper,
+    workspace_store: &dyn WorkspaceStore,
+    old_name: &WorkspaceName,
+    new_name: &WorkspaceName,
+    old_root: Option<&PathBuf>,
+    force: bool,
+) -> Result<(), CommandError> {
+    let root = workspace_command.workspace_root().to_owned();
     let mut tx = workspace_command.start_transaction().into_inner();
     let (mut locked_ws, _wc_commit) = workspace_command.start_working_copy_mutation()?;
 
     locked_ws.locked_wc().rename_workspace(new_name.to_owned());
 
     tx.repo_mut()
-        .rename_workspace(&old_name, new_name.to_owned())?;
-    let repo = tx.commit(format!(
-        "Renamed workspace '{old}' to '{new}'",
-        old = old_name.as_symbol(),
-        new = new_name.as_symbol()
-    ))?;
-    locked_ws.finish(repo.op_id().clone())?;
+        .rename_workspace(old_name, new_name.to_owned())?;
 
+    // A forgotten workspace of the same name may have left its entry behind.
+    // The path is kept so that it can be restored if renaming fails.
+    let displaced_path = workspace_store.try_get_path(new_name)?;
+    if displaced_path.is_some() && !force {
+        return Err(
+            CommandError::from(WorkspaceStoreError::AlreadyExists(new_name.to_owned()))
+                .hinted("Use --force to replace the recorded path."),
+        );
+    }
+    // Every change to the store is undone below if a later step fails.
+    let mut renamed = false;
+    let result = (|| {
+        if displaced_path.is_some() {
+            workspace_store.remove_path(new_name)?;
+        }
+        if workspace_store.exists(old_name)? {
+            workspace_store.rename(old_name, new_name)?;
+            renamed = true;
+        }
+        // Recorded under the new name if the workspace predates the store
+        if !renamed || old_root.is_some() {
+            workspace_store.set_path(new_name, &root)?;
+        }
+        workspace_store.flush()?;
+        Ok(tx.commit(format!(
+            "Renamed workspace '{old}' to '{new}'",
+            old = old_name.as_symbol(),
+            new = new_name.as_symbol()
+        ))?)
+    })();
+    let repo = match result {
+        Ok(repo) => repo,
+        Err(err) => {
+            // Nothing was committed, so the entry is put back.
+            if renamed {
+                workspace_store.rename(new_name, old_name).ok();
+                if let Some(old_root) = old_root {
+                    workspace_store.set_path(old_name, old_root).ok();
+                }
+            } else {
+                workspace_store.remove_path(new_name).ok();
+            }
+            if let Some(displaced_path) = &displaced_path {
+                workspace_store.set_path(new_name, displaced_path).ok();
+            }
+            workspace_store.flush().ok();
+            return Err(err);
+        }
+    };
+    locked_ws.finish(repo.op_id().clone())?;
     Ok(())
 }
+
+/// Returns the directory the workspace is moved to by `--update-dir`, or
+/// `None` if its directory isn't named after the workspace. Fails if the
+/// directory can't be moved there.
+fn new_workspace_dir(
+    workspace_command: &WorkspaceCommandHelper,
+    old_name: &WorkspaceName,
+    new_name: &WorkspaceName,
+) -> Result<Option<PathBuf>, CommandError> {
+    let old_root = workspace_command.workspace_root();
+    if old_root.file_name() != Some(old_name.as_str().as_ref()) {
+        return Ok(None);
+    }
+    let is_file_name = Path::new(new_name.as_str()).file_name() == Some(new_name.as_str().as_ref());
+    if !is_file_name {
+        return Err(user_error(format!(
+            "Cannot name a directory after workspace {}",
+            new_name.as_symbol()
+        )));
+    }
+    let repo_path = dunce::canonicalize(workspace_command.repo_path())?;
+    if repo_path.starts_with(old_root) {
+        return Err(user_error(format!(
+            "Refusing to move {} since it contains the repo",
+            old_root.display()
+        )));
+    }
+    let new_root = old_root.with_file_name(new_name.as_str());
+    if new_root.symlink_metadata().is_ok() {
+        return Err(user_error(format!(
+            "Refusing to move {} since {} already exists",
+            old_root.display(),
+            new_root.display()
+        )));
+    }
+    Ok(Some(new_root))
+}
+
+/// Moves the workspace directory to `new_root`, which must not exist.
+///
+/// On Unix, `rename()` replaces an empty directory, so the target is checked
+/// again right before moving, in case it was created since
+/// [`new_workspace_dir()`] checked it.
+fn move_dir(old_root: &Path, new_root: &Path) -> io::Result<()> {
+    if new_root.symlink_metadata().is_ok() {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+    }
+    fs::rename(old_root, new_root)
+}
+
+/// Moves the workspace directory back to `old_root` after renaming failed
+/// with `err`. If that fails too, the error tells where the directory was
+/// left.
+fn move_back(err: CommandError, old_root: &Path, new_root: &Path) -> CommandError {
+    match fs::rename(new_root, old_root) {
+        Ok(()) => err,
+        Err(rename_err) => err.hinted(format!(
+            "Failed to move the workspace back to {}, so it was left at {}: {rename_err}",
+            old_root.display(),
+            new_root.display()
+        )),
+    }
+}
@@ -12,29 +12,402 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::env;
 use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
 
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::Utc;
+use clap_complete::ArgValueCandidates;
+use itertools::Itertools as _;
 use jj_lib::file_util;
+use jj_lib::ref_name::WorkspaceName;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::workspace_store::PathStatus;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
 use tracing::instrument;
 
 use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandHelper;
 use crate::command_error::CommandError;
+use crate::command_error::internal_error;
+use crate::command_error::json_error;
 use crate::command_error::user_error;
+use crate::command_error::workspace_store_json_error;
+use crate::complete;
 use crate::ui::Ui;
 
 /// Show the current workspace root directory
+///
+/// With `--workspace` or `--all`, the roots are looked up from the paths
+/// recorded when the workspaces were created, so any workspace of the repo can
+/// be located. Use the global `--repository` option to query the workspaces of
+/// another repo.
+///
+/// If the `JJ_WORKSPACE` environment variable is set, the root of the workspace
+/// it names is shown instead of the current one, unless `--workspace` or
+/// `--all` is given. This lets tools pin the workspace for a subshell.
+///
+/// If the root recorded for the current workspace differs from the directory
+/// it was loaded from, e.g. because it was moved, the recorded root is shown
+/// by default. Set `workspace.root-source = "live"` to show the directory it
+/// was loaded from instead, including for `--check`. `--display` still shows
+/// the path the workspace was created with.
 #[derive(clap::Args, Clone, Debug)]
-pub struct WorkspaceRootArgs {}
+pub struct WorkspaceRootArgs {
+    /// Show the root of the given workspace instead of the current one
+    ///
+    /// `@` refers to the current workspace. Use `'"@"'` to refer to a
+    /// workspace literally named `@`.
+    #[arg(long, add = ArgValueCandidates::new(complete::workspaces))]
+    workspace: Option<WorkspaceNameBuf>,
+    /// Show the roots of all workspaces as `<name>: <path>` lines
+    #[arg(long, conflicts_with = "workspace")]
+    all: bool,
+    /// With `--all`, print each workspace as its name and root, each followed
+    /// by a NUL character
+    ///
+    /// The name is printed as is, without quoting, so names with special
+    /// characters can be read safely, e.g. `jj workspace root --all --print0 |
+    /// while IFS= read -r -d '' name && IFS= read -r -d '' path; do ...; done`.
+    #[arg(long, requires = "all", conflicts_with_all = ["json", "json_array"])]
+    print0: bool,
+    /// Print the roots as JSON
+    ///
+    /// A single workspace is printed as an object with `name`, `path`, and
+    /// `exists` keys, and a `created_at` key with the RFC 3339 time the
+    /// workspace was added, if it's known. With `--all`, an array of such
+    /// objects is printed. If the roots can't be looked up, an object with
+    /// `error`, `code`, and `workspace` keys is printed to stderr instead.
+    #[arg(long)]
+    json: bool,
+    /// Like `--json`, but always print an array, even for a single workspace
+    #[arg(long, conflicts_with = "json")]
+    json_array: bool,
+    /// Don't print the root, but fail if it doesn't exist
+    ///
+    /// This is useful in scripts, e.g. `jj workspace root --check --workspace
+    /// foo && cd "$(jj workspace root --workspace foo)"`.
+    #[arg(long, conflicts_with_all = ["all", "json", "json_array"])]
+    check: bool,
+    /// Show the roots as they were given when the workspaces were created
+    ///
+    /// The recorded roots have symlinks resolved. With this flag, the path the
+    /// workspace was created with is shown instead, if it was recorded.
+    #[arg(long, conflicts_with = "check")]
+    display: bool,
+}
+
+/// Value of the `workspace.root-source` setting.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RootSource {
+    /// The root recorded in the store, falling back to the live root
+    Store,
+    /// The root the current workspace was loaded from
+    Live,
+}
+
+/// JSON representation of a workspace root. Scripts rely on this schema, so
+/// only add fields to it.
+#[derive(serde::Serialize)]
+struct WorkspaceRootJson<'a> {
+    name: &'a str,
+    path: &'a str,
+    exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+}
 
 #[instrument(skip_all)]
 pub fn cmd_workspace_root(
     ui: &mut Ui,
     command: &CommandHelper,
-    _args: &WorkspaceRootArgs,
+    args: &WorkspaceRootArgs,
 ) -> Result<(), CommandError> {
     let loader = command.workspace_loader()?;
-    let path_bytes = file_util::path_to_bytes(loader.workspace_root()).map_err(user_error)?;
+    let json = args.json || args.json_array;
+    let env_workspace = if args.workspace.is_none() && !args.all {
+        workspace_from_env()?
+    } else {
+        None
+    };
+    if args.workspace.is_none()
+        && env_workspace.is_none()
+        && !args.all
+        && !json
+        && !args.check
+        && !args.display
+    {
+        write_path(ui, loader.workspace_root())?;
+        writeln!(ui.stdout())?;
+        return Ok(());
+    }
+
+    // Only the recorded roots are needed, so don't snapshot the working copy.
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let workspace_store = workspace_command.workspace_store()?;
+    let mut workspaces = Workspaces::new(workspace_store.as_ref(), workspace_command.repo().view());
+    // Workspaces created before the store existed have no entry, but the root
+    // of the current workspace is known anyway. There's no current workspace
+    // if `-R` points to another repo.
+    let mut live_root = None;
+    if command.is_cwd_repo()? {
+        workspaces = workspaces.with_current(
+            workspace_command.workspace_name(),
+            workspace_command.workspace_root(),
+        );
+        let root_source: RootSource = workspace_command.settings().get("workspace.root-source")?;
+        if root_source == RootSource::Live {
+            live_root = Some(workspace_command.workspace_root());
+        }
+    }
+
+    let workspace = args
+        .workspace
+        .as_deref()
+        .map(|name| resolve_workspace_name(name, workspace_command.workspace_name()));
+    let workspace = match (workspace, env_workspace.as_deref()) {
+        (Some(name), _) => Some(name),
+        (None, Some(name)) => {
+            let name = resolve_workspace_name(name, workspace_command.workspace_name());
+            // Report the variable, which is easily forgotten in the environment
+            if workspaces.get(name)?.is_none() {
+                if json {
+                    return Err(workspace_store_json_error(&WorkspaceStoreError::NotFound(
+                        name.to_owned(),
+                    )));
+                }
+                return Err(user_error(format!(
+                    "No such workspace: {} (set by ${WORKSPACE_ENV_VAR})",
+                    name.as_symbol()
+                ))
+                .hinted(super::NO_SUCH_WORKSPACE_HINT));
+            }
+            Some(name)
+        }
+        (None, None) => None,
+    };
+    if args.check {
+        let name = workspace.unwrap_or(workspace_command.workspace_name());
+        if live_root.is_some() && name == workspace_command.workspace_name() {
+            return Ok(());
+        }
+        return match workspaces.path_status(name)? {
+            PathStatus::Exists => Ok(()),
+            PathStatus::Missing => match workspaces.get(name)? {
+                Some(entry) => Err(user_error(format!(
+                    "Root of workspace {entry} does not exist"
+                ))),
+                // The entry went away after its status was checked
+                None => Err(super::no_such_workspace_error(name)),
+            },
+            PathStatus::Unknown if workspaces.get(name)?.is_none() => {
+                Err(super::no_such_workspace_error(name))
+            }
+            PathStatus::Unknown => Err(user_error(format!(
+                "Root of workspace {} is unknown",
+                name.as_symbol()
+            ))),
+        };
+    }
+
+    let roots = match look_up_roots(&workspaces, args.all, workspace) {
+        Ok(roots) => roots,
+        Err(err) if json => return Err(workspace_store_json_error(&err)),
+        // Known to the view, e.g. because forgetting it was undone after its
+        // root was deleted
+        Err(WorkspaceStoreError::NotFound(name))
+            if workspace_command
+                .repo()
+                .view()
+                .get_wc_commit_id(&name)
+                .is_some() =>
+        {
+            return Err(user_error(format!(
+                "Root of workspace {} is unknown",
+                name.as_symbol()
+            )));
+        }
+        Err(WorkspaceStoreError::NotFound(name)) => {
+            return Err(super::no_such_workspace_error(&name));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let mut roots = roots.unwrap_or_else(|| {
+        vec![(
+            workspace_command.workspace_name().to_owned(),
+            loader.workspace_root().to_owned(),
+        )]
+    });
+    if let Some(live_root) = live_root {
+        for (name, root) in &mut roots {
+            if name == workspace_command.workspace_name() {
+                *root = live_root.to_owned();
+            }
+        }
+    }
+    if args.display {
+        for (name, root) in &mut roots {
+            match workspace_store.get_display_path(name) {
+                Ok(path) => *root = path,
+                // Not recorded, e.g. the current workspace predates the store
+                Err(WorkspaceStoreError::NotFound(_)) => {}
+                Err(err) if json => return Err(workspace_store_json_error(&err)),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    if json {
+        let entries: Vec<_> = roots
+            .iter()
+            .map(|(name, root)| -> Result<_, CommandError> {
+                let path = file_util::path_to_bytes(root)
+                    .ok()
+                    .and_then(|path| str::from_utf8(path).ok())
+                    .ok_or_else(|| {
+                        json_error(
+                            "encode_path",
+                            format!("Root of workspace {} is not valid UTF-8", name.as_symbol()),
+                            Some(name),
+                        )
+                    })?;
+                let created_at = created_at(&workspace_command, workspace_store.as_ref(), name)
+                    .map_err(|err| workspace_store_json_error(&err))?;
+                Ok(WorkspaceRootJson {
+                    name: name.as_str(),
+                    path,
+                    exists: root.is_dir(),
+                    created_at: created_at.map(|time| time.to_rfc3339()),
+                })
+            })
+            .try_collect()?;
+        let text = if args.all || args.json_array {
+            serde_json::to_string_pretty(&entries)
+        } else {
+            serde_json::to_string_pretty(&entries[0])
+        };
+        writeln!(ui.stdout(), "{}", text.map_err(internal_error)?)?;
+    } else if args.print0 {
+        for (name, root) in &roots {
+            write_print0_entry(ui, name, root)?;
+        }
+    } else if args.all {
+        for (name, root) in &roots {
+            write!(ui.stdout(), "{}: ", name.as_symbol())?;
+            write_path(ui, root)?;
+            writeln!(ui.stdout())?;
+        }
+    } else {
+        write_path(ui, &roots[0].1)?;
+        writeln!(ui.stdout())?;
+    }
+    Ok(())
+}
+
+const WORKSPACE_ENV_VAR: &str = "JJ_WORKSPACE";
+
+/// Returns the workspace named by `$JJ_WORKSPACE`. An empty value is the same
+/// as an unset one.
+fn workspace_from_env() -> Result<Option<WorkspaceNameBuf>, CommandError> {
+    match env::var(WORKSPACE_ENV_VAR) {
+        Ok(name) if name.is_empty() => Ok(None),
+        Ok(name) if name.trim() != name || name.contains(char::is_control) => Err(user_error(
+            format!("${WORKSPACE_ENV_VAR} is not a valid workspace name: {name:?}"),
+        )),
+        Ok(name) => Ok(Some(name.into())),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(name)) => Err(user_error(format!(
+            "${WORKSPACE_ENV_VAR} is not a valid workspace name: {}",
+            name.display()
+        ))),
+    }
+}
+
+/// Maps `@` to the current workspace, and `"@"` to the workspace named `@`.
+fn resolve_workspace_name<'a>(
+    name: &'a WorkspaceName,
+    current_name: &'a WorkspaceName,
+) -> &'a WorkspaceName {
+    match name.as_str() {
+        "@" => current_name,
+        r#""@""# => WorkspaceName::new("@"),
+        _ => name,
+    }
+}
+
+/// Looks up the roots of the requested workspaces. Returns `None` if the
+/// current workspace was requested.
+fn look_up_roots(
+    workspaces: &Workspaces,
+    all: bool,
+    workspace: Option<&WorkspaceName>,
+) -> Result<Option<Vec<(WorkspaceNameBuf, PathBuf)>>, WorkspaceStoreError> {
+    if all {
+        let mut roots = vec![];
+        for name in workspaces.all()? {
+            // Workspaces whose root is unknown are skipped.
+            if let Some(root) = workspaces.get(&name)?.and_then(|entry| entry.path) {
+                roots.push((name, root));
+            }
+        }
+        Ok(Some(roots))
+    } else if let Some(name) = workspace {
+        let root = workspaces.resolve_root(name)?;
+        Ok(Some(vec![(name.to_owned(), root)]))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Returns the time the workspace was added: the time of the operation that
+/// added it if it's recorded, or else the time its root was recorded. Returns
+/// `None` if the workspace has no entry.
+fn created_at(
+    workspace_command: &WorkspaceCommandHelper,
+    workspace_store: &dyn WorkspaceStore,
+    name: &WorkspaceName,
+) -> Result<Option<DateTime<FixedOffset>>, WorkspaceStoreError> {
+    let op_id = match workspace_store.created_at_op_id(name) {
+        Ok(op_id) => op_id,
+        // Not recorded, e.g. the current workspace predates the store
+        Err(WorkspaceStoreError::NotFound(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    // The operation may have been garbage-collected since
+    let op_time = op_id
+        .and_then(|op_id| {
+            workspace_command
+                .repo()
+                .loader()
+                .load_operation(&op_id)
+                .ok()
+        })
+        .and_then(|op| op.metadata().time.end.to_datetime().ok());
+    if let Some(time) = op_time {
+        return Ok(Some(time));
+    }
+    // The file system doesn't keep the time zone
+    let mtime = workspace_store.entry_mtime(name)?;
+    Ok(Some(DateTime::<Utc>::from(mtime).fixed_offset()))
+}
+
+/// Writes `<name>\0<path>\0`.
+fn write_print0_entry(ui: &Ui, name: &WorkspaceName, path: &Path) -> Result<(), CommandError> {
+    ui.stdout().write_all(name.as_str().as_bytes())?;
+    ui.stdout().write_all(b"\0")?;
+    write_path(ui, path)?;
+    ui.stdout().write_all(b"\0")?;
+    Ok(())
+}
+
+fn write_path(ui: &Ui, path: &Path) -> Result<(), CommandError> {
+    let path_bytes = file_util::path_to_bytes(path).map_err(user_error)?;
     ui.stdout().write_all(path_bytes)?;
-    writeln!(ui.stdout())?;
     Ok(())
 }
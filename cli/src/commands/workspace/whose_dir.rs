@@ -0,0 +1,107 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use itertools::Itertools as _;
+use jj_lib::file_util;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::workspace_store::Workspaces;
+use tracing::instrument;
+
+use crate::cli_util::CommandHelper;
+use crate::command_error::CommandError;
+use crate::command_error::user_error;
+use crate::ui::Ui;
+
+/// Show which workspaces a directory belongs to
+///
+/// Prints the names of the workspaces whose root is the directory, e.g. a Git
+/// worktree. If there are none, the workspaces whose root is inside the
+/// directory are printed instead, and if there are none either, the workspace
+/// whose root contains the directory.
+#[derive(clap::Args, Clone, Debug)]
+pub struct WorkspaceWhoseDirArgs {
+    /// The directory to look up
+    #[arg(value_hint = clap::ValueHint::DirPath)]
+    path: PathBuf,
+}
+
+#[instrument(skip_all)]
+pub fn cmd_workspace_whose_dir(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &WorkspaceWhoseDirArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let path = canonicalize(&command.cwd().join(&args.path));
+    let workspace_store = workspace_command.workspace_store()?;
+    let mut names = workspace_store.find_by_path(&path)?;
+    if names.is_empty() {
+        let mut workspaces =
+            Workspaces::new(workspace_store.as_ref(), workspace_command.repo().view());
+        if command.is_cwd_repo()? {
+            workspaces = workspaces.with_current(
+                workspace_command.workspace_name(),
+                workspace_command.workspace_root(),
+            );
+        }
+        let mut roots = vec![];
+        for name in workspaces.all()? {
+            if let Some(root) = workspaces.get(&name)?.and_then(|entry| entry.path) {
+                roots.push((name, canonicalize(&root)));
+            }
+        }
+        names = names_by_root(&roots, &path);
+    }
+    if names.is_empty() {
+        return Err(user_error(format!(
+            "No workspace found for {}",
+            path.display()
+        )));
+    }
+    for name in &names {
+        writeln!(ui.stdout(), "{}", name.as_symbol())?;
+    }
+    Ok(())
+}
+
+/// Returns the workspaces whose root is `path` or is inside it, or else the
+/// one whose root is the closest ancestor of `path`.
+fn names_by_root(roots: &[(WorkspaceNameBuf, PathBuf)], path: &Path) -> Vec<WorkspaceNameBuf> {
+    let inside = roots
+        .iter()
+        .filter(|(_, root)| root.starts_with(path))
+        .map(|(name, _)| name.clone())
+        .collect_vec();
+    if !inside.is_empty() {
+        return inside;
+    }
+    // Workspaces may be nested, so only the innermost one is the owner.
+    roots
+        .iter()
+        .filter(|(_, root)| path.starts_with(root))
+        .max_set_by_key(|(_, root)| root.components().count())
+        .into_iter()
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Resolves symlinks like the recorded roots. Paths that can't be
+/// canonicalized, e.g. because they no longer exist, are only normalized.
+fn canonicalize(path: &Path) -> PathBuf {
+    dunce::canonicalize(path).unwrap_or_else(|_| file_util::normalize_path(path))
+}
@@ -516,19 +516,41 @@ pub fn workspaces() -> Vec<CompletionCandidate> {
             .build()
             .arg("workspace")
             .arg("list")
+            .arg("--no-orphans")
             .arg("-T")
             .arg(r#"name ++ "\t" ++ if(target.description(), target.description().first_line(), "(no description set)") ++ "\n""#)
             .output()
             .map_err(user_error)?;
         let stdout = String::from_utf8_lossy(&output.stdout);
-
-        Ok(stdout
+        let mut candidates = stdout
             .lines()
             .map(|line| {
                 let (name, desc) = line.split_once(": ").unwrap_or((line, ""));
                 CompletionCandidate::new(name).help(Some(desc.to_string().into()))
             })
-            .collect())
+            .collect_vec();
+
+        // Workspaces that are only known to the workspace store have no
+        // working-copy commit to render the template with.
+        let output = jj
+            .build()
+            .arg("workspace")
+            .arg("list")
+            .arg("--json")
+            .output()
+            .map_err(user_error)?;
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+        let orphans = json["entries"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry["orphan"].as_bool() == Some(true))
+            .filter_map(|entry| entry["name"].as_str());
+        candidates.extend(orphans.map(|name| {
+            CompletionCandidate::new(name).help(Some("(no working-copy commit)".into()))
+        }));
+        candidates.sort_by(|a, b| a.get_value().cmp(b.get_value()));
+        Ok(candidates)
     })
 }
 
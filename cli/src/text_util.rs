@@ -93,6 +93,39 @@ pub fn elide_end<'a>(text: &'a str, ellipsis: &'a str, max_width: usize) -> (Cow
     (Cow::Owned([text, ellipsis].concat()), concat_width)
 }
 
+/// Shortens `text` to `max_width` by removing characters in the middle.
+/// `ellipsis` is added if the `text` gets truncated.
+///
+/// The returned string (including `ellipsis`) never exceeds the `max_width`.
+pub fn elide_middle<'a>(
+    text: &'a str,
+    ellipsis: &'a str,
+    max_width: usize,
+) -> (Cow<'a, str>, usize) {
+    let (text_end, text_width) = truncate_end_pos(text, max_width);
+    if text_end == text.len() {
+        return (Cow::Borrowed(text), text_width);
+    }
+
+    let (ellipsis_end, ellipsis_width) = truncate_end_pos(ellipsis, max_width);
+    if ellipsis_end != ellipsis.len() {
+        let ellipsis = &ellipsis[..ellipsis_end];
+        return (Cow::Borrowed(ellipsis), ellipsis_width);
+    }
+
+    // The tail tends to be more informative (e.g. the last path component), so
+    // it gets the extra column.
+    let max_text_width = max_width - ellipsis_width;
+    let (head, head_width) = elide_end(text, "", max_text_width / 2);
+    let (tail, tail_width) = elide_start(text, "", max_text_width - head_width);
+    let concat_width = head_width + ellipsis_width + tail_width;
+    assert!(concat_width <= max_width);
+    (
+        Cow::Owned([&*head, ellipsis, &*tail].concat()),
+        concat_width,
+    )
+}
+
 /// Shortens `text` to `max_width` by removing leading characters, returning
 /// `(start_index, width)`.
 ///
@@ -732,6 +765,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_elide_middle() {
+        // Empty string
+        assert_eq!(elide_middle("", "", 1), ("".into(), 0));
+
+        // Basic truncation
+        assert_eq!(elide_middle("abcdef", "", 6), ("abcdef".into(), 6));
+        assert_eq!(elide_middle("abcdef", "", 5), ("abdef".into(), 5));
+        assert_eq!(elide_middle("abcdef", "", 1), ("f".into(), 1));
+        assert_eq!(elide_middle("abcdef", "", 0), ("".into(), 0));
+        assert_eq!(elide_middle("abcdef", "-=~", 6), ("abcdef".into(), 6));
+        assert_eq!(elide_middle("abcdef", "-=~", 5), ("a-=~f".into(), 5));
+        assert_eq!(elide_middle("abcdef", "-=~", 4), ("-=~f".into(), 4));
+        assert_eq!(elide_middle("abcdef", "-=~", 3), ("-=~".into(), 3));
+        assert_eq!(elide_middle("abcdef", "-=~", 2), ("-=".into(), 2));
+        assert_eq!(elide_middle("abcdef", "-=~", 0), ("".into(), 0));
+
+        // East Asian characters (char.width() == 2)
+        assert_eq!(elide_middle("一二三", "", 6), ("一二三".into(), 6));
+        assert_eq!(elide_middle("一二三", "", 5), ("一三".into(), 4));
+        assert_eq!(elide_middle("一二三", ".", 5), ("一.三".into(), 5));
+        assert_eq!(elide_middle("一二三", ".", 4), (".三".into(), 3));
+
+        // Paths
+        assert_eq!(
+            elide_middle("/home/user/workspaces/feature", "…", 16),
+            ("/home/u…/feature".into(), 16)
+        );
+    }
+
     #[test]
     fn test_write_truncated_labeled() {
         let ellipsis_recorder = FormatRecorder::new();
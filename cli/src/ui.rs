@@ -415,6 +415,17 @@ impl Ui {
         self.formatter_factory.is_color()
     }
 
+    /// Whether stdout is meant to be read by the user: a terminal, the pager,
+    /// or output that's colored as if it were a terminal.
+    pub fn stdout_is_interactive(&self) -> bool {
+        let terminal = match &self.output {
+            UiOutput::Terminal { stdout, .. } => stdout.is_terminal(),
+            UiOutput::Paged { .. } | UiOutput::BuiltinPaged { .. } => true,
+            UiOutput::Null => false,
+        };
+        terminal || self.color()
+    }
+
     pub fn new_formatter<'output, W: Write + 'output>(
         &self,
         output: W,
@@ -1073,6 +1073,20 @@ fn test_workspaces() {
     default	initial	
     [EOF]
     ");
+
+    // Workspaces only known to the workspace store are included
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "def-orphan", "../orphan"])
+        .success();
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    let output = main_dir.complete_fish(["workspace", "root", "--workspace", "def"]);
+    insta::assert_snapshot!(output, @r"
+    def-orphan	(no working-copy commit)
+    def-second	(no description set)	
+    default	initial	
+    [EOF]
+    ");
 }
 
 #[test]
@@ -330,6 +330,34 @@ fn test_debug_tree() {
     );
 }
 
+#[test]
+fn test_debug_workspace_store() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+    work_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+
+    let output = work_dir.run_jj(["debug", "workspace-store"]);
+    assert_snapshot!(output.normalize_backslash(), @r"
+    Backend: simple_workspace_store
+    Directory: $TEST_ENV/repo/.jj/repo/workspace_store
+    Entries: 2
+    [EOF]
+    ");
+
+    let output = work_dir.run_jj(["debug", "workspace-store", "second", "third"]);
+    assert_snapshot!(output.normalize_backslash(), @r"
+    Backend: simple_workspace_store
+    Directory: $TEST_ENV/repo/.jj/repo/workspace_store
+    Entries: 2
+    second: $TEST_ENV/repo/.jj/repo/workspace_store/second
+    third: $TEST_ENV/repo/.jj/repo/workspace_store/third (no entry)
+    [EOF]
+    ");
+}
+
 fn filter_index_stats(output: CommandOutput) -> CommandOutput {
     let regex = Regex::new(r"    Name: [0-9a-z]+").unwrap();
     output.normalize_stdout_with(|text| regex.replace_all(&text, "    Name: [hash]").into_owned())
@@ -17,6 +17,7 @@ use test_case::test_case;
 use crate::common::CommandOutput;
 use crate::common::TestEnvironment;
 use crate::common::TestWorkDir;
+use crate::common::force_interactive;
 
 /// Test adding a second workspace
 #[test]
@@ -417,6 +418,47 @@ fn test_workspaces_add_workspace_from_subdir() {
     ");
 }
 
+#[test]
+fn test_workspaces_add_name_from_dir() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../stale"]).success();
+    // The path of stale stays recorded
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("stale")).unwrap();
+
+    let output = main_dir.run_jj(["workspace", "add", "--name-from-dir", "../review-pr-42"]);
+    insta::assert_snapshot!(output.normalize_backslash(), @r#"
+    ------- stderr -------
+    Created workspace in "../review-pr-42"
+    Working copy  (@) now at: rzvqmyuk 42960ba4 (empty) (no description set)
+    Parent commit (@-)      : zzzzzzzz 00000000 (empty) (no description set)
+    [EOF]
+    "#);
+
+    let output = main_dir.run_jj(["workspace", "add", "--name-from-dir", "../stale"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: A path is already recorded for workspace stale
+    [EOF]
+    [exit status: 1]
+    ");
+    assert!(!test_env.env_root().join("stale").exists());
+
+    // Without the flag, the stale path is replaced
+    main_dir.run_jj(["workspace", "add", "../stale"]).success();
+
+    let output = main_dir.run_jj(["workspace", "list", "-T", "name ++ '\n'"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    review-pr-42
+    stale
+    [EOF]
+    ");
+}
+
 #[test]
 fn test_workspaces_add_workspace_in_current_workspace() {
     let test_env = TestEnvironment::default();
@@ -1101,6 +1143,48 @@ fn test_workspaces_forget_multi_transaction() {
     ");
 }
 
+#[test]
+fn test_workspaces_forget_many_description() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+
+    for name in ["ws1", "ws2", "ws3", "ws4", "ws5"] {
+        main_dir
+            .run_jj(["workspace", "add", &format!("../{name}")])
+            .success();
+    }
+
+    // only the first few names are listed in the description, but the tags
+    // keep the full list
+    main_dir
+        .run_jj(["workspace", "forget", "ws1", "ws2", "ws3", "ws4", "ws5"])
+        .success();
+    let output = main_dir.run_jj([
+        "op",
+        "log",
+        "--limit",
+        "1",
+        "--no-graph",
+        "-T",
+        "description",
+    ]);
+    insta::assert_snapshot!(output, @"forget 5 workspaces (ws1, ws2, ws3, …)[EOF]");
+    let output = main_dir.run_jj([
+        "op",
+        "log",
+        "--limit",
+        "1",
+        "--no-graph",
+        "-T",
+        r#"tags.lines().filter(|line| line.starts_with("workspaces: ")).join("\n") ++ "\n""#,
+    ]);
+    insta::assert_snapshot!(output, @r"
+    workspaces: ws1, ws2, ws3, ws4, ws5
+    [EOF]
+    ");
+}
+
 #[test]
 fn test_workspaces_forget_abandon_commits() {
     let test_env = TestEnvironment::default();
@@ -1169,187 +1253,2865 @@ fn test_workspaces_forget_abandon_commits() {
     ");
 }
 
-/// Test context of commit summary template
 #[test]
-fn test_list_workspaces_template() {
+fn test_workspaces_forget_keep_going() {
     let test_env = TestEnvironment::default();
     test_env.run_jj_in(".", ["git", "init", "main"]).success();
-    test_env.add_config(
-        r#"
-        templates.workspace_list = """name ++ ": " ++ target.commit_id().short() ++ " " ++
-                                      target.description().first_line() ++
-                                      if(target.current_working_copy(), " (current)") ++ "\n""""
-        "#,
-    );
     let main_dir = test_env.work_dir("main");
-    let secondary_dir = test_env.work_dir("secondary");
-
-    main_dir.write_file("file", "contents");
-    main_dir.run_jj(["commit", "-m", "initial"]).success();
-    main_dir
-        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
-        .success();
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
 
-    // "current_working_copy" should point to the workspace we operate on
-    let output = main_dir.run_jj(["workspace", "list"]);
+    // Without --keep-going, nothing is forgotten
+    let output = main_dir.run_jj(["workspace", "forget", "second", "unknown"]);
     insta::assert_snapshot!(output, @r"
-    default: 504e3d8c1bcd  (current)
-    second: 058f604dffcd 
+    ------- stderr -------
+    Error: No such workspace: unknown
+    Hint: Run `jj workspace list` to see the available workspaces.
     [EOF]
+    [exit status: 1]
     ");
 
-    let output = secondary_dir.run_jj(["workspace", "list"]);
+    let output = main_dir.run_jj([
+        "workspace",
+        "forget",
+        "--keep-going",
+        "second",
+        "unknown",
+        "third",
+        "other",
+    ]);
     insta::assert_snapshot!(output, @r"
-    default: 504e3d8c1bcd 
-    second: 058f604dffcd  (current)
+    ------- stderr -------
+    Warning: No such workspace: unknown
+    Warning: No such workspace: other
+    Error: Skipped unknown workspaces: unknown, other
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--include-orphans"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["op", "log", "-n1", "-T", "description"]);
+    insta::assert_snapshot!(output, @r"
+    @  forget workspaces second, third
     [EOF]
     ");
 
-    // Using template option
-    let template = r#"name ++ ": " ++ target.commit_id().short() ++ "\n""#;
-    let output = main_dir.run_jj(["workspace", "list", "-T", template]);
+    // Nothing to forget
+    let output = main_dir.run_jj(["workspace", "forget", "--keep-going", "unknown"]);
     insta::assert_snapshot!(output, @r"
-    default: 504e3d8c1bcd
-    second: 058f604dffcd
+    ------- stderr -------
+    Warning: No such workspace: unknown
+    Error: Skipped unknown workspaces: unknown
     [EOF]
+    [exit status: 1]
     ");
 }
 
-/// Test getting the workspace root from primary and secondary workspaces
 #[test]
-fn test_workspaces_root() {
+fn test_workspaces_forget_verbose() {
     let test_env = TestEnvironment::default();
     test_env.run_jj_in(".", ["git", "init", "main"]).success();
     let main_dir = test_env.work_dir("main");
-    let secondary_dir = test_env.work_dir("secondary");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    std::fs::remove_file(main_dir.root().join(".jj/repo/workspace_store/second")).unwrap();
 
-    let output = main_dir.run_jj(["workspace", "root"]);
+    let output = main_dir.run_jj(["workspace", "forget", "--verbose", "second", "third"]);
     insta::assert_snapshot!(output, @r"
-    $TEST_ENV/main
+    ------- stderr -------
+    second: store entry already absent
     [EOF]
     ");
-    let main_subdir_dir = main_dir.create_dir("subdir");
-    let output = main_subdir_dir.run_jj(["workspace", "root"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspaces_forget_and_delete_through_symlink() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    test_env.add_config("workspace.canonicalize-paths = false");
+    let main_dir = test_env.work_dir("main");
+    std::fs::create_dir(test_env.env_root().join("real")).unwrap();
+    std::os::unix::fs::symlink(
+        test_env.env_root().join("real"),
+        test_env.env_root().join("link"),
+    )
+    .unwrap();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../link/second"])
+        .success();
+
+    // The current workspace is recognized even if its root was recorded
+    // through a symlink
+    let second_dir = test_env.work_dir("real/second");
+    let output = second_dir.run_jj(["workspace", "forget", "--and-delete", "--yes"]);
     insta::assert_snapshot!(output, @r"
-    $TEST_ENV/main
+    ------- stderr -------
+    Error: Refusing to delete the current workspace second ($TEST_ENV/link/second)
     [EOF]
+    [exit status: 1]
     ");
+    assert!(test_env.env_root().join("real/second").exists());
+}
 
+#[test]
+fn test_workspaces_forget_abandon_keep_commits() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["describe", "-m", "main"]).success();
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
     main_dir
-        .run_jj(["workspace", "add", "--name", "secondary", "../secondary"])
+        .run_jj(["describe", "-m", "second work", "second@"])
         .success();
-    let output = secondary_dir.run_jj(["workspace", "root"]);
-    insta::assert_snapshot!(output, @r"
-    $TEST_ENV/secondary
+    insta::assert_snapshot!(get_log_output(&main_dir), @r"
+    @  1e8717e2b7da default@
+    │ ○  602ab46f4418 second@
+    ├─╯
+    │ ○  42960ba4ec00 third@
+    ├─╯
+    ◆  000000000000
     [EOF]
     ");
-    let secondary_subdir_dir = secondary_dir.create_dir("subdir");
-    let output = secondary_subdir_dir.run_jj(["workspace", "root"]);
-    insta::assert_snapshot!(output, @r"
-    $TEST_ENV/secondary
+
+    // By default, only the empty working-copy commit is abandoned
+    main_dir
+        .run_jj(["workspace", "forget", "second", "third"])
+        .success();
+    insta::assert_snapshot!(get_log_output(&main_dir), @r"
+    @  1e8717e2b7da
+    │ ○  602ab46f4418
+    ├─╯
+    ◆  000000000000
     [EOF]
     ");
-}
+    main_dir.run_jj(["undo"]).success();
 
-#[test]
-fn test_debug_snapshot() {
-    let test_env = TestEnvironment::default();
-    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
-    let work_dir = test_env.work_dir("repo");
+    let output = main_dir.run_jj(["workspace", "forget", "--keep-commits", "second", "third"]);
+    insta::assert_snapshot!(output, @"");
+    insta::assert_snapshot!(get_log_output(&main_dir), @r"
+    @  1e8717e2b7da
+    │ ○  602ab46f4418
+    ├─╯
+    │ ○  42960ba4ec00
+    ├─╯
+    ◆  000000000000
+    [EOF]
+    ");
+    main_dir.run_jj(["undo"]).success();
 
-    work_dir.write_file("file", "contents");
-    work_dir.run_jj(["debug", "snapshot"]).success();
-    let output = work_dir.run_jj(["op", "log"]);
+    let output = main_dir.run_jj([
+        "workspace",
+        "forget",
+        "--abandon-commits",
+        "second",
+        "third",
+    ]);
     insta::assert_snapshot!(output, @r"
-    @  594dfebf2565 test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
-    │  snapshot working copy
-    │  args: jj debug snapshot
-    ○  8f47435a3990 test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
-    │  add workspace 'default'
-    ○  000000000000 root()
+    ------- stderr -------
+    Abandoned 2 commits:
+      pmmvwywv 602ab46f (empty) second work
+      rzvqmyuk 42960ba4 (empty) (no description set)
     [EOF]
     ");
-    work_dir.run_jj(["describe", "-m", "initial"]).success();
-    let output = work_dir.run_jj(["op", "log"]);
-    insta::assert_snapshot!(output, @r"
-    @  81e4a0f2e793 test-username@host.example.com 2001-02-03 04:05:10.000 +07:00 - 2001-02-03 04:05:10.000 +07:00
-    │  describe commit 006bd1130b84e90ab082adeabd7409270d5a86da
-    │  args: jj describe -m initial
-    ○  594dfebf2565 test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
-    │  snapshot working copy
-    │  args: jj debug snapshot
-    ○  8f47435a3990 test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
-    │  add workspace 'default'
-    ○  000000000000 root()
+    insta::assert_snapshot!(get_log_output(&main_dir), @r"
+    @  1e8717e2b7da
+    ◆  000000000000
     [EOF]
     ");
-}
+    main_dir.run_jj(["undo"]).success();
 
-#[test]
-fn test_workspaces_rename_nothing_changed() {
-    let test_env = TestEnvironment::default();
-    test_env.run_jj_in(".", ["git", "init", "main"]).success();
-    let main_dir = test_env.work_dir("main");
-    let output = main_dir.run_jj(["workspace", "rename", "default"]);
-    insta::assert_snapshot!(output, @r"
+    // Commits pointed to by a bookmark are kept
+    main_dir
+        .run_jj(["bookmark", "create", "-r", "second@", "feature"])
+        .success();
+    let output = main_dir.run_jj(["workspace", "forget", "--abandon-commits", "second"]);
+    insta::assert_snapshot!(output, @"");
+
+    let output = main_dir.run_jj([
+        "workspace",
+        "forget",
+        "--abandon-commits",
+        "--keep-commits",
+        "third",
+    ]);
+    insta::assert_snapshot!(output.strip_stderr_last_line(), @r"
     ------- stderr -------
-    Nothing changed.
+    error: the argument '--abandon-commits' cannot be used with '--keep-commits'
+
+    Usage: jj workspace forget --abandon-commits <WORKSPACES>...
+
     [EOF]
+    [exit status: 2]
     ");
 }
 
 #[test]
-fn test_workspaces_rename_new_workspace_name_already_used() {
+fn test_workspaces_forget_store_failure() {
     let test_env = TestEnvironment::default();
     test_env.run_jj_in(".", ["git", "init", "main"]).success();
     let main_dir = test_env.work_dir("main");
     main_dir
-        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
         .success();
-    let output = main_dir.run_jj(["workspace", "rename", "second"]);
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    // third becomes an orphan
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+
+    // The store can't be locked, but the view is still updated
+    let lock_path = main_dir.root().join(".jj/repo/workspace_store.lock");
+    std::fs::create_dir(&lock_path).unwrap();
+    let output = main_dir.run_jj(["workspace", "forget", "second"]);
     insta::assert_snapshot!(output, @r"
     ------- stderr -------
-    Error: Failed to rename a workspace
-    Caused by: Workspace second already exists
+    Warning: Failed to remove the recorded roots of the forgotten workspaces: Failed to open lock file: $TEST_ENV/main/.jj/repo/workspace_store.lock
+    Hint: They are listed by `jj workspace list --orphans-only` until they're removed.
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", r#"name ++ "\n""#]);
+    insta::assert_snapshot!(output, @r"
+    default
+    second ($TEST_ENV/second): (no working-copy commit)
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+
+    // Orphans can't be forgotten without the store
+    let output = main_dir.run_jj(["workspace", "forget", "third"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Internal error: Failed to access the workspace store
+    Caused by:
+    1: Failed to open lock file: $TEST_ENV/main/.jj/repo/workspace_store.lock
+    2: Is a directory (os error 21)
+    [EOF]
+    [exit status: 255]
+    ");
+
+    std::fs::remove_dir(&lock_path).unwrap();
+    let output = main_dir.run_jj(["workspace", "list", "--orphans-only"]);
+    insta::assert_snapshot!(output, @r"
+    second ($TEST_ENV/second): (no working-copy commit)
+      jj workspace forget second
+    third ($TEST_ENV/third): (no working-copy commit)
+      jj workspace forget third
     [EOF]
-    [exit status: 1]
     ");
 }
 
 #[test]
-fn test_workspaces_rename_forgotten_workspace() {
+fn test_workspaces_forget_undo_tombstones() {
     let test_env = TestEnvironment::default();
+    test_env.add_config("workspace.tombstones = true");
     test_env.run_jj_in(".", ["git", "init", "main"]).success();
     let main_dir = test_env.work_dir("main");
     main_dir
-        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
         .success();
+
     main_dir.run_jj(["workspace", "forget", "second"]).success();
-    let secondary_dir = test_env.work_dir("secondary");
-    let output = secondary_dir.run_jj(["workspace", "rename", "third"]);
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
     insta::assert_snapshot!(output, @r"
     ------- stderr -------
-    Error: The current workspace 'second' is not tracked in the repo.
+    Error: No such workspace: second
+    Hint: Run `jj workspace list` to see the available workspaces.
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // Undoing the forget restores the recorded root
+    main_dir.run_jj(["undo"]).success();
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/second
+    [EOF]
+    ");
+
+    // Old tombstones are deleted by gc
+    main_dir.run_jj(["workspace", "forget", "second"]).success();
+    let tombstones_dir = main_dir.root().join(".jj/repo/workspace_store/.tombstones");
+    assert!(tombstones_dir.join("second").exists());
+    main_dir.run_jj(["util", "gc", "--expire=now"]).success();
+    assert!(!tombstones_dir.join("second").exists());
+
+    // Once its tombstone is deleted, the workspace comes back without its root
+    main_dir.run_jj(["undo"]).success();
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Root of workspace second is unknown
     [EOF]
     [exit status: 1]
     ");
 }
 
 #[test]
-fn test_workspaces_rename_workspace() {
+fn test_workspaces_forget_from_file() {
     let test_env = TestEnvironment::default();
     test_env.run_jj_in(".", ["git", "init", "main"]).success();
     let main_dir = test_env.work_dir("main");
-    main_dir
-        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
-        .success();
-    let secondary_dir = test_env.work_dir("secondary");
+    for name in ["second", "third", "fourth"] {
+        main_dir
+            .run_jj(["workspace", "add", &format!("../{name}")])
+            .success();
+    }
 
-    // Both workspaces show up when we list them
-    let output = main_dir.run_jj(["workspace", "list"]);
+    test_env.work_dir("").write_file(
+        "to_forget.txt",
+        "# throwaway workspaces\nsecond\n\n  third  \n",
+    );
+    let output = main_dir.run_jj(["workspace", "forget", "--from-file", "../to_forget.txt"]);
+    insta::assert_snapshot!(output, @"");
+    let output = main_dir.run_jj(["op", "log", "-n1", "-T", "description"]);
     insta::assert_snapshot!(output, @r"
-    default: qpvuntsm e8849ae1 (empty) (no description set)
-    second: uuqppmxq 94f41578 (empty) (no description set)
+    @  forget workspaces second, third
+    [EOF]
+    ");
+
+    // Names are read from stdin and combined with the positional names
+    main_dir.run_jj(["workspace", "add", "../fifth"]).success();
+    let output = main_dir.run_jj_with(|cmd| {
+        cmd.args(["workspace", "forget", "--from-file", "-", "fifth"])
+            .write_stdin("fourth\n")
+    });
+    insta::assert_snapshot!(output, @"");
+    let output = main_dir.run_jj(["workspace", "list", "-T", "name ++ '\n'"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    [EOF]
+    ");
+
+    // An empty list doesn't forget the current workspace
+    test_env.work_dir("").write_file("empty.txt", "# nothing\n");
+    let output = main_dir.run_jj(["workspace", "forget", "--from-file", "../empty.txt"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No workspaces to forget
+    [EOF]
+    [exit status: 1]
+    ");
+
+    let output = main_dir.run_jj(["workspace", "forget", "--from-file", "../missing.txt"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Failed to read ../missing.txt
+    Caused by: No such file or directory (os error 2)
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_workspaces_forget_with_changes() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    let second_dir = test_env.work_dir("second");
+    second_dir.write_file("file", "contents");
+    second_dir.run_jj(["status"]).success();
+
+    // Confirmation is required unless --yes is given
+    let output = main_dir.run_jj(["workspace", "forget", "second", "third"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following workspaces have changes in their working copies:
+      second ($TEST_ENV/second)
+    Error: Cannot prompt for input since the output is not connected to a terminal
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj_with(|cmd| {
+        force_interactive(cmd)
+            .args(["workspace", "forget", "second", "third"])
+            .write_stdin("n\n")
+    });
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following workspaces have changes in their working copies:
+      second ($TEST_ENV/second)
+    Forget them anyway? (yn): Error: Aborted
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj(["workspace", "forget", "--yes", "second"]);
+    insta::assert_snapshot!(output, @"");
+
+    // The current workspace and workspaces without changes don't need it
+    main_dir.write_file("file", "contents");
+    let output = main_dir.run_jj(["workspace", "forget", "default", "third"]);
+    insta::assert_snapshot!(output, @"");
+}
+
+#[test]
+fn test_workspaces_forget_and_delete() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+
+    // The current workspace and the workspace containing the repo can't be
+    // deleted
+    let output = main_dir.run_jj(["workspace", "forget", "--and-delete", "--yes"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Refusing to delete the current workspace default ($TEST_ENV/main)
+    [EOF]
+    [exit status: 1]
+    ");
+    let second_dir = test_env.work_dir("second");
+    let output = second_dir.run_jj(["workspace", "forget", "--and-delete", "--yes", "default"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Refusing to delete workspace default ($TEST_ENV/main) since it contains the repo
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // Confirmation is required unless --yes is given
+    let output = main_dir.run_jj(["workspace", "forget", "--and-delete", "second"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following directories will be deleted:
+      $TEST_ENV/second
+    Error: Cannot prompt for input since the output is not connected to a terminal
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj_with(|cmd| {
+        force_interactive(cmd)
+            .args(["workspace", "forget", "--and-delete", "second"])
+            .write_stdin("n\n")
+    });
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following directories will be deleted:
+      $TEST_ENV/second
+    Continue? (yn): Error: Aborted
+    [EOF]
+    [exit status: 1]
+    ");
+    assert!(test_env.env_root().join("second").exists());
+
+    let output = main_dir.run_jj_with(|cmd| {
+        force_interactive(cmd)
+            .args(["workspace", "forget", "--and-delete", "second"])
+            .write_stdin("y\n")
+    });
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following directories will be deleted:
+      $TEST_ENV/second
+    Continue? (yn): Deleted $TEST_ENV/second
+    [EOF]
+    ");
+    assert!(!test_env.env_root().join("second").exists());
+
+    let output = main_dir.run_jj(["workspace", "forget", "--and-delete", "--yes", "third"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Deleted $TEST_ENV/third
+    [EOF]
+    ");
+    assert!(!test_env.env_root().join("third").exists());
+    let output = main_dir.run_jj(["workspace", "list"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_forget_and_delete_containing_current() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../outer"]).success();
+    // The forgotten workspace keeps its recorded root, which contains the
+    // workspace added next
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    main_dir
+        .run_jj(["workspace", "add", "../outer/inner"])
+        .success();
+
+    let inner_dir = test_env.work_dir("outer/inner");
+    let output = inner_dir.run_jj(["workspace", "forget", "--and-delete", "--yes", "outer"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Refusing to delete workspace outer ($TEST_ENV/outer) since it contains the current workspace
+    [EOF]
+    [exit status: 1]
+    ");
+    assert!(test_env.env_root().join("outer/inner").exists());
+}
+
+#[test]
+fn test_workspaces_forget_and_delete_report_size() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    // Orphans whose directories have known contents
+    let first_dir = test_env.work_dir("first");
+    first_dir.write_file("a", "a".repeat(1000));
+    first_dir.write_file("sub/b", "b".repeat(2000));
+    let second_dir = test_env.work_dir("second");
+    second_dir.write_file("c", "c".repeat(100));
+    main_dir
+        .run_jj_with(|cmd| {
+            cmd.args(["workspace", "import", "--stdin-paths"])
+                .write_stdin("../first\n../second\n")
+        })
+        .success();
+
+    let output = main_dir.run_jj(["workspace", "forget", "--report-size", "first"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the following required arguments were not provided:
+      --and-delete
+
+    Usage: jj workspace forget --and-delete --report-size <WORKSPACES>...
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+
+    let output = main_dir.run_jj([
+        "workspace",
+        "forget",
+        "--and-delete",
+        "--report-size",
+        "--yes",
+        "first",
+        "second",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Deleted $TEST_ENV/first (about 2.9KiB)
+    Deleted $TEST_ENV/second (about 100.0B)
+    Freed about 3.0KiB in total
+    [EOF]
+    ");
+    assert!(!test_env.env_root().join("first").exists());
+}
+
+/// Test context of commit summary template
+#[test]
+fn test_list_workspaces_template() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    test_env.add_config(
+        r#"
+        templates.workspace_list = """name ++ ": " ++ target.commit_id().short() ++ " " ++
+                                      target.description().first_line() ++
+                                      if(target.current_working_copy(), " (current)") ++ "\n""""
+        "#,
+    );
+    let main_dir = test_env.work_dir("main");
+    let secondary_dir = test_env.work_dir("secondary");
+
+    main_dir.write_file("file", "contents");
+    main_dir.run_jj(["commit", "-m", "initial"]).success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+
+    // "current_working_copy" should point to the workspace we operate on
+    let output = main_dir.run_jj(["workspace", "list"]);
+    insta::assert_snapshot!(output, @r"
+    default: 504e3d8c1bcd  (current)
+    second: 058f604dffcd 
+    [EOF]
+    ");
+
+    let output = secondary_dir.run_jj(["workspace", "list"]);
+    insta::assert_snapshot!(output, @r"
+    default: 504e3d8c1bcd 
+    second: 058f604dffcd  (current)
+    [EOF]
+    ");
+
+    // Using template option
+    let template = r#"name ++ ": " ++ target.commit_id().short() ++ "\n""#;
+    let output = main_dir.run_jj(["workspace", "list", "-T", template]);
+    insta::assert_snapshot!(output, @r"
+    default: 504e3d8c1bcd
+    second: 058f604dffcd
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_without_working_copy_commit() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+
+    // Restoring the repo forgets the working-copy commit of the new workspace,
+    // but its path is still recorded
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    let output = main_dir.run_jj(["workspace", "list"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    second ($TEST_ENV/secondary): (no working-copy commit)
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_util_gc_prunes_workspace_store() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("secondary")).unwrap();
+
+    // Only the orphan whose directory is gone is forgotten
+    let output = main_dir.run_jj(["util", "gc"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Forgot the roots of removed workspaces: second
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--include-orphans"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_exists_only_orphans() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    main_dir.run_jj(["workspace", "add", "../fourth"]).success();
+    // third and fourth become orphans
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    std::fs::remove_dir_all(test_env.env_root().join("fourth")).unwrap();
+
+    let template = r#"name ++ "\n""#;
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--no-orphans"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    second
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--no-orphans",
+        "--exists-only",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    default
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template]);
+    insta::assert_snapshot!(output, @r"
+    default
+    fourth ($TEST_ENV/fourth): (no working-copy commit)
+    second
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--no-orphans",
+        "--include-orphans",
+        "--exists-only",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    default
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_paths_only() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("third")).unwrap();
+
+    // Workspaces whose directory is gone are skipped by default
+    let output = main_dir.run_jj(["workspace", "list", "--paths-only"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    $TEST_ENV/second
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--paths-only", "--include-missing"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    $TEST_ENV/second
+    $TEST_ENV/third
+    [EOF]
+    ");
+
+    let output = main_dir
+        .run_jj(["workspace", "list", "--paths-only", "--null"])
+        .success();
+    assert_eq!(output.stdout.raw().matches('\0').count(), 2);
+    assert!(!output.stdout.raw().contains('\n'));
+
+    // Only the paths can be printed
+    let output = main_dir.run_jj(["workspace", "list", "--paths-only", "--long"]);
+    insta::assert_snapshot!(output.strip_stderr_last_line(), @r"
+    ------- stderr -------
+    error: the argument '--paths-only' cannot be used with '--long'
+
+    Usage: jj workspace list --paths-only [NAMES]...
+
+    [EOF]
+    [exit status: 2]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--null"]);
+    insta::assert_snapshot!(output.strip_stderr_last_line(), @r"
+    ------- stderr -------
+    error: the following required arguments were not provided:
+      --paths-only
+
+    Usage: jj workspace list --paths-only --null [NAMES]...
+
+    [EOF]
+    [exit status: 2]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_orphans_only() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../kept"]).success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "gone one", "../gone"])
+        .success();
+    // kept and "gone one" become orphans
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("gone")).unwrap();
+
+    // Only a directory that's gone can be added again
+    let output = main_dir.run_jj(["workspace", "list", "--orphans-only"]);
+    insta::assert_snapshot!(output, @r#"
+    "gone one" ($TEST_ENV/gone): (no working-copy commit)
+      jj workspace forget 'gone one'
+      jj workspace add --name 'gone one' $TEST_ENV/gone
+    kept ($TEST_ENV/kept): (no working-copy commit)
+      jj workspace forget kept
+    [EOF]
+    "#);
+
+    // The suggested commands resolve the orphans
+    main_dir
+        .run_jj(["workspace", "add", "--name", "gone one", "../gone"])
+        .success();
+    main_dir.run_jj(["workspace", "forget", "kept"]).success();
+    let output = main_dir.run_jj(["workspace", "list", "--orphans-only"]);
+    insta::assert_snapshot!(output, @"");
+    let output = main_dir.run_jj(["workspace", "list", "-T", r#"name ++ "\n""#]);
+    insta::assert_snapshot!(output, @r#"
+    default
+    "gone one"
+    second
+    [EOF]
+    "#);
+}
+
+#[test]
+fn test_list_workspaces_missing_delete() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../fourth"]).success();
+    // fourth becomes an orphan
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    std::fs::remove_dir_all(test_env.env_root().join("fourth")).unwrap();
+
+    let template = r#"name ++ "\n""#;
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--missing",
+        "--no-orphans",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    second
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--missing"]);
+    insta::assert_snapshot!(output, @r"
+    fourth ($TEST_ENV/fourth): (no working-copy commit)
+    second
+    [EOF]
+    ");
+
+    // Forgetting requires --missing and --yes
+    let output = main_dir.run_jj(["workspace", "list", "--missing", "--delete"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the following required arguments were not provided:
+      --yes
+
+    Usage: jj workspace list --missing --yes --delete [NAMES]...
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--delete", "--yes"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the following required arguments were not provided:
+      --missing
+
+    Usage: jj workspace list --missing --yes --delete [NAMES]...
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--missing",
+        "--include-orphans",
+        "--forget",
+        "--yes",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    fourth ($TEST_ENV/fourth): (no working-copy commit)
+    second
+    [EOF]
+    ------- stderr -------
+    Forgot 2 workspaces
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--include-orphans"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    third
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["op", "log", "-n1", "-T", "description"]);
+    insta::assert_snapshot!(output, @r"
+    @  forget workspace second
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_limit_offset() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+
+    let template = r#"name ++ "\n""#;
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--limit", "2"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    second
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--offset", "1"]);
+    insta::assert_snapshot!(output, @r"
+    second
+    third
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--offset",
+        "1",
+        "--limit",
+        "1",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    second
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--offset", "5"]);
+    insta::assert_snapshot!(output, @"");
+}
+
+#[test]
+fn test_list_workspaces_name_glob() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "feature-a", "../feature-a"])
+        .success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "feature-b", "../feature-b"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "bugfix", "../bugfix"])
+        .success();
+
+    let template = r#"name ++ "\n""#;
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "feature-*"]);
+    insta::assert_snapshot!(output, @r"
+    feature-a
+    feature-b
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "bug*", "default"]);
+    insta::assert_snapshot!(output, @r"
+    bugfix
+    default
+    [EOF]
+    ");
+
+    // feature-b becomes an orphan, which is listed along with the others
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "feature-*"]);
+    insta::assert_snapshot!(output, @r"
+    feature-a
+    feature-b ($TEST_ENV/feature-b): (no working-copy commit)
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--orphans-only", "feature-*"]);
+    insta::assert_snapshot!(output, @r"
+    feature-b ($TEST_ENV/feature-b): (no working-copy commit)
+      jj workspace forget feature-b
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "--paths-only", "feature-*"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/feature-a
+    $TEST_ENV/feature-b
+    [EOF]
+    ");
+
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "release-*"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Warning: No matching workspaces for patterns: release-*
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_long() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    // A workspace that predates the store has no recorded path
+    std::fs::remove_file(
+        main_dir
+            .root()
+            .join(".jj")
+            .join("repo")
+            .join("workspace_store")
+            .join("second"),
+    )
+    .unwrap();
+
+    let output = main_dir.run_jj(["workspace", "list", "--long"]);
+    // The owner is only recorded on Unix
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt as _;
+        let uid = main_dir.root().metadata().unwrap().uid();
+        assert!(
+            output
+                .stdout
+                .raw()
+                .contains(&format!("  Owner: uid {uid}\n"))
+        );
+    }
+    let output = output.normalize_stdout_with(|s| {
+        let s = regex::Regex::new(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d{3} [+-]\d{2}:\d{2}")
+            .unwrap()
+            .replace_all(&s, "<timestamp>")
+            .replace(env!("CARGO_PKG_VERSION"), "<version>");
+        regex::Regex::new(r"(?m)^  Owner: .*\n")
+            .unwrap()
+            .replace_all(&s, "")
+            .into_owned()
+    });
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+      Path recorded: <timestamp>
+      Recorded by: jj <version>
+    second: uuqppmxq 94f41578 (empty) (no description set)
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_sort_mtime() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    for name in ["a", "b", "c"] {
+        main_dir
+            .run_jj(["workspace", "add", "--name", name, &format!("../{name}")])
+            .success();
+    }
+    let store_dir = main_dir
+        .root()
+        .join(".jj")
+        .join("repo")
+        .join("workspace_store");
+    let set_mtime = |name: &str, secs: u64| {
+        let file = std::fs::File::options()
+            .write(true)
+            .open(store_dir.join(name))
+            .unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            .unwrap();
+    };
+    set_mtime("a", 1000);
+    set_mtime("b", 2000);
+    set_mtime("c", 1000);
+    // A workspace that predates the store has no recorded path. Run from
+    // another workspace, since the root of the current one would be recorded.
+    std::fs::remove_file(store_dir.join("default")).unwrap();
+    let a_dir = test_env.work_dir("a");
+
+    let output = a_dir.run_jj(["workspace", "list", "--sort=mtime", "-T", "name ++ '\n'"]);
+    insta::assert_snapshot!(output, @r"
+    b
+    a
+    c
+    default
+    [EOF]
+    ");
+
+    let output = a_dir.run_jj(["workspace", "list", "--sort=name", "-T", "name ++ '\n'"]);
+    insta::assert_snapshot!(output, @r"
+    a
+    b
+    c
+    default
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspace_store_created_by_recording_commands_only() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    // The repo was created before workspace roots were recorded
+    let store_dir = main_dir.root().join(".jj/repo/workspace_store");
+    std::fs::remove_dir_all(&store_dir).unwrap();
+
+    let output = main_dir.run_jj(["workspace", "list", "-T", "name ++ '\n'"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    [EOF]
+    ");
+    main_dir.run_jj(["workspace", "root", "--all"]).success();
+    let output = main_dir.run_jj(["workspace", "doctor"]);
+    insta::assert_snapshot!(output, @r"
+    Workspaces without a recorded root:
+      default
+    [EOF]
+    ------- stderr -------
+    Error: Found 1 problem
+    Hint: Run `jj workspace doctor --fix` to apply the safe repairs.
+    [EOF]
+    [exit status: 1]
+    ");
+    main_dir.run_jj(["describe", "-m", "first"]).success();
+    assert!(!store_dir.exists());
+
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    assert!(store_dir.exists());
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/second
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_group_by_fs() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    std::fs::remove_dir_all(test_env.env_root().join("third")).unwrap();
+
+    let output = main_dir
+        .run_jj(["workspace", "list", "--group-by=fs", "-T", "name ++ '\n'"])
+        .normalize_stdout_with(|s| {
+            regex::Regex::new(r"(device|drive) \w+")
+                .unwrap()
+                .replace_all(&s, "<fs>")
+                .into_owned()
+        });
+    insta::assert_snapshot!(output, @r"
+    Workspaces on <fs>:
+    default
+    second
+    Workspaces on an unknown file system:
+    third
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_table() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    // third becomes an orphan, and second goes missing
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    let two_days_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(2 * 24 * 3600);
+    for name in ["main", "third"] {
+        let working_copy_dir = test_env
+            .env_root()
+            .join(name)
+            .join(".jj")
+            .join("working_copy");
+        for entry in std::fs::read_dir(working_copy_dir).unwrap() {
+            let file = std::fs::File::open(entry.unwrap().path()).unwrap();
+            file.set_modified(two_days_ago).unwrap();
+        }
+    }
+
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "--format=table",
+        "--include-orphans",
+        "--ignore-working-copy",
+    ]);
+    // The columns are aligned
+    let column_starts = |line: &str| {
+        regex::Regex::new(r" {2,}")
+            .unwrap()
+            .find_iter(line)
+            .map(|m| m.end())
+            .collect::<Vec<_>>()
+    };
+    let stdout = output.stdout.raw();
+    let starts = stdout.lines().map(column_starts).collect::<Vec<_>>();
+    assert!(starts.windows(2).all(|w| w[0] == w[1]), "{stdout}");
+    // The width of the path column depends on $TEST_ENV
+    let output = output.normalize_stdout_with(|s| {
+        regex::Regex::new(r" {2,}")
+            .unwrap()
+            .replace_all(&s, " | ")
+            .into_owned()
+    });
+    insta::assert_snapshot!(output, @r"
+    Name | Path | State | Last used
+    default | $TEST_ENV/main | present | 2 days ago
+    second | $TEST_ENV/second | missing | -
+    third | $TEST_ENV/third | orphan | 2 days ago
+    [EOF]
+    ");
+
+    // Paths are shortened in the middle to fit the terminal
+    let output = main_dir.run_jj_with(|cmd| {
+        cmd.args([
+            "workspace",
+            "list",
+            "--format=table",
+            "--ignore-working-copy",
+        ])
+        .env("COLUMNS", "40")
+    });
+    let stdout = output.stdout.raw();
+    assert!(
+        stdout.lines().all(|line| line.chars().count() <= 40),
+        "{stdout}"
+    );
+    assert!(
+        stdout.lines().skip(1).all(|line| line.contains('…')),
+        "{stdout}"
+    );
+
+    // The template is used if the output isn't a terminal
+    let output = main_dir.run_jj(["workspace", "list", "--ignore-working-copy"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    second: uuqppmxq 94f41578 (empty) (no description set)
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+
+    // ...unless it's colored as if it were one
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "--ignore-working-copy",
+        "--color=always",
+    ]);
+    assert!(output.stdout.raw().starts_with("Name "), "{output}");
+
+    // A configured template is used anyway
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "--ignore-working-copy",
+        "--color=always",
+        r#"--config=templates.workspace_list='name ++ "\n"'"#,
+    ]);
+    insta::assert_snapshot!(output, @r"
+    default
+    second
+    third ($TEST_ENV/third): [38;5;1m(no working-copy commit)[39m
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_since() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../first"]).success();
+    let since_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    // third becomes an orphan
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+
+    // The operation that added the default workspace isn't recorded, so the
+    // time its path was recorded is compared with the (fixed) time of the
+    // operation instead
+    let template = r#"name ++ "\n""#;
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--since", &since_opid]);
+    insta::assert_snapshot!(output, @r"
+    default
+    second
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--include-orphans",
+        "--since",
+        &since_opid,
+    ]);
+    insta::assert_snapshot!(output, @r"
+    default
+    second
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+    let entry_file = main_dir.root().join(".jj/repo/workspace_store/default");
+    std::fs::File::options()
+        .write(true)
+        .open(entry_file)
+        .unwrap()
+        .set_modified(std::time::UNIX_EPOCH)
+        .unwrap();
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--since", &since_opid]);
+    insta::assert_snapshot!(output, @r"
+    second
+    third ($TEST_ENV/third): (no working-copy commit)
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", template, "--since", "@"]);
+    insta::assert_snapshot!(output, @"");
+}
+
+#[test]
+fn test_list_workspaces_dead_for() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    // third goes missing, so the time its path was recorded is used
+    std::fs::remove_dir_all(test_env.env_root().join("third")).unwrap();
+    let forty_days_ago =
+        std::time::SystemTime::now() - std::time::Duration::from_secs(40 * 24 * 3600);
+    let working_copy_dir = test_env.env_root().join("second/.jj/working_copy");
+    for entry in std::fs::read_dir(working_copy_dir).unwrap() {
+        let file = std::fs::File::open(entry.unwrap().path()).unwrap();
+        file.set_modified(forty_days_ago).unwrap();
+    }
+
+    let template = r#"name ++ "\n""#;
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--dead-for=30d",
+        "--ignore-working-copy",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    second
+      Last used: 1 month ago
+    [EOF]
+    ");
+    let entry_file = main_dir.root().join(".jj/repo/workspace_store/third");
+    std::fs::File::options()
+        .write(true)
+        .open(entry_file)
+        .unwrap()
+        .set_modified(forty_days_ago)
+        .unwrap();
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--dead-for=4w",
+        "--ignore-working-copy",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    second
+      Last used: 1 month ago
+    third
+      Last used: 1 month ago
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--dead-for=6w",
+        "--ignore-working-copy",
+    ]);
+    insta::assert_snapshot!(output, @"");
+
+    // Durations and dates are parsed like the date patterns of revsets
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--dead-for=5 weeks",
+        "--ignore-working-copy",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    second
+      Last used: 1 month ago
+    third
+      Last used: 1 month ago
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--dead-for=30 days ago",
+        "--ignore-working-copy",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    second
+      Last used: 1 month ago
+    third
+      Last used: 1 month ago
+    [EOF]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "-T",
+        template,
+        "--dead-for=2001-01-01",
+        "--ignore-working-copy",
+    ]);
+    insta::assert_snapshot!(output, @"");
+
+    let output = main_dir.run_jj(["workspace", "list", "--dead-for=30 fortnights"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: invalid value '30 fortnights' for '--dead-for <DURATION>': expected month or time unit as position 3..13
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_json() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    // third becomes an orphan, and second goes missing
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+
+    let output = main_dir.run_jj(["workspace", "list", "--json", "--include-orphans"]);
+    insta::assert_snapshot!(output, @r#"
+    {
+      "schema": 1,
+      "entries": [
+        {
+          "name": "default",
+          "path": "$TEST_ENV/main",
+          "exists": true,
+          "orphan": false
+        },
+        {
+          "name": "second",
+          "path": "$TEST_ENV/second",
+          "exists": false,
+          "orphan": false
+        },
+        {
+          "name": "third",
+          "path": "$TEST_ENV/third",
+          "exists": true,
+          "orphan": true
+        }
+      ]
+    }
+    [EOF]
+    "#);
+
+    // The stats count the workspaces skipped by --limit too
+    let output = main_dir.run_jj([
+        "workspace",
+        "list",
+        "--json",
+        "--stats",
+        "--include-orphans",
+        "--limit=1",
+    ]);
+    insta::assert_snapshot!(output, @r#"
+    {
+      "schema": 1,
+      "stats": {
+        "total": 3,
+        "missing": 1,
+        "orphaned": 1
+      },
+      "entries": [
+        {
+          "name": "default",
+          "path": "$TEST_ENV/main",
+          "exists": true,
+          "orphan": false
+        }
+      ]
+    }
+    [EOF]
+    "#);
+
+    let output = main_dir.run_jj(["workspace", "list", "--stats"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the following required arguments were not provided:
+      --json
+
+    Usage: jj workspace list --json --stats [NAMES]...
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+}
+
+#[test]
+fn test_workspaces_prune() {
+    let test_env = TestEnvironment::default();
+    // The pruned roots are restored by undo
+    test_env.add_config("workspace.tombstones = true");
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    main_dir.run_jj(["workspace", "add", "../fourth"]).success();
+
+    let output = main_dir.run_jj(["workspace", "prune"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    No workspaces to prune.
+    [EOF]
+    ");
+
+    // second goes missing, and third hasn't been used for a while
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    let forty_days_ago =
+        std::time::SystemTime::now() - std::time::Duration::from_secs(40 * 24 * 3600);
+    let working_copy_dir = test_env.env_root().join("third/.jj/working_copy");
+    for entry in std::fs::read_dir(working_copy_dir).unwrap() {
+        let file = std::fs::File::open(entry.unwrap().path()).unwrap();
+        file.set_modified(forty_days_ago).unwrap();
+    }
+
+    // Only missing workspaces are pruned by default
+    let output = main_dir.run_jj(["workspace", "prune"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Forgot 1 workspace
+      second: directory is missing
+    [EOF]
+    ");
+    main_dir.run_jj(["undo"]).success();
+
+    // Pruning by age requires confirmation
+    let output = main_dir.run_jj(["workspace", "prune", "--older-than=30d"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following workspaces haven't been used recently:
+      third (last used 1 month ago)
+    Error: Cannot prompt for input since the output is not connected to a terminal
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj_with(|cmd| {
+        force_interactive(cmd)
+            .args(["workspace", "prune", "--older-than=30d"])
+            .write_stdin("n\n")
+    });
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following workspaces haven't been used recently:
+      third (last used 1 month ago)
+    Forget them? (yn): Error: Aborted
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj(["workspace", "prune", "--older-than=30d", "--yes"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Forgot 2 workspaces
+      second: directory is missing
+      third: last used 1 month ago
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "list", "-T", r#"name ++ "\n""#]);
+    insta::assert_snapshot!(output, @r"
+    default
+    fourth
+    [EOF]
+    ");
+
+    // Both are restored at once
+    main_dir.run_jj(["undo"]).success();
+    let output = main_dir.run_jj(["workspace", "list", "-T", r#"name ++ "\n""#]);
+    insta::assert_snapshot!(output, @r"
+    default
+    fourth
+    second
+    third
+    [EOF]
+    ");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspaces_prune_owner() {
+    use std::os::unix::fs::MetadataExt as _;
+
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    let uid = std::fs::metadata(test_env.env_root()).unwrap().uid();
+
+    // Workspaces recorded by other users are kept
+    let output = main_dir.run_jj(["workspace", "prune", &format!("--owner={}", uid + 1)]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    No workspaces to prune.
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "prune", "--owner=no-such-user"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No such user: no-such-user
+    [EOF]
+    [exit status: 1]
+    ");
+
+    let output = main_dir.run_jj(["workspace", "prune", &format!("--owner={uid}")]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Forgot 1 workspace
+      second: directory is missing
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_list_workspaces_format_shell() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "my-ws", "../my-ws"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "my_ws", "../with space"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "my.ws", "../third"])
+        .success();
+
+    // Names are sanitized, and colliding ones are suffixed. Paths are quoted.
+    let output = main_dir.run_jj(["workspace", "list", "--format=shell"]);
+    insta::assert_snapshot!(output, @r"
+    JJ_WS_default=$TEST_ENV/main
+    JJ_WS_my_ws=$TEST_ENV/my-ws
+    JJ_WS_my_ws_2=$TEST_ENV/third
+    JJ_WS_my_ws_3='$TEST_ENV/with space'
+    [EOF]
+    ");
+
+    // The usual filters apply
+    let output = main_dir.run_jj(["workspace", "list", "--format=shell", "my_*"]);
+    insta::assert_snapshot!(output, @r"
+    JJ_WS_my_ws='$TEST_ENV/with space'
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_whose_dir() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "nested", "../second/nested"])
+        .success();
+    test_env.work_dir("").create_dir_all("second/nested/sub");
+
+    // The workspace recorded at the directory
+    let output = main_dir.run_jj(["workspace", "whose-dir", "../second"]);
+    insta::assert_snapshot!(output, @r"
+    second
+    [EOF]
+    ");
+    // The workspaces inside the directory
+    let output = main_dir.run_jj(["workspace", "whose-dir", ".."]);
+    insta::assert_snapshot!(output, @r"
+    default
+    nested
+    second
+    [EOF]
+    ");
+    // The innermost workspace containing the directory
+    let output = main_dir.run_jj(["workspace", "whose-dir", "../second/nested/sub"]);
+    insta::assert_snapshot!(output, @r"
+    nested
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "whose-dir", "src"]);
+    insta::assert_snapshot!(output, @r"
+    default
+    [EOF]
+    ");
+
+    let output = main_dir.run_jj(["workspace", "whose-dir", "../elsewhere"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No workspace found for $TEST_ENV/elsewhere
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_workspaces_import_stdin_paths() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    main_dir.run_jj(["workspace", "add", "../third"]).success();
+    // The recorded roots were lost
+    let store_dir = main_dir.root().join(".jj/repo/workspace_store");
+    std::fs::remove_file(store_dir.join("second")).unwrap();
+    std::fs::remove_file(store_dir.join("third")).unwrap();
+
+    let import = |stdin: &str, extra_args: &[&str]| {
+        main_dir.run_jj_with(|cmd| {
+            cmd.args(["workspace", "import", "--stdin-paths"])
+                .args(extra_args)
+                .write_stdin(stdin)
+        })
+    };
+    let output = import("../second\0../third\0", &["-0"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Recorded the roots of workspaces: second, third
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "third"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/third
+    [EOF]
+    ");
+
+    // Importing again changes nothing
+    let output = import("../second\n", &[]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Nothing changed.
+    [EOF]
+    ");
+
+    // Directories with the same basename can't both be recorded
+    test_env.work_dir("").create_dir_all("a/fourth");
+    test_env.work_dir("").create_dir_all("b/fourth");
+    let output = import("../a/fourth\n../b/fourth\n", &[]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Warning: Directories named after workspace fourth:
+      $TEST_ENV/a/fourth
+      $TEST_ENV/b/fourth
+    Error: Some directories would be recorded for the same workspace
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // A different root is only recorded with --overwrite
+    test_env.work_dir("").create_dir_all("moved/second");
+    let output = import("../moved/second\n../a/fourth\n", &[]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following workspaces already have a different root:
+      second: $TEST_ENV/second
+    Error: Refusing to replace the recorded roots
+    Hint: Use --overwrite to replace them.
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = import("../moved/second\n../a/fourth\n", &["--overwrite"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    The following workspaces already have a different root:
+      second: $TEST_ENV/second
+    Recorded the roots of workspaces: fourth, second
+    Warning: Workspace fourth has no working-copy commit, so it's an orphan
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/moved/second
+    [EOF]
+    ");
+
+    let output = import("../missing\n", &[]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Not a directory: $TEST_ENV/missing
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = import("", &[]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No workspaces to import
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_workspaces_import_from_git_worktrees() {
+    let test_env = TestEnvironment::default();
+    test_env
+        .run_jj_in(".", ["git", "init", "--colocate", "main"])
+        .success();
+    let main_dir = test_env.work_dir("main");
+    // Only the `gitdir` files of the linked worktrees are read
+    let add_git_worktree = |id: &str, dir: &str| {
+        let worktree_git_dir = main_dir.root().join(".git/worktrees").join(id);
+        std::fs::create_dir_all(&worktree_git_dir).unwrap();
+        let dir = test_env.env_root().join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            worktree_git_dir.join("gitdir"),
+            format!("{}\n", dir.join(".git").display()),
+        )
+        .unwrap();
+    };
+    add_git_worktree("feature", "feature");
+    add_git_worktree("fix", "fixes/fix");
+    add_git_worktree("gone", "gone");
+    std::fs::remove_dir(test_env.env_root().join("gone")).unwrap();
+    // Too long once encoded as a file name
+    add_git_worktree("percent", &"%".repeat(100));
+
+    let output = main_dir.run_jj(["workspace", "import", "--from-git-worktrees", "--dry-run"]);
+    insta::assert_snapshot!(output, @r#"
+    ------- stderr -------
+    Warning: Skipping Git worktree gone since its directory is gone
+    Warning: Skipping Git worktree $TEST_ENV/%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%: Workspace name "%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%" can't be stored
+    Would record the roots of workspaces:
+      feature: $TEST_ENV/feature
+      fix: $TEST_ENV/fixes/fix
+    [EOF]
+    "#);
+    let output = main_dir.run_jj(["workspace", "list", "--orphans-only"]);
+    insta::assert_snapshot!(output, @"");
+
+    let output = main_dir.run_jj(["workspace", "import", "--from-git-worktrees"]);
+    insta::assert_snapshot!(output, @r#"
+    ------- stderr -------
+    Warning: Skipping Git worktree gone since its directory is gone
+    Warning: Skipping Git worktree $TEST_ENV/%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%: Workspace name "%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%" can't be stored
+    Recorded the roots of workspaces: feature, fix
+    Warning: Workspace feature has no working-copy commit, so it's an orphan
+    Warning: Workspace fix has no working-copy commit, so it's an orphan
+    [EOF]
+    "#);
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "fix"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/fixes/fix
+    [EOF]
+    ");
+    // The worktrees aren't jj workspaces, but they're expected
+    let output = main_dir.run_jj(["workspace", "list", "--check"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    No problems found.
+    [EOF]
+    ");
+
+    // Worktrees named after the same workspace aren't recorded
+    add_git_worktree("fix1", "other/fix");
+    let output = main_dir.run_jj(["workspace", "import", "--from-git-worktrees"]);
+    insta::assert_snapshot!(output, @r#"
+    ------- stderr -------
+    Warning: Skipping Git worktree gone since its directory is gone
+    Warning: Skipping Git worktree $TEST_ENV/%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%: Workspace name "%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%" can't be stored
+    Warning: Directories named after workspace fix:
+      $TEST_ENV/fixes/fix
+      $TEST_ENV/other/fix
+    Error: Some directories would be recorded for the same workspace
+    [EOF]
+    [exit status: 1]
+    "#);
+
+    let output = main_dir.run_jj([
+        "workspace",
+        "import",
+        "--from-git-worktrees",
+        "--stdin-paths",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the argument '--from-git-worktrees' cannot be used with '--stdin-paths'
+
+    Usage: jj workspace import <--stdin-paths|--from-git-worktrees>
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+
+    // The imported roots aren't pruned even if the directory is gone
+    std::fs::remove_dir(test_env.env_root().join("feature")).unwrap();
+    main_dir.run_jj(["util", "gc"]).success();
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "feature"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/feature
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_info() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    main_dir.run_jj(["describe", "-m", "main work"]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("secondary")).unwrap();
+
+    let normalize = |s: String| {
+        let s = regex::Regex::new(
+            r"\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}(\.\d+)? ?([+-]\d{2}:\d{2}|Z)",
+        )
+        .unwrap()
+        .replace_all(&s, "<timestamp>")
+        .replace(env!("CARGO_PKG_VERSION"), "<version>");
+        regex::Regex::new(r"(?m)^  \x22owner_uid\x22: .*\n|^Owner: .*\n")
+            .unwrap()
+            .replace_all(&s, "")
+            .into_owned()
+    };
+    let output = main_dir
+        .run_jj(["workspace", "info"])
+        .normalize_stdout_with(normalize);
+    insta::assert_snapshot!(output, @r"
+    Name: default
+    Path: $TEST_ENV/main
+    Exists: yes
+    Path recorded: <timestamp>
+    Recorded by: jj <version>
+    Working copy: qpvuntsmwlqt 9bf9b574a3d2
+    Description: main work
+    [EOF]
+    ");
+
+    let output = main_dir
+        .run_jj(["workspace", "info", "second", "--format=json"])
+        .normalize_stdout_with(normalize);
+    insta::assert_snapshot!(output, @r#"
+    {
+      "name": "second",
+      "path": "$TEST_ENV/secondary",
+      "exists": false,
+      "path_recorded": "<timestamp>",
+      "recorded_by_version": "<version>",
+      "created_at_op_id": "ac06581a5715e74d82a5e0f7b5429fdc3295d11d50f080916fbab3082e17448bfb30b3613ae4278b05a24ea90a12dbd7c164a9e9191ac147c4065171aefd4cb5",
+      "working_copy": {
+        "change_id": "uuqppmxqsxsnvoxuwlkqvswowpyprsmr",
+        "commit_id": "94f41578a9e101e2c85877f3b4eaa9f5e915783f",
+        "description": ""
+      }
+    }
+    [EOF]
+    "#);
+
+    // The recorded operation is the one that added the workspace
+    let output = main_dir.run_jj(["workspace", "info", "second", "--format=json"]);
+    let info: serde_json::Value = serde_json::from_str(&output.stdout.into_raw()).unwrap();
+    let op_id = info["created_at_op_id"].as_str().unwrap();
+    let output = main_dir.run_jj(["op", "log", "-n1", "--at-op", op_id, "-T", "description"]);
+    insta::assert_snapshot!(output, @r"
+    @  create initial working-copy commit in workspace second
+    [EOF]
+    ");
+
+    let output = main_dir.run_jj(["workspace", "info", "unknown"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No such workspace: unknown
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+/// Test getting the workspace root from primary and secondary workspaces
+#[test]
+fn test_workspaces_root() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let secondary_dir = test_env.work_dir("secondary");
+
+    let output = main_dir.run_jj(["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    let main_subdir_dir = main_dir.create_dir("subdir");
+    let output = main_subdir_dir.run_jj(["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+
+    main_dir
+        .run_jj(["workspace", "add", "--name", "secondary", "../secondary"])
+        .success();
+    let output = secondary_dir.run_jj(["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/secondary
+    [EOF]
+    ");
+    let secondary_subdir_dir = secondary_dir.create_dir("subdir");
+    let output = secondary_subdir_dir.run_jj(["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/secondary
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_other_workspaces() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let secondary_dir = test_env.work_dir("secondary");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/secondary
+    [EOF]
+    ");
+    let output = secondary_dir.run_jj(["workspace", "root", "--workspace", "default"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    second: $TEST_ENV/secondary
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "unknown"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No such workspace: unknown
+    Hint: Run `jj workspace list` to see the available workspaces.
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // The recorded paths follow renames and forgets
+    secondary_dir
+        .run_jj(["workspace", "rename", "third"])
+        .success();
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    third: $TEST_ENV/secondary
+    [EOF]
+    ");
+    main_dir.run_jj(["workspace", "forget", "third"]).success();
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    [EOF]
+    ");
+
+    // Another repo can be queried without changing directory
+    let output = test_env.run_jj_in(".", ["-R", "main", "workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    [EOF]
+    ");
+
+    // The root of a workspace created before the store existed is recorded
+    // by the next command that modifies the repo and uses the store, so other
+    // workspaces can find it afterwards
+    std::fs::remove_file(
+        main_dir
+            .root()
+            .join(".jj")
+            .join("repo")
+            .join("workspace_store")
+            .join("default"),
+    )
+    .unwrap();
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    [EOF]
+    ");
+    let output = test_env.run_jj_in(
+        ".",
+        ["-R", "main", "workspace", "root", "--workspace", "default"],
+    );
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Root of workspace default is unknown
+    [EOF]
+    [exit status: 1]
+    ");
+    // -R pointing to a workspace of the current repo doesn't hide the root of
+    // the current workspace
+    let output = main_dir.run_jj(["-R", ".", "workspace", "root", "--workspace", "default"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    main_dir.run_jj(["workspace", "add", "../fourth"]).success();
+    main_dir.run_jj(["workspace", "forget", "fourth"]).success();
+    let output = test_env.run_jj_in(
+        ".",
+        ["-R", "main", "workspace", "root", "--workspace", "default"],
+    );
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_backfill_current() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+    // The repo predates the store
+    let store_dir = main_dir
+        .root()
+        .join(".jj")
+        .join("repo")
+        .join("workspace_store");
+    std::fs::remove_dir_all(&store_dir).unwrap();
+
+    // Commands that don't use the store don't record anything
+    main_dir.run_jj(["status"]).success();
+    assert!(!store_dir.exists());
+
+    // Only the root of the current workspace is known. Commands that don't
+    // modify the repo don't record it.
+    let output = main_dir.run_jj(["workspace", "list", "--paths-only"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    assert!(!store_dir.join("default").exists());
+
+    // It's recorded once the repo is modified
+    main_dir.run_jj(["workspace", "forget", "second"]).success();
+    assert!(store_dir.join("default").is_file());
+    assert!(!store_dir.join("second").exists());
+
+    // A forgotten workspace isn't recorded again
+    let second_dir = test_env.work_dir("second");
+    second_dir
+        .run_jj(["workspace", "list", "--paths-only", "--ignore-working-copy"])
+        .success();
+    assert!(!store_dir.join("second").exists());
+}
+
+#[test]
+fn test_workspaces_root_print0() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "my: ws", "../secondary"])
+        .success();
+
+    // Names are printed unquoted, each name and root followed by NUL
+    let output = main_dir.run_jj(["workspace", "root", "--all", "--print0"]);
+    assert_eq!(output.stdout.raw().matches('\0').count(), 4);
+    insta::assert_snapshot!(
+        output.normalize_stdout_with(|s| s.replace('\0', "<NUL>\n")), @r"
+    default<NUL>
+    $TEST_ENV/main<NUL>
+    my: ws<NUL>
+    $TEST_ENV/secondary<NUL>
+    [EOF]
+    ");
+
+    // Only --all is supported
+    let output = main_dir.run_jj(["workspace", "root", "--print0"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the following required arguments were not provided:
+      --all
+
+    Usage: jj workspace root --all --print0
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_check() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+
+    let output = main_dir.run_jj(["workspace", "root", "--check"]);
+    insta::assert_snapshot!(output, @"");
+    let output = main_dir.run_jj(["workspace", "root", "--check", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @"");
+
+    std::fs::remove_dir_all(test_env.env_root().join("secondary")).unwrap();
+    let output = main_dir.run_jj(["workspace", "root", "--check", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Root of workspace second ($TEST_ENV/secondary) does not exist
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = main_dir.run_jj([
+        "workspace",
+        "root",
+        "--check",
+        "--workspace",
+        "unknown",
+        "--quiet",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No such workspace: unknown
+    Hint: Run `jj workspace list` to see the available workspaces.
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspaces_root_no_canonicalize() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    std::fs::create_dir(test_env.env_root().join("real")).unwrap();
+    std::os::unix::fs::symlink(
+        test_env.env_root().join("real"),
+        test_env.env_root().join("link"),
+    )
+    .unwrap();
+
+    // By default, symlinks are resolved
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../link/second"])
+        .success();
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/real/second
+    [EOF]
+    ");
+
+    // The root is recorded as given, but normalized
+    main_dir
+        .run_jj([
+            "workspace",
+            "add",
+            "--name",
+            "third",
+            "--config=workspace.canonicalize-paths=false",
+            "../link/./third",
+        ])
+        .success();
+    let output = main_dir.run_jj([
+        "workspace",
+        "root",
+        "--workspace",
+        "third",
+        "--config=workspace.canonicalize-paths=false",
+    ]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/link/third
+    [EOF]
+    ");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_workspaces_root_display() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    std::fs::create_dir(test_env.env_root().join("real")).unwrap();
+    std::os::unix::fs::symlink(
+        test_env.env_root().join("real"),
+        test_env.env_root().join("link"),
+    )
+    .unwrap();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../link/second"])
+        .success();
+
+    // The root is resolved, but the path it was created with is kept for display
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/real/second
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second", "--display"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/link/second
+    [EOF]
+    ");
+
+    // Roots that were given without symlinks have no separate display path
+    let output = main_dir.run_jj(["workspace", "root", "--all", "--display"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    second: $TEST_ENV/link/second
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_env() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+    let run_with_env = |value: &str, args: &[&str]| {
+        main_dir.run_jj_with(|cmd| cmd.args(args).env("JJ_WORKSPACE", value))
+    };
+
+    // The variable replaces the current workspace
+    let output = run_with_env("second", &["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/second
+    [EOF]
+    ");
+    let output = run_with_env("second", &["workspace", "root", "--check"]);
+    insta::assert_snapshot!(output, @"");
+
+    // --workspace takes precedence, and --all ignores the variable
+    let output = run_with_env("second", &["workspace", "root", "--workspace", "default"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    let output = run_with_env("second", &["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    second: $TEST_ENV/second
+    [EOF]
+    ");
+
+    // An empty value is ignored
+    let output = run_with_env("", &["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+
+    let output = run_with_env("third", &["workspace", "root"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: No such workspace: third (set by $JJ_WORKSPACE)
+    Hint: Run `jj workspace list` to see the available workspaces.
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = run_with_env(" second", &["workspace", "root"]);
+    insta::assert_snapshot!(output, @r#"
+    ------- stderr -------
+    Error: $JJ_WORKSPACE is not a valid workspace name: " second"
+    [EOF]
+    [exit status: 1]
+    "#);
+}
+
+#[test]
+fn test_workspaces_doctor() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let output = main_dir.run_jj(["workspace", "doctor"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    No problems found.
+    [EOF]
+    ");
+
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    // third becomes an orphan
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    std::fs::remove_dir_all(test_env.env_root().join("third")).unwrap();
+    std::fs::write(main_dir.root().join(".jj/repo/workspace_store/bad"), "").unwrap();
+
+    let output = main_dir.run_jj(["workspace", "doctor"]);
+    insta::assert_snapshot!(output, @r"
+    Unreadable entries:
+      bad
+    Missing roots:
+      second ($TEST_ENV/second)
+      third ($TEST_ENV/third)
+    Workspaces without a working-copy commit:
+      third ($TEST_ENV/third)
+        jj workspace forget third
+        jj workspace add --name third $TEST_ENV/third
+    [EOF]
+    ------- stderr -------
+    Error: Found 4 problems
+    Hint: Run `jj workspace doctor --fix` to apply the safe repairs.
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // Only the safe repairs are applied
+    let output = main_dir.run_jj_with(|cmd| {
+        force_interactive(cmd)
+            .args(["workspace", "doctor", "--fix"])
+            .write_stdin("y\n")
+    });
+    insta::assert_snapshot!(output, @r"
+    Missing roots:
+      second ($TEST_ENV/second)
+    [EOF]
+    ------- stderr -------
+    The following workspaces have no working-copy commit and their roots are gone:
+      third
+    Forget their roots? (yn): Moved unreadable workspace store entries of: bad
+    Forgot the roots of removed workspaces: third
+    Error: Found 1 problem
+    [EOF]
+    [exit status: 1]
+    ");
+
+    main_dir.run_jj(["workspace", "forget", "second"]).success();
+    let output = main_dir.run_jj(["workspace", "doctor"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    No problems found.
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_list_check() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let output = main_dir.run_jj(["workspace", "list", "--check"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    No problems found.
+    [EOF]
+    ");
+
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    // third becomes an orphan
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+    std::fs::remove_dir_all(test_env.env_root().join("second")).unwrap();
+    std::fs::remove_dir_all(test_env.env_root().join("third")).unwrap();
+
+    // The exit code is the one of the first class of problems
+    let output = main_dir.run_jj(["workspace", "list", "--check"]);
+    insta::assert_snapshot!(output, @r"
+    missing	second
+    missing	third
+    orphan	third
+    [EOF]
+    ------- stderr -------
+    Error: Found 3 problems
+    [EOF]
+    [exit status: 12]
+    ");
+
+    std::fs::write(main_dir.root().join(".jj/repo/workspace_store/bad"), "").unwrap();
+    let output = main_dir.run_jj(["workspace", "list", "--check"]);
+    insta::assert_snapshot!(output, @r"
+    corrupt	bad
+    missing	second
+    missing	third
+    orphan	third
+    [EOF]
+    ------- stderr -------
+    Error: Found 4 problems
+    [EOF]
+    [exit status: 10]
+    ");
+
+    let output = main_dir.run_jj(["workspace", "list", "--check", "--json"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    error: the argument '--check' cannot be used with '--json'
+
+    Usage: jj workspace list --check [NAMES]...
+
+    For more information, try '--help'.
+    [EOF]
+    [exit status: 2]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_nested_name() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "team/feature", "../feature"])
+        .success();
+
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "team/feature"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/feature
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_current_token() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "@", "../at"])
+        .success();
+
+    // `@` is the current workspace
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "@"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    let output =
+        test_env
+            .work_dir("at")
+            .run_jj(["workspace", "root", "--workspace", "@", "--json"]);
+    insta::assert_snapshot!(output, @r#"
+    {
+      "name": "@",
+      "path": "$TEST_ENV/at",
+      "exists": true,
+      "created_at": "2001-02-03T04:05:08+07:00"
+    }
+    [EOF]
+    "#);
+
+    // A workspace named `@` can be referred to by quoting it
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", r#""@""#]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/at
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_json() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    std::fs::remove_dir_all(test_env.env_root().join("secondary")).unwrap();
+    // The default workspace was recorded without the operation that added it,
+    // so the time its root was recorded is reported instead.
+    std::fs::File::options()
+        .write(true)
+        .open(main_dir.root().join(".jj/repo/workspace_store/default"))
+        .unwrap()
+        .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000))
+        .unwrap();
+
+    let output = main_dir.run_jj(["workspace", "root", "--json"]);
+    insta::assert_snapshot!(output, @r#"
+    {
+      "name": "default",
+      "path": "$TEST_ENV/main",
+      "exists": true,
+      "created_at": "2001-09-09T01:46:40+00:00"
+    }
+    [EOF]
+    "#);
+    let output = main_dir.run_jj(["workspace", "root", "--json", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r#"
+    {
+      "name": "second",
+      "path": "$TEST_ENV/secondary",
+      "exists": false,
+      "created_at": "2001-02-03T04:05:08+07:00"
+    }
+    [EOF]
+    "#);
+    let output = main_dir.run_jj(["workspace", "root", "--json", "--all"]);
+    insta::assert_snapshot!(output, @r#"
+    [
+      {
+        "name": "default",
+        "path": "$TEST_ENV/main",
+        "exists": true,
+        "created_at": "2001-09-09T01:46:40+00:00"
+      },
+      {
+        "name": "second",
+        "path": "$TEST_ENV/secondary",
+        "exists": false,
+        "created_at": "2001-02-03T04:05:08+07:00"
+      }
+    ]
+    [EOF]
+    "#);
+
+    // Always an array with --json-array
+    let output = main_dir.run_jj(["workspace", "root", "--json-array"]);
+    insta::assert_snapshot!(output, @r#"
+    [
+      {
+        "name": "default",
+        "path": "$TEST_ENV/main",
+        "exists": true,
+        "created_at": "2001-09-09T01:46:40+00:00"
+      }
+    ]
+    [EOF]
+    "#);
+    let output = main_dir.run_jj(["workspace", "root", "--json-array", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r#"
+    [
+      {
+        "name": "second",
+        "path": "$TEST_ENV/secondary",
+        "exists": false,
+        "created_at": "2001-02-03T04:05:08+07:00"
+      }
+    ]
+    [EOF]
+    "#);
+    let output = main_dir.run_jj(["workspace", "root", "--json-array", "--all"]);
+    insta::assert_snapshot!(output, @r#"
+    [
+      {
+        "name": "default",
+        "path": "$TEST_ENV/main",
+        "exists": true,
+        "created_at": "2001-09-09T01:46:40+00:00"
+      },
+      {
+        "name": "second",
+        "path": "$TEST_ENV/secondary",
+        "exists": false,
+        "created_at": "2001-02-03T04:05:08+07:00"
+      }
+    ]
+    [EOF]
+    "#);
+
+    // Errors are reported as JSON too
+    let output = main_dir.run_jj(["workspace", "root", "--json", "--workspace", "unknown"]);
+    insta::assert_snapshot!(output, @r#"
+    ------- stderr -------
+    {"error":"No path is recorded for workspace unknown","code":"not_found","workspace":"unknown"}
+    [EOF]
+    [exit status: 1]
+    "#);
+    std::fs::write(
+        main_dir.root().join(".jj/repo/workspace_store/second"),
+        "garbage",
+    )
+    .unwrap();
+    let output = main_dir.run_jj(["workspace", "root", "--json", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r#"
+    ------- stderr -------
+    {"error":"Corrupt workspace store entry for second","code":"corrupt","workspace":"second"}
+    [EOF]
+    [exit status: 1]
+    "#);
+}
+
+#[test]
+fn test_workspaces_json_store() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    // Switch the repo to the JSON store, which records its type in the store
+    // directory.
+    let store_dir = main_dir.root().join(".jj/repo/workspace_store");
+    std::fs::remove_dir_all(&store_dir).unwrap();
+    std::fs::create_dir(&store_dir).unwrap();
+    std::fs::write(store_dir.join(".type"), "json").unwrap();
+
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../second"])
+        .success();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "third", "../third"])
+        .success();
+    assert!(store_dir.join("second.json").is_file());
+
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/second
+    [EOF]
+    ");
+    let second_dir = test_env.work_dir("second");
+    second_dir
+        .run_jj(["workspace", "rename", "renamed"])
+        .success();
+    assert!(store_dir.join("renamed.json").is_file());
+
+    let output = main_dir.run_jj(["workspace", "forget", "renamed"]);
+    insta::assert_snapshot!(output, @"");
+    assert!(!store_dir.join("renamed.json").exists());
+    let output = main_dir.run_jj(["util", "gc"]);
+    insta::assert_snapshot!(output, @"");
+}
+
+#[test]
+fn test_debug_snapshot() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "repo"]).success();
+    let work_dir = test_env.work_dir("repo");
+
+    work_dir.write_file("file", "contents");
+    work_dir.run_jj(["debug", "snapshot"]).success();
+    let output = work_dir.run_jj(["op", "log"]);
+    insta::assert_snapshot!(output, @r"
+    @  594dfebf2565 test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
+    │  snapshot working copy
+    │  args: jj debug snapshot
+    ○  8f47435a3990 test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+    │  add workspace 'default'
+    ○  000000000000 root()
+    [EOF]
+    ");
+    work_dir.run_jj(["describe", "-m", "initial"]).success();
+    let output = work_dir.run_jj(["op", "log"]);
+    insta::assert_snapshot!(output, @r"
+    @  81e4a0f2e793 test-username@host.example.com 2001-02-03 04:05:10.000 +07:00 - 2001-02-03 04:05:10.000 +07:00
+    │  describe commit 006bd1130b84e90ab082adeabd7409270d5a86da
+    │  args: jj describe -m initial
+    ○  594dfebf2565 test-username@host.example.com 2001-02-03 04:05:08.000 +07:00 - 2001-02-03 04:05:08.000 +07:00
+    │  snapshot working copy
+    │  args: jj debug snapshot
+    ○  8f47435a3990 test-username@host.example.com 2001-02-03 04:05:07.000 +07:00 - 2001-02-03 04:05:07.000 +07:00
+    │  add workspace 'default'
+    ○  000000000000 root()
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_rename_nothing_changed() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let output = main_dir.run_jj(["workspace", "rename", "default"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Nothing changed.
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_rename_new_workspace_name_already_used() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    let output = main_dir.run_jj(["workspace", "rename", "second"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Failed to rename a workspace
+    Caused by: Workspace second already exists
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_workspaces_rename_forgotten_workspace() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    main_dir.run_jj(["workspace", "forget", "second"]).success();
+    let secondary_dir = test_env.work_dir("secondary");
+    let output = secondary_dir.run_jj(["workspace", "rename", "third"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: The current workspace 'second' is not tracked in the repo.
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[test]
+fn test_workspaces_rename_workspace() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    let secondary_dir = test_env.work_dir("secondary");
+
+    // Both workspaces show up when we list them
+    let output = main_dir.run_jj(["workspace", "list"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    second: uuqppmxq 94f41578 (empty) (no description set)
     [EOF]
     ");
 
@@ -1380,6 +4142,144 @@ fn test_workspaces_rename_workspace() {
     ");
 }
 
+#[test]
+fn test_workspaces_rename_over_orphan() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    let setup_opid = main_dir.current_operation_id();
+    main_dir
+        .run_jj(["workspace", "add", "--name", "second", "../secondary"])
+        .success();
+    // The path of the forgotten workspace is still recorded
+    main_dir.run_jj(["op", "restore", &setup_opid]).success();
+
+    let output = main_dir.run_jj(["workspace", "rename", "second"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: A path is already recorded for workspace second
+    Hint: Use --force to replace the recorded path.
+    [EOF]
+    [exit status: 1]
+    ");
+    // Nothing was renamed
+    let output = main_dir.run_jj(["workspace", "list"]);
+    insta::assert_snapshot!(output, @r"
+    default: qpvuntsm e8849ae1 (empty) (no description set)
+    second ($TEST_ENV/secondary): (no working-copy commit)
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    second: $TEST_ENV/secondary
+    [EOF]
+    ");
+
+    main_dir
+        .run_jj(["workspace", "rename", "--force", "second"])
+        .success();
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    second: $TEST_ENV/main
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_rename_update_dir() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir.run_jj(["workspace", "add", "../second"]).success();
+
+    let second_dir = test_env.work_dir("second");
+    let output = second_dir.run_jj(["workspace", "rename", "--update-dir", "third"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Moved workspace to $TEST_ENV/third
+    [EOF]
+    ");
+    assert!(!test_env.env_root().join("second").exists());
+    let third_dir = test_env.work_dir("third");
+    let output = third_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/main
+    third: $TEST_ENV/third
+    [EOF]
+    ");
+
+    // An existing directory isn't replaced
+    std::fs::create_dir(test_env.env_root().join("fourth")).unwrap();
+    let output = third_dir.run_jj(["workspace", "rename", "--update-dir", "fourth"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Refusing to move $TEST_ENV/third since $TEST_ENV/fourth already exists
+    [EOF]
+    [exit status: 1]
+    ");
+    let output = third_dir.run_jj(["workspace", "rename", "--update-dir", "sub/fifth"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Cannot name a directory after workspace sub/fifth
+    [EOF]
+    [exit status: 1]
+    ");
+
+    // Directories not named after the workspace are kept
+    let output = main_dir.run_jj(["workspace", "rename", "--update-dir", "primary"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Warning: Not moving $TEST_ENV/main since it isn't named after the workspace
+    [EOF]
+    ");
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output, @r"
+    primary: $TEST_ENV/main
+    third: $TEST_ENV/third
+    [EOF]
+    ");
+
+    // The workspace containing the repo can't be moved
+    test_env
+        .run_jj_in(".", ["git", "init", "default"])
+        .success();
+    let default_dir = test_env.work_dir("default");
+    let output = default_dir.run_jj(["workspace", "rename", "--update-dir", "other"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Refusing to move $TEST_ENV/default since it contains the repo
+    [EOF]
+    [exit status: 1]
+    ");
+}
+
+#[cfg(windows)]
+#[test]
+fn test_workspaces_add_reserved_name() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+
+    // CON is a device name on Windows, but can still be used as a workspace
+    // name
+    main_dir
+        .run_jj(["workspace", "add", "--name", "CON", "../secondary"])
+        .success();
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output.normalize_backslash(), @r"
+    CON: $TEST_ENV/secondary
+    default: $TEST_ENV/main
+    [EOF]
+    ");
+    main_dir.run_jj(["workspace", "forget", "CON"]).success();
+    let output = main_dir.run_jj(["workspace", "root", "--all"]);
+    insta::assert_snapshot!(output.normalize_backslash(), @r"
+    default: $TEST_ENV/main
+    [EOF]
+    ");
+}
+
 #[must_use]
 fn get_log_output(work_dir: &TestWorkDir) -> CommandOutput {
     let template = r#"
@@ -1391,3 +4291,72 @@ fn get_log_output(work_dir: &TestWorkDir) -> CommandOutput {
     "#;
     work_dir.run_jj(["log", "-T", template, "-r", "all()"])
 }
+
+#[test]
+fn test_workspaces_store_compress() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    let main_dir = test_env.work_dir("main");
+    main_dir
+        .run_jj([
+            "workspace",
+            "add",
+            "--config=workspace.store-compress=true",
+            "../second",
+        ])
+        .success();
+    let entry_file = main_dir.root().join(".jj/repo/workspace_store/second");
+    assert!(
+        std::fs::read(entry_file)
+            .unwrap()
+            .starts_with(&[0x1f, 0x8b])
+    );
+
+    // The compressed root is read without the setting
+    let output = main_dir.run_jj(["workspace", "root", "--workspace", "second"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/second
+    [EOF]
+    ");
+}
+
+#[test]
+fn test_workspaces_root_source() {
+    let test_env = TestEnvironment::default();
+    test_env.run_jj_in(".", ["git", "init", "main"]).success();
+    std::fs::rename(
+        test_env.env_root().join("main"),
+        test_env.env_root().join("moved"),
+    )
+    .unwrap();
+    let moved_dir = test_env.work_dir("moved");
+
+    // The recorded root is shown by default
+    let output = moved_dir.run_jj(["workspace", "root", "--workspace", "@"]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/main
+    [EOF]
+    ");
+    let output = moved_dir.run_jj(["workspace", "root", "--check"]);
+    insta::assert_snapshot!(output, @r"
+    ------- stderr -------
+    Error: Root of workspace default ($TEST_ENV/main) does not exist
+    [EOF]
+    [exit status: 1]
+    ");
+
+    let live_config = "--config=workspace.root-source=live";
+    let output = moved_dir.run_jj(["workspace", "root", "--workspace", "@", live_config]);
+    insta::assert_snapshot!(output, @r"
+    $TEST_ENV/moved
+    [EOF]
+    ");
+    let output = moved_dir.run_jj(["workspace", "root", "--all", live_config]);
+    insta::assert_snapshot!(output, @r"
+    default: $TEST_ENV/moved
+    [EOF]
+    ");
+    moved_dir
+        .run_jj(["workspace", "root", "--check", live_config])
+        .success();
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::workspace_store::NormalizingPathCanonicalizer;
+use jj_lib::workspace_store::SimpleWorkspaceStore;
+use jj_lib::workspace_store::SimpleWorkspaceStoreOptions;
+use jj_lib::workspace_store::WorkspaceStore;
+
+fn store_options() -> SimpleWorkspaceStoreOptions {
+    // The roots don't have to exist
+    SimpleWorkspaceStoreOptions {
+        canonicalizer: Arc::new(NormalizingPathCanonicalizer),
+        ..Default::default()
+    }
+}
+
+/// Records `count` workspaces in the store. The entries are written to a
+/// temporary directory, so the reads pay the real file system costs.
+fn populate(store: &dyn WorkspaceStore, roots_dir: &Path, count: usize) {
+    let roots: Vec<(WorkspaceNameBuf, PathBuf)> = (0..count)
+        .map(|i| {
+            let name = format!("workspace-{i}");
+            let root = roots_dir.join(&name);
+            (name.into(), root)
+        })
+        .collect();
+    let entries: Vec<_> = roots
+        .iter()
+        .map(|(name, root)| (name.clone(), root.as_path()))
+        .collect();
+    store.replace_all(&entries).unwrap();
+    store.flush().unwrap();
+}
+
+/// Benchmarks enumerating the store. `list()` is the name-only listing: it
+/// only reads the directory, whereas `get_all_paths()` reads and decodes every
+/// entry. `len()` counts the entries without collecting their names.
+fn bench_enumerate(c: &mut Criterion, group_name: &str, store: &dyn WorkspaceStore, count: usize) {
+    let mut group = c.benchmark_group(group_name);
+    let label = format!("{}k", count / 1000);
+    group.bench_function(BenchmarkId::new("list", &label), |b| {
+        b.iter(|| store.list().unwrap());
+    });
+    group.bench_function(BenchmarkId::new("len", &label), |b| {
+        b.iter(|| store.len().unwrap());
+    });
+    group.bench_function(BenchmarkId::new("get_all_paths", &label), |b| {
+        b.iter(|| store.get_all_paths().unwrap());
+    });
+}
+
+fn bench_simple_store(c: &mut Criterion) {
+    for count in [1000, 10000] {
+        let temp_dir = testutils::new_temp_dir();
+        let repo_path = temp_dir.path().join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        // Roots inside the repo directory can't be recorded
+        let roots_dir = testutils::new_temp_dir();
+        let store = SimpleWorkspaceStore::init_with_options(&repo_path, store_options()).unwrap();
+        populate(&store, roots_dir.path(), count);
+        bench_enumerate(c, "bench_simple_store", &store, count);
+    }
+}
+
+fn bench_json_store(c: &mut Criterion) {
+    for count in [1000, 10000] {
+        let temp_dir = testutils::new_temp_dir();
+        let repo_path = temp_dir.path().join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        // Roots inside the repo directory can't be recorded
+        let roots_dir = testutils::new_temp_dir();
+        let store = SimpleWorkspaceStore::init_json(&repo_path, store_options()).unwrap();
+        populate(&store, roots_dir.path(), count);
+        bench_enumerate(c, "bench_json_store", &store, count);
+    }
+}
+
+criterion_group!(benches, bench_simple_store, bench_json_store);
+criterion_main!(benches);
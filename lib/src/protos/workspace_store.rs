@@ -0,0 +1,44 @@
+// This file is @generated by prost-build.
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct Workspace {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// Canonical path to the workspace root.
+    #[prost(bytes = "vec", tag = "2")]
+    pub path: ::prost::alloc::vec::Vec<u8>,
+    /// Entries of version 2 and later are followed by the little-endian CRC32 of
+    /// the encoded message. Entries without this field are of version 1.
+    #[prost(uint32, tag = "3")]
+    pub format_version: u32,
+    /// Version of jj that recorded the path, if known.
+    #[prost(string, tag = "4")]
+    pub created_by_version: ::prost::alloc::string::String,
+    /// Unix user id of the user that recorded the path. Not recorded on other
+    /// platforms.
+    #[prost(uint32, optional, tag = "5")]
+    pub created_by_uid: ::core::option::Option<u32>,
+    /// Path to the workspace root as it was given, before symlinks were
+    /// resolved. Only recorded if it differs from `path`, which is used to locate
+    /// the workspace.
+    #[prost(bytes = "vec", optional, tag = "6")]
+    pub display_path: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// Name of the workspace this entry is being renamed from. Set while the
+    /// entry of the old name may still exist, so that a rename interrupted by a
+    /// crash can be finished.
+    #[prost(string, tag = "7")]
+    pub renamed_from: ::prost::alloc::string::String,
+    /// Id of the operation that added the workspace to the repo. Empty if
+    /// unknown, e.g. for entries recorded by older versions or backfilled from an
+    /// existing workspace.
+    #[prost(bytes = "vec", tag = "8")]
+    pub created_at_op_id: ::prost::alloc::vec::Vec<u8>,
+    /// Set if the root was recorded by importing an existing directory, and the
+    /// workspace hasn't been added to the repo since. Such entries aren't pruned
+    /// automatically while the workspace has no working-copy commit.
+    #[prost(bool, tag = "9")]
+    pub imported: bool,
+    /// If set, `path` is a link file whose contents give the workspace root, so
+    /// that the workspace can be moved by updating the link file.
+    #[prost(bool, tag = "10")]
+    pub path_is_link: bool,
+}
@@ -1469,6 +1469,12 @@ impl MutableRepo {
         Ok(())
     }
 
+    /// Like [`Self::remove_wc_commit()`], but keeps the working-copy commit
+    /// even if it's discardable.
+    pub fn remove_wc_commit_without_abandoning(&mut self, name: &WorkspaceName) {
+        self.view_mut().remove_wc_commit(name);
+    }
+
     /// Merges working-copy commit. If there's a conflict, and if the workspace
     /// isn't removed at either side, we keep the self side.
     fn merge_wc_commit(
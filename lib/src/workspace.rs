@@ -61,6 +61,10 @@ use crate::working_copy::LockedWorkingCopy;
 use crate::working_copy::WorkingCopy;
 use crate::working_copy::WorkingCopyFactory;
 use crate::working_copy::WorkingCopyStateError;
+use crate::workspace_store::SimpleWorkspaceStore;
+use crate::workspace_store::SimpleWorkspaceStoreOptions;
+use crate::workspace_store::WorkspaceStoreError;
+use crate::workspace_store::load_workspace_store;
 
 #[derive(Error, Debug)]
 pub enum WorkspaceInitError {
@@ -82,6 +86,8 @@ pub enum WorkspaceInitError {
     SignInit(#[from] SignInitError),
     #[error(transparent)]
     TransactionCommit(#[from] TransactionCommitError),
+    #[error(transparent)]
+    WorkspaceStore(#[from] WorkspaceStoreError),
 }
 
 #[derive(Error, Debug)]
@@ -154,6 +160,30 @@ fn init_working_copy(
     Ok((working_copy, repo))
 }
 
+/// Records the root of the workspace in the workspace store. `workspace_root`
+/// is the root as it was given, which is kept for display if it differs from
+/// the canonical root.
+fn record_workspace_path(
+    workspace: &Workspace,
+    workspace_root: &Path,
+    workspace_store_options: &SimpleWorkspaceStoreOptions,
+) -> Result<(), WorkspaceInitError> {
+    let workspace_store =
+        match load_workspace_store(workspace.repo_path(), workspace_store_options.clone()) {
+            Ok(store) => store,
+            // The repo was created before workspace roots were recorded
+            Err(WorkspaceStoreError::NotInitialized(_)) => {
+                Box::new(SimpleWorkspaceStore::init_with_options(
+                    workspace.repo_path(),
+                    workspace_store_options.clone(),
+                )?)
+            }
+            Err(err) => return Err(err.into()),
+        };
+    workspace_store.set_path(workspace.workspace_name(), workspace_root)?;
+    Ok(())
+}
+
 impl Workspace {
     pub fn new(
         workspace_root: &Path,
@@ -187,11 +217,31 @@ impl Workspace {
     pub fn init_simple(
         user_settings: &UserSettings,
         workspace_root: &Path,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_simple_with_workspace_store_options(
+            user_settings,
+            workspace_root,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    /// Like [`Self::init_simple()`], but records the root of the workspace
+    /// with non-default `workspace_store_options`.
+    pub fn init_simple_with_workspace_store_options(
+        user_settings: &UserSettings,
+        workspace_root: &Path,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let backend_initializer: &BackendInitializer =
             &|_settings, store_path| Ok(Box::new(SimpleBackend::init(store_path)));
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, backend_initializer, signer)
+        Self::init_with_backend_and_workspace_store_options(
+            user_settings,
+            workspace_root,
+            backend_initializer,
+            signer,
+            workspace_store_options,
+        )
     }
 
     /// Initializes a workspace with a new Git backend and bare Git repo in
@@ -200,6 +250,21 @@ impl Workspace {
     pub fn init_internal_git(
         user_settings: &UserSettings,
         workspace_root: &Path,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_internal_git_with_workspace_store_options(
+            user_settings,
+            workspace_root,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    /// Like [`Self::init_internal_git()`], but records the root of the
+    /// workspace with non-default `workspace_store_options`.
+    #[cfg(feature = "git")]
+    pub fn init_internal_git_with_workspace_store_options(
+        user_settings: &UserSettings,
+        workspace_root: &Path,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let backend_initializer: &BackendInitializer = &|settings, store_path| {
             Ok(Box::new(crate::git_backend::GitBackend::init_internal(
@@ -207,7 +272,13 @@ impl Workspace {
             )?))
         };
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, backend_initializer, signer)
+        Self::init_with_backend_and_workspace_store_options(
+            user_settings,
+            workspace_root,
+            backend_initializer,
+            signer,
+            workspace_store_options,
+        )
     }
 
     /// Initializes a workspace with a new Git backend and Git repo that shares
@@ -216,6 +287,21 @@ impl Workspace {
     pub fn init_colocated_git(
         user_settings: &UserSettings,
         workspace_root: &Path,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_colocated_git_with_workspace_store_options(
+            user_settings,
+            workspace_root,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    /// Like [`Self::init_colocated_git()`], but records the root of the
+    /// workspace with non-default `workspace_store_options`.
+    #[cfg(feature = "git")]
+    pub fn init_colocated_git_with_workspace_store_options(
+        user_settings: &UserSettings,
+        workspace_root: &Path,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let backend_initializer = |settings: &UserSettings,
                                    store_path: &Path|
@@ -237,7 +323,13 @@ impl Workspace {
             Ok(Box::new(backend))
         };
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, &backend_initializer, signer)
+        Self::init_with_backend_and_workspace_store_options(
+            user_settings,
+            workspace_root,
+            &backend_initializer,
+            signer,
+            workspace_store_options,
+        )
     }
 
     /// Initializes a workspace with an existing Git repo at the specified path.
@@ -249,6 +341,23 @@ impl Workspace {
         user_settings: &UserSettings,
         workspace_root: &Path,
         git_repo_path: &Path,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_external_git_with_workspace_store_options(
+            user_settings,
+            workspace_root,
+            git_repo_path,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    /// Like [`Self::init_external_git()`], but records the root of the
+    /// workspace with non-default `workspace_store_options`.
+    #[cfg(feature = "git")]
+    pub fn init_external_git_with_workspace_store_options(
+        user_settings: &UserSettings,
+        workspace_root: &Path,
+        git_repo_path: &Path,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let backend_initializer = |settings: &UserSettings,
                                    store_path: &Path|
@@ -277,7 +386,13 @@ impl Workspace {
             Ok(Box::new(backend))
         };
         let signer = Signer::from_settings(user_settings)?;
-        Self::init_with_backend(user_settings, workspace_root, &backend_initializer, signer)
+        Self::init_with_backend_and_workspace_store_options(
+            user_settings,
+            workspace_root,
+            &backend_initializer,
+            signer,
+            workspace_store_options,
+        )
     }
 
     #[expect(clippy::too_many_arguments)]
@@ -292,6 +407,35 @@ impl Workspace {
         submodule_store_initializer: &SubmoduleStoreInitializer,
         working_copy_factory: &dyn WorkingCopyFactory,
         workspace_name: WorkspaceNameBuf,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_with_factories_and_workspace_store_options(
+            user_settings,
+            workspace_root,
+            backend_initializer,
+            signer,
+            op_store_initializer,
+            op_heads_store_initializer,
+            index_store_initializer,
+            submodule_store_initializer,
+            working_copy_factory,
+            workspace_name,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    #[expect(clippy::too_many_arguments)]
+    fn init_with_factories_and_workspace_store_options(
+        user_settings: &UserSettings,
+        workspace_root: &Path,
+        backend_initializer: &BackendInitializer,
+        signer: Signer,
+        op_store_initializer: &OpStoreInitializer,
+        op_heads_store_initializer: &OpHeadsStoreInitializer,
+        index_store_initializer: &IndexStoreInitializer,
+        submodule_store_initializer: &SubmoduleStoreInitializer,
+        working_copy_factory: &dyn WorkingCopyFactory,
+        workspace_name: WorkspaceNameBuf,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let jj_dir = create_jj_dir(workspace_root)?;
         (|| {
@@ -312,6 +456,7 @@ impl Workspace {
                 RepoInitError::OpHeadsStore(err) => WorkspaceInitError::OpHeadsStore(err),
                 RepoInitError::Path(err) => WorkspaceInitError::Path(err),
             })?;
+            SimpleWorkspaceStore::init_with_options(&repo_dir, workspace_store_options.clone())?;
             let (working_copy, repo) = init_working_copy(
                 &repo,
                 workspace_root,
@@ -321,6 +466,7 @@ impl Workspace {
             )?;
             let repo_loader = repo.loader().clone();
             let workspace = Self::new(workspace_root, repo_dir, working_copy, repo_loader)?;
+            record_workspace_path(&workspace, workspace_root, workspace_store_options)?;
             Ok((workspace, repo))
         })()
         .inspect_err(|_err| {
@@ -334,7 +480,23 @@ impl Workspace {
         backend_initializer: &BackendInitializer,
         signer: Signer,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
-        Self::init_with_factories(
+        Self::init_with_backend_and_workspace_store_options(
+            user_settings,
+            workspace_root,
+            backend_initializer,
+            signer,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    fn init_with_backend_and_workspace_store_options(
+        user_settings: &UserSettings,
+        workspace_root: &Path,
+        backend_initializer: &BackendInitializer,
+        signer: Signer,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_with_factories_and_workspace_store_options(
             user_settings,
             workspace_root,
             backend_initializer,
@@ -345,6 +507,7 @@ impl Workspace {
             ReadonlyRepo::default_submodule_store_initializer(),
             &*default_working_copy_factory(),
             WorkspaceName::DEFAULT.to_owned(),
+            workspace_store_options,
         )
     }
 
@@ -354,6 +517,26 @@ impl Workspace {
         repo: &Arc<ReadonlyRepo>,
         working_copy_factory: &dyn WorkingCopyFactory,
         workspace_name: WorkspaceNameBuf,
+    ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
+        Self::init_workspace_with_existing_repo_with_workspace_store_options(
+            workspace_root,
+            repo_path,
+            repo,
+            working_copy_factory,
+            workspace_name,
+            &SimpleWorkspaceStoreOptions::default(),
+        )
+    }
+
+    /// Like [`Self::init_workspace_with_existing_repo()`], but records the
+    /// root of the workspace with non-default `workspace_store_options`.
+    pub fn init_workspace_with_existing_repo_with_workspace_store_options(
+        workspace_root: &Path,
+        repo_path: &Path,
+        repo: &Arc<ReadonlyRepo>,
+        working_copy_factory: &dyn WorkingCopyFactory,
+        workspace_name: WorkspaceNameBuf,
+        workspace_store_options: &SimpleWorkspaceStoreOptions,
     ) -> Result<(Self, Arc<ReadonlyRepo>), WorkspaceInitError> {
         let jj_dir = create_jj_dir(workspace_root)?;
 
@@ -376,6 +559,7 @@ impl Workspace {
             working_copy,
             repo.loader().clone(),
         )?;
+        record_workspace_path(&workspace, workspace_root, workspace_store_options)?;
         Ok((workspace, repo))
     }
 
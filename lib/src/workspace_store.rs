@@ -0,0 +1,3836 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Records where each workspace of a repo lives on disk.
+//!
+//! The view only knows the working-copy commit of each workspace. The
+//! workspace store keeps track of the workspace roots so that any workspace
+//! can be located from any other workspace of the same repo.
+//!
+//! The store only needs the repo directory, so it can be queried without
+//! loading the repo, and [`SimpleWorkspaceStore::load_readonly()`] doesn't
+//! write to it. For example, the roots of all workspaces sharing a repo with
+//! the workspace at `workspace_root` can be printed like this:
+//! ```no_run
+//! # use std::path::Path;
+//! use jj_lib::workspace::DefaultWorkspaceLoaderFactory;
+//! use jj_lib::workspace::WorkspaceLoaderFactory as _;
+//! use jj_lib::workspace_store::SimpleWorkspaceStore;
+//! use jj_lib::workspace_store::WorkspaceStore as _;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let workspace_root = Path::new("/path/to/workspace");
+//! let loader = DefaultWorkspaceLoaderFactory.create(workspace_root)?;
+//! let store = SimpleWorkspaceStore::load_readonly(loader.repo_path())?;
+//! for (name, path) in store.get_all_paths()? {
+//!     println!("{}: {}", name.as_symbol(), path.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::io::Write as _;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use blake2::Blake2b512;
+use blake2::Digest as _;
+use itertools::Itertools as _;
+use prost::Message as _;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+use zlib_rs::ReturnCode;
+use zlib_rs::deflate;
+use zlib_rs::deflate::DeflateConfig;
+use zlib_rs::inflate;
+use zlib_rs::inflate::InflateConfig;
+
+use crate::backend::BackendError;
+use crate::backend::ChangeId;
+use crate::backend::CommitId;
+use crate::file_util;
+use crate::file_util::BadPathEncoding;
+use crate::file_util::IoResultExt as _;
+use crate::file_util::PathError;
+use crate::file_util::persist_temp_file;
+use crate::hex_util;
+use crate::lock::FileLock;
+use crate::lock::FileLockError;
+use crate::object_id::ObjectId as _;
+use crate::op_store::OperationId;
+use crate::protos::workspace_store as proto;
+use crate::ref_name::WorkspaceName;
+use crate::ref_name::WorkspaceNameBuf;
+use crate::repo::Repo;
+use crate::store::Store;
+use crate::view::View;
+use crate::workspace::DefaultWorkspaceLoaderFactory;
+use crate::workspace::WorkspaceLoaderFactory as _;
+
+/// Conventional name of the link files recorded by
+/// [`SimpleWorkspaceStore::set_link_path()`].
+pub const WORKSPACE_LINK_FILE_NAME: &str = ".jj-workspace-link";
+/// Prefix of the temporary files written by [`SimpleWorkspaceStore`].
+const TEMP_FILE_PREFIX: &str = ".tmp";
+/// Name of the lock file held while [`SimpleWorkspaceStore`] is mutated. It's
+/// in the repo directory rather than the store directory, so that it stays in
+/// place while [`SimpleWorkspaceStore::replace_all()`] swaps the store
+/// directory.
+const LOCK_FILE_NAME: &str = "workspace_store.lock";
+/// Name of the file that marks a rename by [`SimpleWorkspaceStore`] as in
+/// progress. It's left behind if the rename is interrupted.
+const RENAME_MARKER_FILE_NAME: &str = ".renaming";
+/// Name of the directory [`SimpleWorkspaceStore::repair()`] moves unreadable
+/// entries to.
+const CORRUPT_DIR_NAME: &str = ".corrupt";
+/// Name of the directory removed entries are moved to if
+/// [`SimpleWorkspaceStoreOptions::tombstones`] is set.
+const TOMBSTONE_DIR_NAME: &str = ".tombstones";
+/// Name of the file recording which store implementation wrote the entries.
+/// Stores without it were written by [`SimpleWorkspaceStore`].
+const TYPE_FILE_NAME: &str = ".type";
+/// Suffix of the entry files of [`SimpleWorkspaceStore`] in the JSON format.
+const JSON_FILE_SUFFIX: &str = ".json";
+/// Version of jj recorded in new entries.
+const JJ_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// First entry format version whose entries are followed by a checksum.
+const CHECKSUM_FORMAT_VERSION: u32 = 2;
+/// Characters that can't appear in file names on Windows. They're encoded on
+/// all platforms so that the store can be shared.
+const ILLEGAL_FILE_NAME_CHARS: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+/// Suffix of the directory [`SimpleWorkspaceStore::replace_all()`] writes the
+/// new entries to.
+const REPLACEMENT_DIR_SUFFIX: &str = ".new";
+/// Suffix of the directory [`SimpleWorkspaceStore::replace_all()`] moves the
+/// old entries to.
+const REPLACED_DIR_SUFFIX: &str = ".old";
+/// How long reads wait for [`SimpleWorkspaceStore::replace_all()`] to move
+/// the new store directory into place.
+const REPLACEMENT_WAIT: Duration = Duration::from_millis(500);
+/// First bytes of gzip-compressed entries. No entry in either format starts
+/// with them: the first byte isn't a valid protobuf tag, nor the start of a
+/// JSON object.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// `window_bits` selecting the gzip wrapper in zlib.
+const GZIP_WINDOW_BITS: i32 = 15 + 16;
+/// Size of the gzip header and trailer, which [`deflate::compress_bound()`]
+/// doesn't account for.
+const GZIP_WRAP_LEN: usize = 18;
+/// Largest decompressed entry that is read, so that a corrupt size in the
+/// gzip trailer can't exhaust memory.
+const MAX_DECOMPRESSED_LEN: usize = 1 << 20;
+/// Longest encoded file name most file systems support.
+const MAX_FILE_NAME_LEN: usize = 255;
+/// Number of link files followed to resolve a workspace root before giving up.
+/// See [`SimpleWorkspaceStore::set_link_path()`].
+const MAX_LINK_DEPTH: usize = 8;
+/// Device names that can't be used as file names on Windows, with or without
+/// an extension. `COM` and `LPT` are followed by a digit.
+const RESERVED_FILE_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+/// How [`SimpleWorkspaceStore`] maps workspace names to entry file names.
+///
+/// A `/` in a workspace name separates nested namespaces, such as
+/// `team/feature`, which are stored in subdirectories. Names with empty, `.`,
+/// or `..` components can't be stored. Within each component, with either
+/// encoding, `%`, a leading `.`, and characters that can't appear in file names
+/// on some platforms, such as `:`, are percent-encoded. The first character of
+/// names reserved for devices on Windows, such as `CON` or `nul.txt`, is
+/// percent-encoded as well, on every platform.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NameEncoding {
+    /// The workspace name is otherwise used as the file name. Names differing
+    /// only in case map to the same file on case-insensitive file systems.
+    #[default]
+    Plain,
+    /// Uppercase characters are percent-encoded too, so names differing only
+    /// in case map to distinct files even on case-insensitive file systems.
+    /// The file names are harder to read, and all users of the repo must open
+    /// the store with this encoding.
+    CaseSensitive,
+}
+
+impl NameEncoding {
+    /// Returns the encoded path of the entry relative to the store directory,
+    /// with components separated by `/`.
+    fn encode(self, workspace_name: &WorkspaceName) -> Result<String, WorkspaceStoreError> {
+        self.validate(workspace_name)?;
+        Ok(workspace_name
+            .as_str()
+            .split('/')
+            .map(|component| self.encode_component(component))
+            .join("/"))
+    }
+
+    /// Checks that the workspace name can be stored. Every component must be
+    /// non-empty, not `.` or `..`, and fit in a file name once encoded.
+    fn validate(self, workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError> {
+        let is_valid = workspace_name.as_str().split('/').all(|component| {
+            !matches!(component, "" | "." | "..")
+                && self.encode_component(component).len() <= MAX_FILE_NAME_LEN
+        });
+        if is_valid {
+            Ok(())
+        } else {
+            Err(WorkspaceStoreError::InvalidName(workspace_name.to_owned()))
+        }
+    }
+
+    fn encode_component(self, component: &str) -> String {
+        let mut file_name = String::new();
+        for (i, c) in component.chars().enumerate() {
+            // A leading `.` could clash with the lock and temporary files.
+            let needs_escape = c == '%'
+                || (i == 0 && c == '.')
+                || c.is_ascii_control()
+                || ILLEGAL_FILE_NAME_CHARS.contains(&c)
+                || (self == Self::CaseSensitive && c.is_uppercase());
+            if needs_escape {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    file_name.push_str(&format!("%{b:02X}"));
+                }
+            } else {
+                file_name.push(c);
+            }
+        }
+        // Escaped on every platform, so that a store can be shared between
+        // them. The first character is an ASCII letter, which is never escaped
+        // otherwise.
+        if is_reserved_file_name(&file_name) {
+            let first = file_name.remove(0);
+            file_name.insert_str(0, &format!("%{:02X}", first as u32));
+        }
+        file_name
+    }
+
+    /// Decodes a path relative to the store directory, with components
+    /// separated by `/`.
+    fn decode(self, file_name: &str) -> Option<WorkspaceNameBuf> {
+        let mut bytes = vec![];
+        let mut rest = file_name.as_bytes();
+        while let Some((&b, tail)) = rest.split_first() {
+            if b == b'%' {
+                let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+            } else {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+        // Only accept the canonical encoding, so that the entry can be found
+        // again by name.
+        let name = WorkspaceNameBuf::from(String::from_utf8(bytes).ok()?);
+        (self.encode(&name).ok()? == file_name).then_some(name)
+    }
+}
+
+/// Whether Windows treats `file_name` as a device rather than a file.
+fn is_reserved_file_name(file_name: &str) -> bool {
+    let stem = file_name.split('.').next().unwrap().trim_end_matches(' ');
+    let is_port = |prefix: &str| {
+        stem.get(..3)
+            .is_some_and(|s| s.eq_ignore_ascii_case(prefix))
+            && matches!(
+                stem.get(3..),
+                Some("0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "¹" | "²" | "³")
+            )
+    };
+    RESERVED_FILE_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        || is_port("COM")
+        || is_port("LPT")
+}
+
+/// Turns workspace roots into the form recorded in the store.
+pub trait PathCanonicalizer: Send + Sync + Debug {
+    /// Returns the canonical form of `path`.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+}
+
+/// Resolves symlinks and relative components of paths, which must exist. On
+/// Windows, UNC paths are avoided where possible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPathCanonicalizer;
+
+impl PathCanonicalizer for DefaultPathCanonicalizer {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        dunce::canonicalize(path)
+    }
+}
+
+/// Makes paths absolute and removes `.` and `..` components lexically, without
+/// resolving symlinks. The paths don't have to exist.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NormalizingPathCanonicalizer;
+
+impl PathCanonicalizer for NormalizingPathCanonicalizer {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(file_util::normalize_path(&std::path::absolute(path)?))
+    }
+}
+
+/// Options for [`SimpleWorkspaceStore`].
+#[derive(Clone, Debug)]
+pub struct SimpleWorkspaceStoreOptions {
+    /// How workspace names are mapped to entry file names.
+    pub name_encoding: NameEncoding,
+    /// Workspace roots inside the repo directory or inside its ancestors up to
+    /// this many levels are rejected. The default of 1 rejects roots inside the
+    /// `.jj` directory. `None` disables the check for layouts that
+    /// deliberately nest workspaces.
+    pub repo_internal_depth: Option<usize>,
+    /// Older versions could leave a zero-byte entry behind when they crashed
+    /// while writing it. If true, such entries are treated as missing rather
+    /// than corrupt.
+    pub empty_entry_is_missing: bool,
+    /// Canonicalizes workspace roots before they're recorded.
+    pub canonicalizer: Arc<dyn PathCanonicalizer>,
+    /// If true, entries are written with a trailing checksum to detect
+    /// corruption on unreliable storage. Such entries can't be read by older
+    /// versions. Entries with and without a checksum are read regardless of
+    /// this option.
+    pub checksum: bool,
+    /// If true, entries are written gzip-compressed to save space when there
+    /// are many of them. Such entries can't be read by older versions.
+    /// Compressed entries are recognized by their first bytes, so entries with
+    /// and without compression are read regardless of this option.
+    pub compress: bool,
+    /// If set, reading an entry fails with [`WorkspaceStoreError::Timeout`]
+    /// if it takes longer than this, e.g. because a network file system
+    /// hangs. This is best-effort: the read continues in the background since
+    /// it can't be canceled.
+    pub read_timeout: Option<Duration>,
+    /// If true, recording a root that is already recorded for another
+    /// workspace fails with [`WorkspaceStoreError::DuplicatePath`]. Two
+    /// workspaces sharing a directory corrupt each other's working copy.
+    /// Checking this reads all entries on every write, so it's off by default.
+    pub reject_duplicate_paths: bool,
+    /// If set, the roots recorded in the store are also written to an index
+    /// file in this directory, which may be shared by many repos so that all
+    /// their workspaces can be enumerated without loading each repo. See
+    /// [`read_workspace_index()`]. The index is updated on a best-effort
+    /// basis: failures are logged, not returned.
+    pub index_dir: Option<PathBuf>,
+    /// If true, removed entries are moved to the `.tombstones` directory of
+    /// the store rather than deleted, so that
+    /// [`SimpleWorkspaceStore::untombstone()`] can restore them, e.g. when the
+    /// forgetting of a workspace is undone. Renames still delete the entry of
+    /// the old name.
+    pub tombstones: bool,
+    /// If set, [`WorkspaceStore::exists()`] and [`WorkspaceStore::existing()`]
+    /// look names up in a listing of the store that is reused for this long,
+    /// so that checking many names that aren't present reads the directory
+    /// once. Any mutation through the same store instance discards the
+    /// listing, but entries added by other processes may go unnoticed until
+    /// it expires.
+    pub listing_ttl: Option<Duration>,
+    /// On Unix, the permission bits of the entries written to the store, e.g.
+    /// `0o664` so that the other members of the group of a shared repo can
+    /// update them. If unset, entries are created like other files, with the
+    /// permissions allowed by the umask. Ignored on other platforms.
+    pub file_mode: Option<u32>,
+}
+
+impl Default for SimpleWorkspaceStoreOptions {
+    fn default() -> Self {
+        Self {
+            name_encoding: NameEncoding::default(),
+            repo_internal_depth: Some(1),
+            empty_entry_is_missing: true,
+            canonicalizer: Arc::new(DefaultPathCanonicalizer),
+            checksum: false,
+            compress: false,
+            read_timeout: None,
+            reject_duplicate_paths: false,
+            index_dir: None,
+            tombstones: false,
+            listing_ttl: None,
+            file_mode: None,
+        }
+    }
+}
+
+/// Error that may occur when reading or writing the workspace store.
+#[derive(Debug, Error)]
+pub enum WorkspaceStoreError {
+    /// The workspace has no entry in the store.
+    #[error("No path is recorded for workspace {}", .0.as_symbol())]
+    NotFound(WorkspaceNameBuf),
+    /// The workspace already has an entry.
+    #[error("A path is already recorded for workspace {}", .0.as_symbol())]
+    AlreadyExists(WorkspaceNameBuf),
+    /// The workspace name can't be mapped to a file name, e.g. because it's
+    /// empty or too long, or because it clashes with a namespace of nested
+    /// names.
+    #[error("Workspace name {} can't be stored", .0.as_symbol())]
+    InvalidName(WorkspaceNameBuf),
+    /// The entry of the workspace couldn't be decoded.
+    #[error("Corrupt workspace store entry for {}", .name.as_symbol())]
+    Corrupt {
+        /// Name of the workspace.
+        name: WorkspaceNameBuf,
+        /// Underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// Reading the entry took longer than
+    /// [`SimpleWorkspaceStoreOptions::read_timeout`].
+    #[error("Timed out reading workspace store entry {}", .0.display())]
+    Timeout(PathBuf),
+    /// Resolving the root recorded by a link file followed too many links,
+    /// e.g. because they form a loop. See
+    /// [`SimpleWorkspaceStore::set_link_path()`].
+    #[error("Too many links to follow from {}", .0.display())]
+    TooManyRedirects(PathBuf),
+    /// The store location exists but isn't a directory, e.g. a dangling
+    /// symlink.
+    #[error("Workspace store {} is not a directory", .0.display())]
+    NotADirectory(PathBuf),
+    /// The store directory doesn't exist, e.g. because the repo was created by
+    /// a version of jj that didn't record workspace roots. See
+    /// [`SimpleWorkspaceStore::init()`].
+    #[error("Workspace store {} has not been created", .0.display())]
+    NotInitialized(PathBuf),
+    /// The workspace root is inside the internal directories of the repo.
+    #[error(
+        "Workspace root {} is inside the repo directory {}",
+        .path.display(),
+        .repo_dir.display()
+    )]
+    InvalidPath {
+        /// The rejected workspace root.
+        path: PathBuf,
+        /// The repo directory containing it.
+        repo_dir: PathBuf,
+    },
+    /// The workspace root is already recorded for another workspace.
+    #[error(
+        "Workspace root {} is already recorded for workspace {}",
+        .path.display(),
+        .existing_name.as_symbol()
+    )]
+    DuplicatePath {
+        /// The workspace whose root was being recorded.
+        name: WorkspaceNameBuf,
+        /// The workspace the root is already recorded for.
+        existing_name: WorkspaceNameBuf,
+        /// The canonicalized root.
+        path: PathBuf,
+    },
+    /// The store was written by another store implementation, whose entries
+    /// can't be mixed with the requested one.
+    #[error(
+        "Workspace store {} is of type {actual}, not {expected}",
+        .dir.display()
+    )]
+    WrongType {
+        /// The store directory.
+        dir: PathBuf,
+        /// The type of the requested store implementation.
+        expected: &'static str,
+        /// The type recorded in the store.
+        actual: String,
+    },
+    /// The store was written by an unknown store implementation.
+    #[error("Unknown type {type_name} of workspace store {}", .dir.display())]
+    UnknownType {
+        /// The store directory.
+        dir: PathBuf,
+        /// The type recorded in the store.
+        type_name: String,
+    },
+    /// The store can't be written, e.g. because it's on a read-only mount.
+    #[error("Workspace store {} is read-only", .0.display())]
+    ReadOnly(PathBuf),
+    /// The workspace path can't be stored on this platform.
+    #[error("Workspace path could not be encoded")]
+    EncodePath(#[source] BadPathEncoding),
+    /// Failed to lock the store.
+    #[error(transparent)]
+    Lock(#[from] FileLockError),
+    /// Failed to access the store files.
+    #[error(transparent)]
+    Path(#[from] PathError),
+    /// Failed to watch the store for changes.
+    #[cfg(feature = "watch")]
+    #[error("Failed to watch the workspace store")]
+    Watch(#[source] notify::Error),
+}
+
+impl WorkspaceStoreError {
+    /// Returns a stable identifier of the kind of error for machine consumers.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::AlreadyExists(_) => "already_exists",
+            Self::InvalidName(_) => "invalid_name",
+            Self::Corrupt { .. } => "corrupt",
+            Self::Timeout(_) => "timeout",
+            Self::TooManyRedirects(_) => "too_many_redirects",
+            Self::NotADirectory(_) => "not_a_directory",
+            Self::NotInitialized(_) => "not_initialized",
+            Self::InvalidPath { .. } => "invalid_path",
+            Self::DuplicatePath { .. } => "duplicate_path",
+            Self::WrongType { .. } => "wrong_type",
+            Self::UnknownType { .. } => "unknown_type",
+            Self::ReadOnly(_) => "read_only",
+            Self::EncodePath(_) => "encode_path",
+            Self::Lock(_) => "lock",
+            Self::Path(_) => "io",
+            #[cfg(feature = "watch")]
+            Self::Watch(_) => "watch",
+        }
+    }
+
+    /// Returns the name of the workspace the error is about, if it's about a
+    /// single workspace.
+    pub fn workspace_name(&self) -> Option<&WorkspaceName> {
+        match self {
+            Self::NotFound(name)
+            | Self::AlreadyExists(name)
+            | Self::InvalidName(name)
+            | Self::Corrupt { name, .. }
+            | Self::DuplicatePath { name, .. } => Some(name),
+            Self::Timeout(_)
+            | Self::TooManyRedirects(_)
+            | Self::NotADirectory(_)
+            | Self::NotInitialized(_)
+            | Self::InvalidPath { .. }
+            | Self::WrongType { .. }
+            | Self::UnknownType { .. }
+            | Self::ReadOnly(_)
+            | Self::EncodePath(_)
+            | Self::Lock(_)
+            | Self::Path(_) => None,
+            #[cfg(feature = "watch")]
+            Self::Watch(_) => None,
+        }
+    }
+}
+
+/// Change to the workspace store observed by
+/// [`SimpleWorkspaceStore::watch()`].
+#[cfg(feature = "watch")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WorkspaceStoreEvent {
+    /// An entry was added for the workspace.
+    Added(WorkspaceNameBuf),
+    /// The entry of the workspace was removed.
+    Removed(WorkspaceNameBuf),
+    /// The entry of the workspace was replaced.
+    Changed(WorkspaceNameBuf),
+}
+
+/// Maps workspace names to the root directories of the workspaces.
+pub trait WorkspaceStore: Send + Sync + Debug {
+    /// Name of the store implementation.
+    fn name(&self) -> &str;
+
+    /// Returns the directory the entries are stored in, or `None` if the store
+    /// isn't backed by a directory.
+    fn store_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Returns the file the entry of the workspace is stored in, whether or
+    /// not it exists, or `None` if entries aren't stored in files. Fails if the
+    /// name can't be stored. This is meant for debugging.
+    fn entry_file(
+        &self,
+        _workspace_name: &WorkspaceName,
+    ) -> Result<Option<PathBuf>, WorkspaceStoreError> {
+        Ok(None)
+    }
+
+    /// Returns the recorded root of the workspace. Fails with
+    /// [`WorkspaceStoreError::NotFound`] if the workspace has no entry.
+    ///
+    /// Use this if the workspace is expected to have an entry, so that a
+    /// missing one is reported like any other error. Use
+    /// [`Self::try_get_path()`] if a missing entry is a normal outcome.
+    fn get_path(&self, workspace_name: &WorkspaceName) -> Result<PathBuf, WorkspaceStoreError>;
+
+    /// Like [`Self::get_path()`], but returns `None` if the workspace has no
+    /// entry. Other errors, e.g. about corrupt entries, are still returned.
+    fn try_get_path(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<PathBuf>, WorkspaceStoreError> {
+        match self.get_path(workspace_name) {
+            Ok(path) => Ok(Some(path)),
+            Err(WorkspaceStoreError::NotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the recorded roots of the given workspaces, in the same order.
+    /// Workspaces without an entry map to `None`.
+    ///
+    /// Only the entries of the given workspaces are read, which is cheaper
+    /// than [`Self::list()`] if there are many workspaces.
+    fn get_many(
+        &self,
+        workspace_names: &[WorkspaceNameBuf],
+    ) -> Result<Vec<(WorkspaceNameBuf, Option<PathBuf>)>, WorkspaceStoreError> {
+        workspace_names
+            .iter()
+            .map(|name| Ok((name.clone(), self.try_get_path(name)?)))
+            .collect()
+    }
+
+    /// Returns the names and recorded roots of all workspaces that have an
+    /// entry, sorted by name. Entries removed while they're being read are
+    /// skipped.
+    fn get_all_paths(&self) -> Result<Vec<(WorkspaceNameBuf, PathBuf)>, WorkspaceStoreError> {
+        let mut paths = vec![];
+        for name in self.list()? {
+            // Skipped if it was removed since it was listed
+            if let Some(path) = self.try_get_path(&name)? {
+                paths.push((name, path));
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Returns the recorded roots of all workspaces, so that they can be
+    /// compared with a later state of the store by [`diff()`].
+    fn snapshot(&self) -> Result<WorkspaceStoreSnapshot, WorkspaceStoreError> {
+        Ok(WorkspaceStoreSnapshot {
+            paths: self.get_all_paths()?.into_iter().collect(),
+        })
+    }
+
+    /// Returns the names of the workspaces whose recorded root is `path`,
+    /// sorted by name. Backends may canonicalize `path` like the roots they
+    /// record first.
+    fn find_by_path(&self, path: &Path) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        Ok(self
+            .get_all_paths()?
+            .into_iter()
+            .filter(|(_, root)| root == path)
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Returns true if any workspace is recorded at `path`. Unlike
+    /// [`Self::find_by_path()`], the recorded roots are canonicalized like
+    /// `path`, so a root recorded without being canonicalized, e.g. by an
+    /// older version, still matches. Backends canonicalize the paths like the
+    /// roots they record, which by default resolves symlinks.
+    fn path_exists(&self, path: &Path) -> Result<bool, WorkspaceStoreError> {
+        path_recorded(self, &DefaultPathCanonicalizer, path)
+    }
+
+    /// Returns the root of the workspace as it was given when it was recorded,
+    /// before symlinks were resolved. Falls back to [`Self::get_path()`] if
+    /// it wasn't recorded. The returned path is only meant to be shown to the
+    /// user.
+    fn get_display_path(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<PathBuf, WorkspaceStoreError> {
+        self.get_path(workspace_name)
+    }
+
+    /// Records `path` as the root of the workspace, replacing any previous
+    /// entry. The path is canonicalized, which usually requires it to exist.
+    /// If the canonical path differs from `path`, `path` is kept as the
+    /// display path.
+    fn set_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError>;
+
+    /// Like [`Self::set_path()`], but fails with
+    /// [`WorkspaceStoreError::AlreadyExists`] instead of replacing an existing
+    /// entry.
+    fn create_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError>;
+
+    /// Returns the number of bytes [`Self::set_path()`] would write for the
+    /// entry, without writing it. The path is checked and canonicalized as if
+    /// it were recorded, so this fails for paths that can't be recorded. This
+    /// lets callers that record many roots check a quota up front.
+    fn encoded_size(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<usize, WorkspaceStoreError>;
+
+    /// Removes the entry of the workspace. Returns false if the workspace had
+    /// no entry, which isn't an error.
+    fn remove_path(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError>;
+
+    /// Moves the entry of `old_name` to `new_name`. Fails with
+    /// [`WorkspaceStoreError::AlreadyExists`] if `new_name` already has an
+    /// entry. Everything recorded about the workspace, such as the version
+    /// that recorded its root and the time it was recorded, is kept.
+    fn rename(
+        &self,
+        old_name: &WorkspaceName,
+        new_name: &WorkspaceName,
+    ) -> Result<(), WorkspaceStoreError>;
+
+    /// Returns the names of all workspaces that have an entry, sorted by name
+    /// in byte order so that the output doesn't depend on the file system.
+    /// Entries may be removed by another process before they're read.
+    fn list(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError>;
+
+    /// Returns the number of workspaces that have an entry.
+    fn len(&self) -> Result<usize, WorkspaceStoreError> {
+        Ok(self.list()?.len())
+    }
+
+    /// Returns true if no workspace has an entry.
+    fn is_empty(&self) -> Result<bool, WorkspaceStoreError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Returns true if the workspace has an entry.
+    fn exists(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError>;
+
+    /// Returns the names of the given workspaces that have an entry. Prefer
+    /// this over calling [`Self::exists()`] for each name, which backends may
+    /// answer by reading the store once.
+    fn existing(
+        &self,
+        workspace_names: &[WorkspaceNameBuf],
+    ) -> Result<HashSet<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let mut names = HashSet::new();
+        for name in workspace_names {
+            if self.exists(name)? {
+                names.insert(name.clone());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Returns the time the entry of the workspace was last written.
+    fn entry_mtime(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<SystemTime, WorkspaceStoreError>;
+
+    /// Returns the version of jj that recorded the root of the workspace, or
+    /// `None` if the version that recorded it didn't keep track.
+    fn created_by_version(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<String>, WorkspaceStoreError>;
+
+    /// Returns the Unix user id of the user that recorded the root of the
+    /// workspace, or `None` if it wasn't recorded, e.g. on other platforms.
+    fn created_by_uid(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<u32>, WorkspaceStoreError>;
+
+    /// Returns the id of the operation that added the workspace, or `None` if
+    /// it wasn't recorded, e.g. because the root was backfilled.
+    fn created_at_op_id(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<OperationId>, WorkspaceStoreError>;
+
+    /// Records `op_id` as the operation that added the workspace. Fails with
+    /// [`WorkspaceStoreError::NotFound`] if the workspace has no entry. The
+    /// time the root was recorded is kept.
+    fn set_created_at_op_id(
+        &self,
+        workspace_name: &WorkspaceName,
+        op_id: &OperationId,
+    ) -> Result<(), WorkspaceStoreError>;
+
+    /// Returns true if the root was recorded by importing an existing
+    /// directory, and it hasn't been recorded again since. See
+    /// [`Self::set_imported()`].
+    fn is_imported(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError>;
+
+    /// Marks the root of the workspace as imported, e.g. by `jj workspace
+    /// import`. [`maintain()`] doesn't prune such entries, and
+    /// [`Self::diagnose()`] doesn't report them as orphans or foreign, since
+    /// the workspace may never have been added to the repo, e.g. if the root is
+    /// a Git worktree. Recording the root again clears
+    /// the mark. Fails with [`WorkspaceStoreError::NotFound`] if the workspace
+    /// has no entry. The time the root was recorded is kept.
+    fn set_imported(&self, workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError>;
+
+    /// Replaces all entries of the store with `entries`, recording each path
+    /// as with [`Self::set_path()`]. If a name is given more than once, the
+    /// last path wins. Backends that can't do better apply this as a
+    /// transaction, see [`Self::begin()`].
+    fn replace_all(
+        &self,
+        entries: &[(WorkspaceNameBuf, &Path)],
+    ) -> Result<(), WorkspaceStoreError> {
+        replace_all_in_txn(self, entries)
+    }
+
+    /// Starts a group of mutations that are applied together when the returned
+    /// transaction is finished. Backends that can't do better apply the
+    /// mutations one by one.
+    fn begin(&self) -> Result<Box<dyn WorkspaceStoreTxn + '_>, WorkspaceStoreError> {
+        Ok(Box::new(SequentialTxn {
+            store: self,
+            changes: vec![],
+        }))
+    }
+
+    /// Makes the preceding mutations durable. Callers doing many mutations
+    /// should call this once at the end rather than after each mutation.
+    fn flush(&self) -> Result<(), WorkspaceStoreError> {
+        Ok(())
+    }
+
+    /// Returns true if mutations of the store fail with
+    /// [`WorkspaceStoreError::ReadOnly`], e.g. because the repo is read-only.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Checks that an entry can be stored for the workspace name, with
+    /// [`WorkspaceStoreError::InvalidName`] if not. Backends that can store
+    /// any name accept all of them.
+    fn validate_name(&self, _workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError> {
+        Ok(())
+    }
+
+    /// Moves entries that can't be read out of the way. Returns the names of
+    /// the moved entries. Backends whose entries can't be corrupt do nothing.
+    fn repair(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        Ok(vec![])
+    }
+
+    /// Finishes renames that were interrupted by a crash. Returns the new
+    /// names of the finished renames. Backends that rename atomically do
+    /// nothing.
+    fn finish_renames(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        Ok(vec![])
+    }
+
+    /// Restores the entry of the workspace from the tombstone left behind
+    /// when it was removed. Returns false if there's nothing to restore, which
+    /// is always the case for backends that don't keep tombstones.
+    fn untombstone(&self, _workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        Ok(false)
+    }
+
+    /// Deletes the tombstones of entries that were removed before
+    /// `keep_newer`. Returns the number of deleted tombstones.
+    fn purge_tombstones(&self, _keep_newer: SystemTime) -> Result<usize, WorkspaceStoreError> {
+        Ok(0)
+    }
+
+    /// Removes leftovers of writes that were interrupted, e.g. by a crash.
+    /// Returns the number of removed files.
+    fn remove_temp_files(&self) -> Result<usize, WorkspaceStoreError> {
+        Ok(0)
+    }
+
+    /// Checks the store against the view and the file system. Nothing is
+    /// changed; see [`maintain()`] for the repairs.
+    ///
+    /// The default implementation only knows the recorded roots, so it can't
+    /// tell roots of other repos apart or find interrupted renames.
+    fn diagnose(&self, view: &View) -> Result<Diagnosis, WorkspaceStoreError> {
+        let mut diagnosis = Diagnosis::default();
+        let mut names_by_root: BTreeMap<PathBuf, Vec<WorkspaceNameBuf>> = BTreeMap::new();
+        for name in self.list()? {
+            let path = match self.try_get_path(&name) {
+                Ok(Some(path)) => path,
+                // Removed since it was listed
+                Ok(None) => continue,
+                Err(WorkspaceStoreError::Corrupt { .. }) => {
+                    diagnosis.corrupt.push(name);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if view.get_wc_commit_id(&name).is_none() && !self.is_imported(&name)? {
+                diagnosis.orphans.push(name.clone());
+            }
+            if !path.is_dir() {
+                diagnosis.missing.push(name.clone());
+            }
+            names_by_root.entry(path).or_default().push(name);
+        }
+        diagnosis.duplicate_paths = duplicate_paths(names_by_root);
+        diagnosis.unrecorded = unrecorded_names(self, view, &diagnosis.corrupt)?;
+        Ok(diagnosis)
+    }
+}
+
+/// Mutations of a [`WorkspaceStore`] that are applied together by
+/// [`WorkspaceStoreTxn::finish()`]. Dropping the transaction discards them.
+pub trait WorkspaceStoreTxn {
+    /// Records `path` as the root of the workspace. See
+    /// [`WorkspaceStore::set_path()`].
+    fn set_path(
+        &mut self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError>;
+
+    /// Records `path` as the root of the workspace, marked as imported. See
+    /// [`WorkspaceStore::set_imported()`].
+    fn set_imported_path(
+        &mut self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError>;
+
+    /// Removes the entry of the workspace. Returns false if the workspace had
+    /// no entry.
+    fn remove_path(&mut self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError>;
+
+    /// Applies the mutations.
+    fn finish(self: Box<Self>) -> Result<(), WorkspaceStoreError>;
+}
+
+enum SequentialChange {
+    Set(WorkspaceNameBuf, PathBuf),
+    SetImported(WorkspaceNameBuf, PathBuf),
+    Remove(WorkspaceNameBuf),
+}
+
+/// Transaction that applies the mutations one by one when finished.
+struct SequentialTxn<'a, S: ?Sized> {
+    store: &'a S,
+    changes: Vec<SequentialChange>,
+}
+
+impl<S: WorkspaceStore + ?Sized> WorkspaceStoreTxn for SequentialTxn<'_, S> {
+    fn set_path(
+        &mut self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.changes.push(SequentialChange::Set(
+            workspace_name.to_owned(),
+            path.to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn set_imported_path(
+        &mut self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.changes.push(SequentialChange::SetImported(
+            workspace_name.to_owned(),
+            path.to_owned(),
+        ));
+        Ok(())
+    }
+
+    fn remove_path(&mut self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        let existed = match self.changes.iter().rev().find_map(|change| match change {
+            SequentialChange::Set(name, _) | SequentialChange::SetImported(name, _) => {
+                (name == workspace_name).then_some(true)
+            }
+            SequentialChange::Remove(name) => (name == workspace_name).then_some(false),
+        }) {
+            Some(existed) => existed,
+            None => self.store.exists(workspace_name)?,
+        };
+        self.changes
+            .push(SequentialChange::Remove(workspace_name.to_owned()));
+        Ok(existed)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), WorkspaceStoreError> {
+        for change in &self.changes {
+            match change {
+                SequentialChange::Set(name, path) => self.store.set_path(name, path)?,
+                SequentialChange::SetImported(name, path) => {
+                    self.store.set_path(name, path)?;
+                    self.store.set_imported(name)?;
+                }
+                SequentialChange::Remove(name) => {
+                    self.store.remove_path(name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Workspace store that keeps one file per workspace in the
+/// `workspace_store` directory of the repo.
+///
+/// Writes go through a temporary file which is then renamed into place, so
+/// readers never observe a partially-written entry. Mutations are serialized
+/// by a lock file next to the store directory.
+///
+/// Reads don't take the lock, so they're safe to run concurrently with other
+/// processes, but only eventually consistent: an entry that is listed may be
+/// gone by the time it's read, in which case it's skipped or reported as
+/// [`WorkspaceStoreError::NotFound`].
+///
+/// A rename writes the entry of the new name before removing the old one. If
+/// it's interrupted in between, both entries exist until
+/// [`SimpleWorkspaceStore::finish_renames()`] is run.
+///
+/// Entries are protobuf messages, unless the store was created by
+/// [`SimpleWorkspaceStore::init_json()`], which writes them as JSON.
+#[derive(Debug)]
+pub struct SimpleWorkspaceStore {
+    repo_path: PathBuf,
+    workspace_store_dir: PathBuf,
+    options: SimpleWorkspaceStoreOptions,
+    read_only: bool,
+    format: EntryFormat,
+    /// Names of the entries and when they were listed. See
+    /// [`SimpleWorkspaceStoreOptions::listing_ttl`].
+    listing: Mutex<Option<(Instant, Arc<HashSet<WorkspaceNameBuf>>)>>,
+}
+
+/// How the entries of [`SimpleWorkspaceStore`] are serialized.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum EntryFormat {
+    /// Protobuf messages, optionally followed by a checksum.
+    Proto,
+    /// JSON objects in files with the `.json` suffix.
+    Json,
+}
+
+impl SimpleWorkspaceStore {
+    /// Returns the name of the store implementation.
+    pub fn name() -> &'static str {
+        "simple_workspace_store"
+    }
+
+    /// Creates the store of the repo at `repo_path`, or loads it if it was
+    /// already created. If the repo is read-only, the store can't be created,
+    /// but it can still be read, and it's empty.
+    ///
+    /// `repo_path` is the repo directory, which is `.jj/repo` in the workspace
+    /// the repo was created in. In other workspaces, `.jj/repo` is a file
+    /// pointing to that directory. Use
+    /// [`WorkspaceLoader::repo_path()`](crate::workspace::WorkspaceLoader::repo_path)
+    /// to find the repo directory of any workspace.
+    pub fn init(repo_path: &Path) -> Result<Self, WorkspaceStoreError> {
+        Self::init_with_options(repo_path, SimpleWorkspaceStoreOptions::default())
+    }
+
+    /// Like [`Self::init()`], but with non-default `options`.
+    pub fn init_with_options(
+        repo_path: &Path,
+        options: SimpleWorkspaceStoreOptions,
+    ) -> Result<Self, WorkspaceStoreError> {
+        let store = Self::open(repo_path, options, EntryFormat::Proto, true)?;
+        Ok(store.check_untyped()?.finish_interrupted_renames())
+    }
+
+    /// Loads the store of the repo at `repo_path`. Fails with
+    /// [`WorkspaceStoreError::NotInitialized`] if the store wasn't created by
+    /// [`Self::init()`], e.g. because the repo was created by an older version
+    /// of jj.
+    pub fn load(repo_path: &Path) -> Result<Self, WorkspaceStoreError> {
+        Self::load_with_options(repo_path, SimpleWorkspaceStoreOptions::default())
+    }
+
+    /// Like [`Self::load()`], but with non-default `options`.
+    pub fn load_with_options(
+        repo_path: &Path,
+        options: SimpleWorkspaceStoreOptions,
+    ) -> Result<Self, WorkspaceStoreError> {
+        let store = Self::open(repo_path, options, EntryFormat::Proto, false)?;
+        Ok(store.check_untyped()?.finish_interrupted_renames())
+    }
+
+    /// Returns the name of the JSON format, which is also the type recorded in
+    /// the directory of a store created by [`Self::init_json()`].
+    pub fn json_name() -> &'static str {
+        "json"
+    }
+
+    /// Like [`Self::init_with_options()`], but stores each entry as a
+    /// human-readable JSON file, `<name>.json`, so that entries can be
+    /// inspected and edited by hand. The format is recorded in the store
+    /// directory, so that the two formats aren't mixed in one repo. Fails if
+    /// the repo already has entries in another format.
+    pub fn init_json(
+        repo_path: &Path,
+        options: SimpleWorkspaceStoreOptions,
+    ) -> Result<Self, WorkspaceStoreError> {
+        let store = Self::open(repo_path, options, EntryFormat::Json, true)?;
+        match store.read_type()? {
+            Some(type_name) if type_name == Self::json_name() => {}
+            Some(type_name) => {
+                return Err(WorkspaceStoreError::WrongType {
+                    dir: store.workspace_store_dir,
+                    expected: Self::json_name(),
+                    actual: type_name,
+                });
+            }
+            None => {
+                let _lock = store.lock()?;
+                let proto_store =
+                    Self::open(repo_path, store.options.clone(), EntryFormat::Proto, true)?;
+                if !proto_store.is_empty()? {
+                    return Err(WorkspaceStoreError::WrongType {
+                        dir: store.workspace_store_dir,
+                        expected: Self::json_name(),
+                        actual: Self::name().to_owned(),
+                    });
+                }
+                let file = store.workspace_store_dir.join(TYPE_FILE_NAME);
+                fs::write(&file, Self::json_name()).context(&file)?;
+            }
+        }
+        Ok(store.finish_interrupted_renames())
+    }
+
+    /// Loads the store of the repo at `repo_path` in the JSON format. Fails if
+    /// the store wasn't created by [`Self::init_json()`]. Use
+    /// [`load_workspace_store()`] to load the store of a repo regardless of its
+    /// format.
+    pub fn load_json(
+        repo_path: &Path,
+        options: SimpleWorkspaceStoreOptions,
+    ) -> Result<Self, WorkspaceStoreError> {
+        let store = Self::open(repo_path, options, EntryFormat::Json, false)?;
+        let type_name = store
+            .read_type()?
+            .unwrap_or_else(|| Self::name().to_owned());
+        if type_name != Self::json_name() {
+            return Err(WorkspaceStoreError::WrongType {
+                dir: store.workspace_store_dir,
+                expected: Self::json_name(),
+                actual: type_name,
+            });
+        }
+        Ok(store.finish_interrupted_renames())
+    }
+
+    /// Loads the store of the repo at `repo_path` for reading only. Unlike
+    /// [`Self::load()`], nothing is written, not even the store directory, so
+    /// tools can enumerate the workspaces of many repos without modifying them
+    /// or loading anything else of the repo. A repo without a store has no
+    /// entries. Mutations fail with [`WorkspaceStoreError::ReadOnly`].
+    pub fn load_readonly(repo_path: &Path) -> Result<Self, WorkspaceStoreError> {
+        let mut workspace_store_dir = repo_path.join("workspace_store");
+        // `replace_all()` was interrupted after moving the old store aside, so
+        // the complete new entries are read instead.
+        let new_dir = sibling_dir(&workspace_store_dir, REPLACEMENT_DIR_SUFFIX);
+        if !workspace_store_dir.exists() && new_dir.is_dir() {
+            workspace_store_dir = new_dir;
+        }
+        if workspace_store_dir.symlink_metadata().is_ok() && !workspace_store_dir.is_dir() {
+            return Err(WorkspaceStoreError::NotADirectory(workspace_store_dir));
+        }
+        let store = Self {
+            repo_path: repo_path.to_owned(),
+            workspace_store_dir,
+            options: SimpleWorkspaceStoreOptions::default(),
+            read_only: true,
+            format: EntryFormat::Proto,
+            listing: Mutex::new(None),
+        };
+        store.check_untyped()
+    }
+
+    /// Fails if the store directory was created by another store
+    /// implementation.
+    fn check_untyped(self) -> Result<Self, WorkspaceStoreError> {
+        match self.read_type()? {
+            None => Ok(self),
+            Some(type_name) => Err(WorkspaceStoreError::WrongType {
+                dir: self.workspace_store_dir,
+                expected: Self::name(),
+                actual: type_name,
+            }),
+        }
+    }
+
+    /// Finishes the renames that were interrupted since the store was last
+    /// loaded, see [`Self::finish_renames()`]. This is done on a best-effort
+    /// basis: if it fails, the entries of both names are left for `jj
+    /// workspace doctor` to report. Read-only stores are returned as is.
+    fn finish_interrupted_renames(self) -> Self {
+        if !self.read_only
+            && self
+                .workspace_store_dir
+                .join(RENAME_MARKER_FILE_NAME)
+                .exists()
+        {
+            self.finish_renames().ok();
+        }
+        self
+    }
+
+    /// Opens the store directory, creating it if `create` is true.
+    fn open(
+        repo_path: &Path,
+        options: SimpleWorkspaceStoreOptions,
+        format: EntryFormat,
+        create: bool,
+    ) -> Result<Self, WorkspaceStoreError> {
+        let workspace_store_dir = repo_path.join("workspace_store");
+        // `replace_all()` was interrupted after moving the old store aside.
+        // Finish it, unless another process is already doing so.
+        let new_dir = sibling_dir(&workspace_store_dir, REPLACEMENT_DIR_SUFFIX);
+        if !workspace_store_dir.exists() && new_dir.is_dir() {
+            fs::rename(&new_dir, &workspace_store_dir).ok();
+        }
+        if !create && !workspace_store_dir.is_dir() {
+            return if workspace_store_dir.symlink_metadata().is_ok() {
+                Err(WorkspaceStoreError::NotADirectory(workspace_store_dir))
+            } else {
+                Err(WorkspaceStoreError::NotInitialized(workspace_store_dir))
+            };
+        }
+        let result = file_util::create_or_reuse_dir(&workspace_store_dir);
+        // Something other than a directory is in the way. Don't follow a
+        // dangling symlink and create the store somewhere unexpected.
+        if result.is_err() && workspace_store_dir.symlink_metadata().is_ok() {
+            return Err(WorkspaceStoreError::NotADirectory(workspace_store_dir));
+        }
+        // The repo is read-only, so the store can't be created. It's still
+        // possible to read it, and it's empty. An existing store may be on a
+        // read-only mount, which is only found out by writing to it.
+        let result = result.and_then(|()| {
+            tempfile::Builder::new()
+                .prefix(TEMP_FILE_PREFIX)
+                .tempfile_in(&workspace_store_dir)
+                .map(drop)
+        });
+        let read_only = match result {
+            Ok(()) => false,
+            Err(err) if is_read_only_error(&err) => true,
+            Err(err) => {
+                return Err(PathError {
+                    path: workspace_store_dir,
+                    source: err,
+                }
+                .into());
+            }
+        };
+        Ok(Self {
+            repo_path: repo_path.to_owned(),
+            workspace_store_dir,
+            options,
+            read_only,
+            format,
+            listing: Mutex::new(None),
+        })
+    }
+
+    /// Returns the store type recorded in the store directory, or `None` if
+    /// none is recorded.
+    fn read_type(&self) -> Result<Option<String>, WorkspaceStoreError> {
+        let file = self.workspace_store_dir.join(TYPE_FILE_NAME);
+        match fs::read_to_string(&file) {
+            Ok(type_name) => Ok(Some(type_name)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(PathError {
+                path: file,
+                source: err,
+            }
+            .into()),
+        }
+    }
+
+    /// Returns true if the store directory can't be written, e.g. because the
+    /// repo is read-only. Mutations of such a store fail with
+    /// [`WorkspaceStoreError::ReadOnly`].
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the directory the entries are stored in.
+    pub fn store_dir(&self) -> &Path {
+        &self.workspace_store_dir
+    }
+
+    /// Checks that an entry can be stored for the workspace name, with
+    /// [`WorkspaceStoreError::InvalidName`] if not. `/` separates namespaces,
+    /// so no component may be empty, `.`, or `..`. Other characters, including
+    /// those of reserved file names, are encoded, but each encoded component
+    /// must fit in a file name.
+    ///
+    /// Methods that write entries check their names with this before touching
+    /// the file system.
+    pub fn validate_name(&self, workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError> {
+        self.options.name_encoding.validate(workspace_name)
+    }
+
+    /// Moves the store directory to `new_dir`, which must not exist yet, and
+    /// uses it from then on. The directory is renamed if possible, or else
+    /// copied, e.g. to another file system. Either way, readers find the
+    /// complete store at the old or the new location, never a partial copy.
+    ///
+    /// Other instances of the store keep using the old location, and
+    /// [`Self::load()`] still looks for the store in the repo directory, so
+    /// callers that want later loads to find the moved store must link it
+    /// there, e.g. with a symlink.
+    pub fn relocate_store(&mut self, new_dir: &Path) -> Result<(), WorkspaceStoreError> {
+        let old_dir = self.workspace_store_dir.clone();
+        let lock = self.lock()?;
+        if new_dir.symlink_metadata().is_ok() {
+            return Err(PathError {
+                path: new_dir.to_owned(),
+                source: io::ErrorKind::AlreadyExists.into(),
+            }
+            .into());
+        }
+        if fs::rename(&old_dir, new_dir).is_ok() {
+            drop(lock);
+        } else {
+            copy_store_dir(&old_dir, new_dir)?;
+            // Move the old directory out of the way in one step, then delete
+            // it, so that readers don't see it partially deleted.
+            let parent_dir = old_dir.parent().unwrap();
+            let trash_dir = tempfile::Builder::new()
+                .prefix(TEMP_FILE_PREFIX)
+                .tempdir_in(parent_dir)
+                .context(parent_dir)?;
+            fs::rename(&old_dir, trash_dir.path().join("workspace_store")).context(&old_dir)?;
+            drop(lock);
+            drop(trash_dir);
+        }
+        self.workspace_store_dir = new_dir.to_owned();
+        self.invalidate_listing();
+        Ok(())
+    }
+
+    /// Watches the store for changes made by this or other processes. The
+    /// returned iterator blocks until the next change and ends if the store
+    /// can no longer be watched.
+    ///
+    /// Temporary files aren't reported. Several changes made in quick
+    /// succession may be reported as one event.
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> Result<impl Iterator<Item = WorkspaceStoreEvent>, WorkspaceStoreError> {
+        use notify::Watcher as _;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).map_err(WorkspaceStoreError::Watch)?;
+        watcher
+            .watch(&self.workspace_store_dir, notify::RecursiveMode::Recursive)
+            .map_err(WorkspaceStoreError::Watch)?;
+        // Entries are classified by comparing their current state to the names
+        // seen so far, since file system events don't reliably tell whether a
+        // file was created or replaced.
+        let known_names = self.list()?.into_iter().collect();
+        Ok(WorkspaceStoreWatcher {
+            store: self,
+            _watcher: watcher,
+            receiver,
+            known_names,
+            pending: vec![],
+        })
+    }
+
+    fn check_path(&self, path: &Path) -> Result<(), WorkspaceStoreError> {
+        let Some(depth) = self.options.repo_internal_depth else {
+            return Ok(());
+        };
+        let Some(repo_dir) = self.repo_path.ancestors().nth(depth) else {
+            return Ok(());
+        };
+        let repo_dir = self
+            .options
+            .canonicalizer
+            .canonicalize(repo_dir)
+            .context(repo_dir)?;
+        if path.starts_with(&repo_dir) {
+            return Err(WorkspaceStoreError::InvalidPath {
+                path: path.to_owned(),
+                repo_dir,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads the recorded roots of all entries, so that they can be looked up
+    /// by [`Self::check_duplicate_path()`] while the store is locked. Entries
+    /// that can't be read are ignored, so that they don't prevent recording
+    /// other roots.
+    fn read_recorded_paths(&self) -> Result<RecordedPaths, WorkspaceStoreError> {
+        let mut recorded_paths = RecordedPaths::default();
+        for name in self.list()? {
+            if let Ok(existing) = self.read_entry(&name) {
+                recorded_paths.insert(name, existing.path);
+            }
+        }
+        Ok(recorded_paths)
+    }
+
+    /// Checks that the root of `entry` isn't recorded for another workspace
+    /// if [`SimpleWorkspaceStoreOptions::reject_duplicate_paths`] is set.
+    /// The store must be locked.
+    fn reject_duplicate_path(&self, entry: &proto::Workspace) -> Result<(), WorkspaceStoreError> {
+        if !self.options.reject_duplicate_paths {
+            return Ok(());
+        }
+        self.check_duplicate_path(entry, &self.read_recorded_paths()?)
+    }
+
+    /// Fails if the root of `entry` is recorded for another workspace in
+    /// `recorded_paths`.
+    fn check_duplicate_path(
+        &self,
+        entry: &proto::Workspace,
+        recorded_paths: &RecordedPaths,
+    ) -> Result<(), WorkspaceStoreError> {
+        match recorded_paths
+            .names(&entry.path)
+            .find(|name| name.as_str() != entry.name)
+        {
+            Some(name) => Err(WorkspaceStoreError::DuplicatePath {
+                name: WorkspaceName::new(&entry.name).to_owned(),
+                existing_name: name.clone(),
+                path: file_util::path_from_bytes(&entry.path)
+                    .map_err(WorkspaceStoreError::EncodePath)?
+                    .to_owned(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves entries that can't be read, such as zero-byte files left behind
+    /// by older versions, to the `.corrupt` directory of the store. Returns the
+    /// names of the moved entries.
+    pub fn repair(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let _lock = self.lock()?;
+        let corrupt_dir = self.workspace_store_dir.join(CORRUPT_DIR_NAME);
+        let mut moved_names = vec![];
+        for name in self.list()? {
+            let is_corrupt = match self.read_entry(&name) {
+                Ok(entry) => entry.path.is_empty(),
+                // The file was just listed, so it must be empty.
+                Err(WorkspaceStoreError::NotFound(_) | WorkspaceStoreError::Corrupt { .. }) => true,
+                Err(err) => return Err(err),
+            };
+            if !is_corrupt {
+                continue;
+            }
+            file_util::create_or_reuse_dir(&corrupt_dir).context(&corrupt_dir)?;
+            let file = self.get_file(&name)?;
+            // Entries of nested names are moved out of their namespaces.
+            let file_name = self.entry_file_name(&name)?.replace('/', "%2F");
+            self.invalidate_listing();
+            fs::rename(&file, corrupt_dir.join(file_name)).context(&file)?;
+            moved_names.push(name);
+        }
+        if !moved_names.is_empty() {
+            self.update_index();
+        }
+        Ok(moved_names)
+    }
+
+    /// Finishes renames that were interrupted by a crash, which leaves the
+    /// entries of both the old and the new name behind. The entry of the old
+    /// name is removed unless it has since been recorded again with another
+    /// root. Returns the new names of the finished renames.
+    ///
+    /// This is done when the store is loaded if a rename was interrupted since
+    /// it was last loaded.
+    pub fn finish_renames(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let _lock = self.lock()?;
+        let mut names = vec![];
+        for name in self.list()? {
+            let mut entry = match self.read_entry(&name) {
+                Ok(entry) => entry,
+                Err(WorkspaceStoreError::NotFound(_) | WorkspaceStoreError::Corrupt { .. }) => {
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if entry.renamed_from.is_empty() {
+                continue;
+            }
+            let old_name = WorkspaceName::new(&entry.renamed_from);
+            match self.read_entry(old_name) {
+                Ok(old_entry) if old_entry.path == entry.path => {
+                    self.remove_entry(old_name)?;
+                }
+                Ok(_)
+                | Err(WorkspaceStoreError::NotFound(_) | WorkspaceStoreError::Corrupt { .. }) => {}
+                Err(err) => return Err(err),
+            }
+            let mtime = self.entry_mtime(&name)?;
+            entry.renamed_from.clear();
+            self.write_entry_with_mtime(&entry, mtime)?;
+            names.push(name);
+        }
+        if !names.is_empty() {
+            self.update_index();
+        }
+        let marker = self.workspace_store_dir.join(RENAME_MARKER_FILE_NAME);
+        match fs::remove_file(&marker) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(PathError {
+                    path: marker,
+                    source: err,
+                }
+                .into());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Records the link file `link` instead of the workspace root. The link
+    /// file contains the path to the root, relative to the directory of the
+    /// link file if it isn't absolute, and is conventionally named
+    /// [`WORKSPACE_LINK_FILE_NAME`]. The workspace can then be moved by
+    /// updating the link file rather than the store.
+    ///
+    /// The link is followed whenever the root is looked up. If the path it
+    /// contains is another file, that file is followed as a link too, up to a
+    /// limit. A root whose link file doesn't exist is reported as the path of
+    /// the link file, so that it shows up as missing.
+    pub fn set_link_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        link: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        let mut entry = self.new_entry(workspace_name, link)?;
+        entry.path_is_link = true;
+        entry.display_path = None;
+        self.retry_if_dir_vanished(|| {
+            let _lock = self.lock()?;
+            self.reject_duplicate_path(&entry)?;
+            self.write_entry(&entry)?;
+            self.update_index();
+            Ok(())
+        })
+    }
+
+    /// Restores the entry of the workspace from the tombstone left behind
+    /// when it was removed. See [`SimpleWorkspaceStoreOptions::tombstones`].
+    /// Returns false if there's no tombstone, or if the workspace has been
+    /// recorded again since, in which case the tombstone is kept.
+    pub fn untombstone(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        let _lock = self.lock()?;
+        if self.stat_entry(workspace_name)? {
+            return Ok(false);
+        }
+        let tombstone = self.tombstone_file(workspace_name)?;
+        if !tombstone.is_file() {
+            return Ok(false);
+        }
+        let content = fs::read(&tombstone).context(&tombstone)?;
+        let temp_file =
+            write_temp_file(&self.workspace_store_dir, self.options.file_mode, |file| {
+                file.write_all(&content)
+            })?;
+        self.persist_entry(workspace_name, temp_file)?;
+        fs::remove_file(&tombstone).context(&tombstone)?;
+        self.update_index();
+        Ok(true)
+    }
+
+    /// Deletes the tombstones of entries that were removed before
+    /// `keep_newer`. Returns the number of deleted tombstones.
+    pub fn purge_tombstones(&self, keep_newer: SystemTime) -> Result<usize, WorkspaceStoreError> {
+        let _lock = self.lock()?;
+        let dir = self.workspace_store_dir.join(TOMBSTONE_DIR_NAME);
+        let dir_entries = match dir.read_dir() {
+            Ok(dir_entries) => dir_entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => {
+                return Err(PathError {
+                    path: dir,
+                    source: err,
+                }
+                .into());
+            }
+        };
+        let mut count = 0;
+        for dir_entry in dir_entries {
+            let path = dir_entry.context(&dir)?.path();
+            let removed_at = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .context(&path)?;
+            if removed_at < keep_newer {
+                fs::remove_file(&path).context(&path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the name of the workspace whose entry is stored at `path`
+    /// relative to the store directory, or `None` if the file isn't an entry.
+    fn entry_name(&self, path: &Path) -> Option<WorkspaceNameBuf> {
+        // Entries are written by us, so a non-UTF-8 file name can't be one of
+        // them. Likewise for a file name that doesn't decode.
+        let file_names: Vec<_> = path
+            .components()
+            .map(|component| match component {
+                Component::Normal(file_name) => file_name.to_str(),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        if file_names.iter().any(|&file_name| {
+            file_name == RENAME_MARKER_FILE_NAME
+                || file_name == CORRUPT_DIR_NAME
+                || file_name == TOMBSTONE_DIR_NAME
+                || file_name.starts_with(TEMP_FILE_PREFIX)
+        }) {
+            return None;
+        }
+        self.options.name_encoding.decode(&file_names.join("/"))
+    }
+
+    /// Like [`Self::entry_name()`], but `path` is a file rather than a
+    /// namespace directory.
+    fn file_entry_name(&self, path: &Path) -> Option<WorkspaceNameBuf> {
+        match self.format {
+            EntryFormat::Proto => self.entry_name(path),
+            EntryFormat::Json => {
+                let file_name = path.file_name()?.to_str()?;
+                let stem = file_name.strip_suffix(JSON_FILE_SUFFIX)?;
+                self.entry_name(&path.with_file_name(stem))
+            }
+        }
+    }
+
+    /// Returns the names of the entries, reusing the previous listing if it's
+    /// recent enough. See [`SimpleWorkspaceStoreOptions::listing_ttl`].
+    fn cached_listing(&self) -> Result<Arc<HashSet<WorkspaceNameBuf>>, WorkspaceStoreError> {
+        let mut listing = self.listing.lock().unwrap();
+        if let (Some(ttl), Some((listed_at, names))) = (self.options.listing_ttl, &*listing)
+            && listed_at.elapsed() < ttl
+        {
+            return Ok(names.clone());
+        }
+        let listed_at = Instant::now();
+        let mut names = HashSet::new();
+        self.for_each_entry(|name| {
+            names.insert(name);
+        })?;
+        let names = Arc::new(names);
+        if self.options.listing_ttl.is_some() {
+            *listing = Some((listed_at, names.clone()));
+        }
+        Ok(names)
+    }
+
+    /// Returns true if the workspace has an entry, bypassing the cached
+    /// listing.
+    fn stat_entry(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        match self.stat_entry_once(workspace_name) {
+            Ok(false) if self.wait_for_replacement() => self.stat_entry_once(workspace_name),
+            result => result,
+        }
+    }
+
+    fn stat_entry_once(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        let file = self.get_file(workspace_name)?;
+        match fs::metadata(&file) {
+            Ok(metadata) => {
+                Ok(metadata.is_file()
+                    && (metadata.len() > 0 || !self.options.empty_entry_is_missing))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(PathError {
+                path: file,
+                source: err,
+            }
+            .into()),
+        }
+    }
+
+    /// Discards the cached listing. Must be called after every mutation.
+    fn invalidate_listing(&self) {
+        *self.listing.lock().unwrap() = None;
+    }
+
+    /// Calls `f` with the name of each entry.
+    fn for_each_entry(
+        &self,
+        mut f: impl FnMut(WorkspaceNameBuf),
+    ) -> Result<(), WorkspaceStoreError> {
+        let dir = &self.workspace_store_dir;
+        // Listing a missing store directory fails before anything is visited,
+        // so no entry is visited twice.
+        let result = match self.visit_dir(dir, Path::new(""), &mut f) {
+            Err(WorkspaceStoreError::Path(err))
+                if err.source.kind() == io::ErrorKind::NotFound
+                    && err.path == *dir
+                    && self.wait_for_replacement() =>
+            {
+                self.visit_dir(dir, Path::new(""), &mut f)
+            }
+            result => result,
+        };
+        match result {
+            Err(WorkspaceStoreError::Path(err))
+                if self.read_only && err.source.kind() == io::ErrorKind::NotFound =>
+            {
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Calls `f` with the names of the entries in `dir` and its
+    /// subdirectories. `dir` is `relative_dir` relative to the store directory.
+    fn visit_dir(
+        &self,
+        dir: &Path,
+        relative_dir: &Path,
+        f: &mut dyn FnMut(WorkspaceNameBuf),
+    ) -> Result<(), WorkspaceStoreError> {
+        for dir_entry in dir.read_dir().context(dir)? {
+            let dir_entry = dir_entry.context(dir)?;
+            let relative_path = relative_dir.join(dir_entry.file_name());
+            let file_type = match dir_entry.file_type() {
+                Ok(file_type) => file_type,
+                // Removed since it was listed. The file type may have to be
+                // looked up separately, depending on the file system.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(PathError {
+                        path: dir_entry.path(),
+                        source: err,
+                    }
+                    .into());
+                }
+            };
+            if file_type.is_dir() {
+                // Namespaces are encoded like names, so anything else, such
+                // as the `.corrupt` directory, isn't one.
+                if self.entry_name(&relative_path).is_some() {
+                    // Removed since it was listed
+                    match self.visit_dir(&dir_entry.path(), &relative_path, f) {
+                        Err(WorkspaceStoreError::Path(err))
+                            if err.source.kind() == io::ErrorKind::NotFound => {}
+                        result => result?,
+                    }
+                }
+            } else if let Some(name) = self.file_entry_name(&relative_path) {
+                f(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites the index file of this repo in the configured index directory.
+    /// Must be called while the store is locked so that concurrent updates
+    /// are applied in order.
+    fn update_index(&self) {
+        let Some(index_dir) = &self.options.index_dir else {
+            return;
+        };
+        if let Err(err) = self.write_index(index_dir) {
+            tracing::warn!(?err, ?index_dir, "failed to update the workspace index");
+        }
+    }
+
+    fn write_index(&self, index_dir: &Path) -> Result<(), WorkspaceStoreError> {
+        let repo_path = dunce::canonicalize(&self.repo_path).context(&self.repo_path)?;
+        let repo_path_bytes =
+            file_util::path_to_bytes(&repo_path).map_err(WorkspaceStoreError::EncodePath)?;
+        let hash = Blake2b512::digest(repo_path_bytes);
+        let file = index_dir.join(format!("{}.json", hex_util::encode_hex(&hash[..16])));
+        let mut workspaces = BTreeMap::new();
+        for (name, path) in self.get_all_paths()? {
+            let path_bytes =
+                file_util::path_to_bytes(&path).map_err(WorkspaceStoreError::EncodePath)?;
+            workspaces.insert(name.as_str().to_owned(), path_bytes.to_vec().into());
+        }
+        // Repos without workspaces, e.g. ones that are being deleted, aren't
+        // worth enumerating.
+        if workspaces.is_empty() {
+            return match fs::remove_file(&file) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(PathError {
+                    path: file,
+                    source: err,
+                }
+                .into()),
+                _ => Ok(()),
+            };
+        }
+        let index = JsonIndex {
+            repo_path: repo_path_bytes.to_vec().into(),
+            workspaces,
+        };
+        fs::create_dir_all(index_dir).context(index_dir)?;
+        let content = serde_json::to_vec_pretty(&index).unwrap();
+        let temp_file = write_temp_file(index_dir, None, |file| file.write_all(&content))?;
+        persist_temp_file(temp_file, &file).context(&file)?;
+        Ok(())
+    }
+
+    /// Waits for a concurrent [`Self::replace_all()`] to move the new store
+    /// directory into place, which leaves the store directory missing for a
+    /// moment. Returns true if the store directory was missing and has
+    /// reappeared, so a read that found nothing should be retried.
+    ///
+    /// If the replacement was interrupted, it's finished like
+    /// [`Self::load()`] does, unless the store is read-only.
+    fn wait_for_replacement(&self) -> bool {
+        let dir = &self.workspace_store_dir;
+        if dir.exists() {
+            return false;
+        }
+        let new_dir = sibling_dir(dir, REPLACEMENT_DIR_SUFFIX);
+        let deadline = Instant::now() + REPLACEMENT_WAIT;
+        while new_dir.is_dir() {
+            if Instant::now() >= deadline {
+                tracing::debug!(?dir, "finishing interrupted workspace store replacement");
+                if !self.read_only {
+                    fs::rename(&new_dir, dir).ok();
+                }
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        dir.exists()
+    }
+
+    /// Runs `f`, and if it fails because the store directory was removed in
+    /// the meantime, e.g. by a cleanup script, recreates the directory and
+    /// runs `f` once more.
+    fn retry_if_dir_vanished<T>(
+        &self,
+        f: impl Fn() -> Result<T, WorkspaceStoreError>,
+    ) -> Result<T, WorkspaceStoreError> {
+        let dir = &self.workspace_store_dir;
+        match f() {
+            Err(_) if !self.read_only && dir.symlink_metadata().is_err() => {
+                tracing::debug!(?dir, "recreating vanished workspace store directory");
+                file_util::create_or_reuse_dir(dir).context(dir)?;
+                f()
+            }
+            result => result,
+        }
+    }
+
+    /// Removes temporary files left behind by writes that were interrupted,
+    /// e.g. by a crash. Returns the number of removed files.
+    pub fn remove_temp_files(&self) -> Result<usize, WorkspaceStoreError> {
+        // Temporary files only exist while the store is locked, so any that
+        // are found now are stale.
+        let _lock = self.lock()?;
+        let dir = &self.workspace_store_dir;
+        let mut count = 0;
+        for dir_entry in dir.read_dir().context(dir)? {
+            let dir_entry = dir_entry.context(dir)?;
+            if dir_entry
+                .file_name()
+                .to_str()
+                .is_some_and(|file_name| file_name.starts_with(TEMP_FILE_PREFIX))
+            {
+                let path = dir_entry.path();
+                fs::remove_file(&path).context(&path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the path of the entry file relative to the store directory,
+    /// with components separated by `/`.
+    fn entry_file_name(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<String, WorkspaceStoreError> {
+        let file_name = self.options.name_encoding.encode(workspace_name)?;
+        Ok(match self.format {
+            EntryFormat::Proto => file_name,
+            EntryFormat::Json => file_name + JSON_FILE_SUFFIX,
+        })
+    }
+
+    fn get_file(&self, workspace_name: &WorkspaceName) -> Result<PathBuf, WorkspaceStoreError> {
+        let file_name = self.entry_file_name(workspace_name)?;
+        Ok(file_name
+            .split('/')
+            .fold(self.workspace_store_dir.clone(), |dir, file_name| {
+                dir.join(file_name)
+            }))
+    }
+
+    fn lock(&self) -> Result<FileLock, WorkspaceStoreError> {
+        let read_only_error = || WorkspaceStoreError::ReadOnly(self.workspace_store_dir.clone());
+        if self.read_only {
+            return Err(read_only_error());
+        }
+        // The store may exist on a read-only mount.
+        FileLock::lock(self.repo_path.join(LOCK_FILE_NAME)).map_err(|err| {
+            if is_read_only_error(&err.err) {
+                read_only_error()
+            } else {
+                err.into()
+            }
+        })
+    }
+
+    fn read_entry(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<proto::Workspace, WorkspaceStoreError> {
+        match self.read_entry_once(workspace_name) {
+            Err(WorkspaceStoreError::NotFound(_)) if self.wait_for_replacement() => {
+                self.read_entry_once(workspace_name)
+            }
+            result => result,
+        }
+    }
+
+    fn read_entry_once(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<proto::Workspace, WorkspaceStoreError> {
+        let file = self.get_file(workspace_name)?;
+        let buf = match self.read_file(&file)? {
+            Ok(buf) => buf,
+            // A directory is a namespace of nested names, not an entry.
+            Err(err) if err.kind() == io::ErrorKind::NotFound || file.is_dir() => {
+                return Err(WorkspaceStoreError::NotFound(workspace_name.to_owned()));
+            }
+            Err(err) => {
+                return Err(PathError {
+                    path: file,
+                    source: err,
+                }
+                .into());
+            }
+        };
+        if buf.is_empty() {
+            return Err(if self.options.empty_entry_is_missing {
+                WorkspaceStoreError::NotFound(workspace_name.to_owned())
+            } else {
+                WorkspaceStoreError::Corrupt {
+                    name: workspace_name.to_owned(),
+                    source: "Empty entry".into(),
+                }
+            });
+        }
+        let corrupt =
+            |source: Box<dyn std::error::Error + Send + Sync>| WorkspaceStoreError::Corrupt {
+                name: workspace_name.to_owned(),
+                source,
+            };
+        let buf = if buf.starts_with(&GZIP_MAGIC) {
+            gzip_decompress(&buf).map_err(|message| corrupt(message.into()))?
+        } else {
+            buf
+        };
+        if self.format == EntryFormat::Json {
+            let entry: JsonEntry =
+                serde_json::from_slice(&buf).map_err(|err| corrupt(err.into()))?;
+            return Ok(entry.into());
+        }
+        // Whether the entry has a trailing checksum is only known once the
+        // message is decoded.
+        if let Some((message, checksum)) = buf.split_last_chunk()
+            && let Ok(entry) = proto::Workspace::decode(message)
+            && entry.format_version >= CHECKSUM_FORMAT_VERSION
+        {
+            if crc32fast::hash(message) != u32::from_le_bytes(*checksum) {
+                return Err(corrupt("Checksum mismatch".into()));
+            }
+            return Ok(entry);
+        }
+        let entry = proto::Workspace::decode(&*buf).map_err(|err| corrupt(err.into()))?;
+        if entry.format_version >= CHECKSUM_FORMAT_VERSION {
+            return Err(corrupt("Missing checksum".into()));
+        }
+        Ok(entry)
+    }
+
+    fn encode_entry(&self, entry: &proto::Workspace) -> Vec<u8> {
+        let mut entry = entry.clone();
+        let buf = if self.format == EntryFormat::Json {
+            let mut buf = serde_json::to_vec_pretty(&JsonEntry::from(entry)).unwrap();
+            buf.push(b'\n');
+            buf
+        } else if self.options.checksum {
+            entry.format_version = CHECKSUM_FORMAT_VERSION;
+            let mut buf = entry.encode_to_vec();
+            buf.extend(crc32fast::hash(&buf).to_le_bytes());
+            buf
+        } else {
+            // Version 1 is implied by the absence of the field, which keeps the
+            // entry readable by older versions.
+            entry.format_version = 0;
+            entry.encode_to_vec()
+        };
+        if self.options.compress {
+            gzip_compress(&buf)
+        } else {
+            buf
+        }
+    }
+
+    /// Reads `file`, giving up after the configured timeout.
+    fn read_file(&self, file: &Path) -> Result<io::Result<Vec<u8>>, WorkspaceStoreError> {
+        let Some(timeout) = self.options.read_timeout else {
+            return Ok(fs::read(file));
+        };
+        let (sender, receiver) = mpsc::channel();
+        let thread_file = file.to_owned();
+        thread::spawn(move || sender.send(fs::read(thread_file)));
+        receiver
+            .recv_timeout(timeout)
+            .map_err(|_| WorkspaceStoreError::Timeout(file.to_owned()))
+    }
+
+    fn new_entry(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<proto::Workspace, WorkspaceStoreError> {
+        self.validate_name(workspace_name)?;
+        let display_path = file_util::normalize_path(&std::path::absolute(path).context(path)?);
+        let path = self
+            .options
+            .canonicalizer
+            .canonicalize(path)
+            .context(path)?;
+        self.check_path(&path)?;
+        let path_bytes =
+            file_util::path_to_bytes(&path).map_err(WorkspaceStoreError::EncodePath)?;
+        let display_path_bytes = if display_path == path {
+            None
+        } else {
+            let bytes =
+                file_util::path_to_bytes(&display_path).map_err(WorkspaceStoreError::EncodePath)?;
+            Some(bytes.to_owned())
+        };
+        Ok(proto::Workspace {
+            name: workspace_name.as_str().to_owned(),
+            path: path_bytes.to_owned(),
+            format_version: 0,
+            created_by_version: JJ_VERSION.to_owned(),
+            created_by_uid: current_uid(),
+            display_path: display_path_bytes,
+            renamed_from: String::new(),
+            created_at_op_id: vec![],
+            imported: false,
+            path_is_link: false,
+        })
+    }
+
+    fn write_temp_entry(
+        &self,
+        entry: &proto::Workspace,
+    ) -> Result<NamedTempFile, WorkspaceStoreError> {
+        let content = self.encode_entry(entry);
+        write_temp_file(&self.workspace_store_dir, self.options.file_mode, |file| {
+            file.write_all(&content)
+        })
+    }
+
+    fn persist_entry(
+        &self,
+        workspace_name: &WorkspaceName,
+        temp_file: NamedTempFile,
+    ) -> Result<(), WorkspaceStoreError> {
+        let file = self.get_file(workspace_name)?;
+        let invalid_name = || WorkspaceStoreError::InvalidName(workspace_name.to_owned());
+        // The entry can't replace a namespace of nested names, nor can it be
+        // nested in the entry of another workspace.
+        if file.is_dir() {
+            return Err(invalid_name());
+        }
+        let dir = file.parent().unwrap();
+        if let Err(err) = fs::create_dir_all(dir) {
+            if dir.ancestors().any(Path::is_file) {
+                return Err(invalid_name());
+            }
+            return Err(PathError {
+                path: dir.to_owned(),
+                source: err,
+            }
+            .into());
+        }
+        self.invalidate_listing();
+        persist_temp_file(temp_file, &file).context(&file)?;
+        Ok(())
+    }
+
+    fn write_entry(&self, entry: &proto::Workspace) -> Result<(), WorkspaceStoreError> {
+        let temp_file = self.write_temp_entry(entry)?;
+        self.persist_entry(WorkspaceName::new(&entry.name), temp_file)
+    }
+
+    /// Like [`Self::write_entry()`], but keeps the time the entry was last
+    /// written at `mtime`.
+    fn write_entry_with_mtime(
+        &self,
+        entry: &proto::Workspace,
+        mtime: SystemTime,
+    ) -> Result<(), WorkspaceStoreError> {
+        let temp_file = self.write_temp_entry(entry)?;
+        temp_file
+            .as_file()
+            .set_modified(mtime)
+            .context(temp_file.path())?;
+        self.persist_entry(WorkspaceName::new(&entry.name), temp_file)
+    }
+
+    fn remove_entry(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        let file = self.get_file(workspace_name)?;
+        self.invalidate_listing();
+        match fs::remove_file(&file) {
+            Ok(()) => {
+                self.remove_empty_namespaces(&file);
+                Ok(true)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(PathError {
+                path: file,
+                source: err,
+            }
+            .into()),
+        }
+    }
+
+    /// Removes the entry of a workspace that is being forgotten, keeping a
+    /// tombstone if configured.
+    fn discard_entry(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        if !self.options.tombstones {
+            return self.remove_entry(workspace_name);
+        }
+        let file = self.get_file(workspace_name)?;
+        let tombstone = self.tombstone_file(workspace_name)?;
+        self.invalidate_listing();
+        file_util::create_or_reuse_dir(tombstone.parent().unwrap()).context(&tombstone)?;
+        match fs::rename(&file, &tombstone) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => {
+                return Err(PathError {
+                    path: file,
+                    source: err,
+                }
+                .into());
+            }
+        }
+        // The age of the tombstone is the time the entry was removed.
+        fs::File::options()
+            .write(true)
+            .open(&tombstone)
+            .and_then(|tombstone_file| tombstone_file.set_modified(SystemTime::now()))
+            .context(&tombstone)?;
+        self.remove_empty_namespaces(&file);
+        Ok(true)
+    }
+
+    /// Removes the namespaces containing the removed entry `file` that became
+    /// empty. Removing a directory that isn't empty fails, which is fine.
+    fn remove_empty_namespaces(&self, file: &Path) {
+        for dir in file.ancestors().skip(1) {
+            if dir == self.workspace_store_dir || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn tombstone_file(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<PathBuf, WorkspaceStoreError> {
+        // Entries of nested names are moved out of their namespaces.
+        let file_name = self.entry_file_name(workspace_name)?.replace('/', "%2F");
+        Ok(self
+            .workspace_store_dir
+            .join(TOMBSTONE_DIR_NAME)
+            .join(file_name))
+    }
+}
+
+impl WorkspaceStore for SimpleWorkspaceStore {
+    fn name(&self) -> &str {
+        match self.format {
+            EntryFormat::Proto => Self::name(),
+            EntryFormat::Json => Self::json_name(),
+        }
+    }
+
+    fn store_dir(&self) -> Option<&Path> {
+        Some(&self.workspace_store_dir)
+    }
+
+    fn entry_file(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<PathBuf>, WorkspaceStoreError> {
+        Ok(Some(self.get_file(workspace_name)?))
+    }
+
+    fn get_path(&self, workspace_name: &WorkspaceName) -> Result<PathBuf, WorkspaceStoreError> {
+        let entry = self.read_entry(workspace_name)?;
+        if is_newer_version(&entry.created_by_version) {
+            tracing::warn!(
+                ?workspace_name,
+                version = entry.created_by_version,
+                "workspace store entry was written by a newer version of jj"
+            );
+        }
+        let path = decode_path(workspace_name, &entry.path)?;
+        if entry.path_is_link {
+            return resolve_link(&path);
+        }
+        Ok(path)
+    }
+
+    fn find_by_path(&self, path: &Path) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        // Paths that can't be canonicalized, e.g. because they no longer
+        // exist, are compared as they are.
+        let path = self
+            .options
+            .canonicalizer
+            .canonicalize(path)
+            .unwrap_or_else(|_| path.to_owned());
+        Ok(self
+            .get_all_paths()?
+            .into_iter()
+            .filter(|(_, root)| *root == path)
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    fn path_exists(&self, path: &Path) -> Result<bool, WorkspaceStoreError> {
+        path_recorded(self, self.options.canonicalizer.as_ref(), path)
+    }
+
+    fn get_display_path(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<PathBuf, WorkspaceStoreError> {
+        let entry = self.read_entry(workspace_name)?;
+        if entry.path_is_link {
+            return self.get_path(workspace_name);
+        }
+        decode_path(
+            workspace_name,
+            entry.display_path.as_ref().unwrap_or(&entry.path),
+        )
+    }
+
+    fn set_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        let entry = self.new_entry(workspace_name, path)?;
+        self.retry_if_dir_vanished(|| {
+            let _lock = self.lock()?;
+            self.reject_duplicate_path(&entry)?;
+            self.write_entry(&entry)?;
+            self.update_index();
+            Ok(())
+        })
+    }
+
+    fn create_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        let entry = self.new_entry(workspace_name, path)?;
+        self.retry_if_dir_vanished(|| {
+            let _lock = self.lock()?;
+            if self.stat_entry(workspace_name)? {
+                return Err(WorkspaceStoreError::AlreadyExists(
+                    workspace_name.to_owned(),
+                ));
+            }
+            self.reject_duplicate_path(&entry)?;
+            self.write_entry(&entry)?;
+            self.update_index();
+            Ok(())
+        })
+    }
+
+    fn encoded_size(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<usize, WorkspaceStoreError> {
+        let entry = self.new_entry(workspace_name, path)?;
+        Ok(self.encode_entry(&entry).len())
+    }
+
+    fn remove_path(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        self.retry_if_dir_vanished(|| {
+            let _lock = self.lock()?;
+            let existed = self.discard_entry(workspace_name)?;
+            if existed {
+                self.update_index();
+            }
+            Ok(existed)
+        })
+    }
+
+    fn rename(
+        &self,
+        old_name: &WorkspaceName,
+        new_name: &WorkspaceName,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.validate_name(new_name)?;
+        let _lock = self.lock()?;
+        let mut entry = self.read_entry(old_name)?;
+        if old_name == new_name {
+            return Ok(());
+        }
+        if self.stat_entry(new_name)? {
+            return Err(WorkspaceStoreError::AlreadyExists(new_name.to_owned()));
+        }
+        // Only the name changes. Other fields are copied as decoded, and the
+        // time the root was recorded is carried over.
+        let mtime = self.entry_mtime(old_name)?;
+        entry.name = new_name.as_str().to_owned();
+        // The new entry is marked until the old one is removed, so that
+        // `finish_renames()` can complete the rename after a crash. The marker
+        // file tells the next load that it needs to.
+        let marker = self.workspace_store_dir.join(RENAME_MARKER_FILE_NAME);
+        fs::write(&marker, b"").context(&marker)?;
+        entry.renamed_from = old_name.as_str().to_owned();
+        self.write_entry_with_mtime(&entry, mtime)?;
+        self.remove_entry(old_name)?;
+        entry.renamed_from.clear();
+        self.write_entry_with_mtime(&entry, mtime)?;
+        fs::remove_file(&marker).context(&marker)?;
+        self.update_index();
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let mut names = vec![];
+        self.for_each_entry(|name| names.push(name))?;
+        names.sort_unstable();
+        Ok(names)
+    }
+
+    fn len(&self) -> Result<usize, WorkspaceStoreError> {
+        let mut count = 0;
+        self.for_each_entry(|_| count += 1)?;
+        Ok(count)
+    }
+
+    fn exists(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        if self.options.listing_ttl.is_some() {
+            Ok(!self.existing(&[workspace_name.to_owned()])?.is_empty())
+        } else {
+            self.stat_entry(workspace_name)
+        }
+    }
+
+    fn existing(
+        &self,
+        workspace_names: &[WorkspaceNameBuf],
+    ) -> Result<HashSet<WorkspaceNameBuf>, WorkspaceStoreError> {
+        for name in workspace_names {
+            self.get_file(name)?;
+        }
+        let listing = self.cached_listing()?;
+        let mut names = HashSet::new();
+        for name in workspace_names {
+            // Listed entries may be empty, which only the entry itself tells.
+            if listing.contains(name) && self.stat_entry(name)? {
+                names.insert(name.clone());
+            }
+        }
+        Ok(names)
+    }
+
+    fn entry_mtime(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<SystemTime, WorkspaceStoreError> {
+        let file = self.get_file(workspace_name)?;
+        match fs::metadata(&file).and_then(|metadata| metadata.modified()) {
+            Ok(_) if file.is_dir() => Err(WorkspaceStoreError::NotFound(workspace_name.to_owned())),
+            Ok(mtime) => Ok(mtime),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Err(WorkspaceStoreError::NotFound(workspace_name.to_owned()))
+            }
+            Err(err) => Err(PathError {
+                path: file,
+                source: err,
+            }
+            .into()),
+        }
+    }
+
+    fn created_by_version(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<String>, WorkspaceStoreError> {
+        let entry = self.read_entry(workspace_name)?;
+        Ok((!entry.created_by_version.is_empty()).then_some(entry.created_by_version))
+    }
+
+    fn created_by_uid(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<u32>, WorkspaceStoreError> {
+        Ok(self.read_entry(workspace_name)?.created_by_uid)
+    }
+
+    fn created_at_op_id(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<OperationId>, WorkspaceStoreError> {
+        let entry = self.read_entry(workspace_name)?;
+        Ok((!entry.created_at_op_id.is_empty()).then(|| OperationId::new(entry.created_at_op_id)))
+    }
+
+    fn set_created_at_op_id(
+        &self,
+        workspace_name: &WorkspaceName,
+        op_id: &OperationId,
+    ) -> Result<(), WorkspaceStoreError> {
+        let _lock = self.lock()?;
+        let mut entry = self.read_entry(workspace_name)?;
+        let mtime = self.entry_mtime(workspace_name)?;
+        entry.created_at_op_id = op_id.to_bytes();
+        self.write_entry_with_mtime(&entry, mtime)
+    }
+
+    fn is_imported(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        Ok(self.read_entry(workspace_name)?.imported)
+    }
+
+    fn set_imported(&self, workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError> {
+        let _lock = self.lock()?;
+        let mut entry = self.read_entry(workspace_name)?;
+        let mtime = self.entry_mtime(workspace_name)?;
+        entry.imported = true;
+        self.write_entry_with_mtime(&entry, mtime)
+    }
+
+    /// Writes the entries to a new store directory, which then takes the place
+    /// of the old one. If this is interrupted after the old directory was
+    /// moved aside, the next load finishes the replacement. Tombstones and
+    /// entries moved aside by [`Self::repair()`] are discarded.
+    ///
+    /// Reads that find the store directory missing while it's being swapped
+    /// wait for the swap to finish.
+    ///
+    /// On Windows, a directory can't be renamed while files in it are open,
+    /// e.g. by concurrent readers, so the entries are replaced in a
+    /// transaction instead.
+    fn replace_all(
+        &self,
+        entries: &[(WorkspaceNameBuf, &Path)],
+    ) -> Result<(), WorkspaceStoreError> {
+        if !cfg!(unix) {
+            return replace_all_in_txn(self, entries);
+        }
+        let new_entries: BTreeMap<_, _> = entries
+            .iter()
+            .map(|(name, path)| Ok((name.clone(), self.new_entry(name, path)?)))
+            .collect::<Result<_, WorkspaceStoreError>>()?;
+        if self.options.reject_duplicate_paths {
+            let mut names_by_path: HashMap<&[u8], &WorkspaceNameBuf> = HashMap::new();
+            for (name, entry) in &new_entries {
+                if let Some(existing_name) = names_by_path.insert(&entry.path, name) {
+                    return Err(WorkspaceStoreError::DuplicatePath {
+                        name: name.clone(),
+                        existing_name: existing_name.clone(),
+                        path: file_util::path_from_bytes(&entry.path)
+                            .map_err(WorkspaceStoreError::EncodePath)?
+                            .to_owned(),
+                    });
+                }
+            }
+        }
+
+        let lock = self.lock()?;
+        let new_dir = sibling_dir(&self.workspace_store_dir, REPLACEMENT_DIR_SUFFIX);
+        let old_dir = sibling_dir(&self.workspace_store_dir, REPLACED_DIR_SUFFIX);
+        // Left behind by an earlier replacement that failed or was
+        // interrupted
+        for dir in [&new_dir, &old_dir] {
+            match fs::remove_dir_all(dir) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(PathError {
+                        path: dir.clone(),
+                        source: err,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        // Write the new directory under a temporary name first, so that it
+        // isn't mistaken for a complete replacement if this fails.
+        let parent_dir = self.workspace_store_dir.parent().unwrap();
+        let temp_dir = tempfile::Builder::new()
+            .prefix(TEMP_FILE_PREFIX)
+            .tempdir_in(parent_dir)
+            .context(parent_dir)?;
+        let type_file = self.workspace_store_dir.join(TYPE_FILE_NAME);
+        if type_file.exists() {
+            let new_type_file = temp_dir.path().join(TYPE_FILE_NAME);
+            fs::copy(&type_file, &new_type_file).context(&type_file)?;
+        }
+        for (name, entry) in &new_entries {
+            let file = self
+                .entry_file_name(name)?
+                .split('/')
+                .fold(temp_dir.path().to_owned(), |dir, file_name| {
+                    dir.join(file_name)
+                });
+            let invalid_name = || WorkspaceStoreError::InvalidName(name.clone());
+            // Entries are written in order, so a namespace of nested names is
+            // created after the entry of the same name.
+            let dir = file.parent().unwrap();
+            if fs::create_dir_all(dir).is_err() && dir.ancestors().any(Path::is_file) {
+                return Err(invalid_name());
+            }
+            fs::File::create_new(&file)
+                .and_then(|mut new_file| {
+                    set_file_mode(&new_file, self.options.file_mode)?;
+                    new_file.write_all(&self.encode_entry(entry))?;
+                    new_file.sync_all()
+                })
+                .context(&file)?;
+        }
+        fs::rename(temp_dir.path(), &new_dir).context(&new_dir)?;
+
+        // The store is missing between the two renames, so concurrent reads
+        // wait for the second one. The lock file isn't in the store
+        // directory, so writers keep waiting for the lock meanwhile. If this
+        // is interrupted in between, loading the store finishes the
+        // replacement.
+        self.invalidate_listing();
+        fs::rename(&self.workspace_store_dir, &old_dir).context(&self.workspace_store_dir)?;
+        match fs::rename(&new_dir, &self.workspace_store_dir) {
+            Ok(()) => {}
+            // Finished by another process loading the store
+            Err(err)
+                if err.kind() == io::ErrorKind::NotFound && self.workspace_store_dir.is_dir() => {}
+            Err(err) => {
+                return Err(PathError {
+                    path: new_dir,
+                    source: err,
+                }
+                .into());
+            }
+        }
+        self.update_index();
+        drop(lock);
+        fs::remove_dir_all(&old_dir).ok();
+        Ok(())
+    }
+
+    fn begin(&self) -> Result<Box<dyn WorkspaceStoreTxn + '_>, WorkspaceStoreError> {
+        Ok(Box::new(SimpleWorkspaceStoreTxn {
+            store: self,
+            changes: BTreeMap::new(),
+            recorded_paths: None,
+            _lock: self.lock()?,
+        }))
+    }
+
+    fn flush(&self) -> Result<(), WorkspaceStoreError> {
+        // Entry contents are synced when they're written, but the directory
+        // entries pointing to them have to be synced separately. Directories
+        // can't be opened as files on Windows.
+        if cfg!(unix) && !self.read_only {
+            let dir = &self.workspace_store_dir;
+            fs::File::open(dir)
+                .and_then(|file| file.sync_all())
+                .context(dir)?;
+        }
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        Self::is_read_only(self)
+    }
+
+    fn validate_name(&self, workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError> {
+        Self::validate_name(self, workspace_name)
+    }
+
+    fn repair(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        Self::repair(self)
+    }
+
+    fn finish_renames(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        Self::finish_renames(self)
+    }
+
+    fn untombstone(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        Self::untombstone(self, workspace_name)
+    }
+
+    fn purge_tombstones(&self, keep_newer: SystemTime) -> Result<usize, WorkspaceStoreError> {
+        Self::purge_tombstones(self, keep_newer)
+    }
+
+    fn remove_temp_files(&self) -> Result<usize, WorkspaceStoreError> {
+        Self::remove_temp_files(self)
+    }
+
+    fn diagnose(&self, view: &View) -> Result<Diagnosis, WorkspaceStoreError> {
+        let mut diagnosis = Diagnosis::default();
+        let repo_path = dunce::canonicalize(&self.repo_path).context(&self.repo_path)?;
+        let mut names_by_root: BTreeMap<PathBuf, Vec<WorkspaceNameBuf>> = BTreeMap::new();
+        for name in self.list()? {
+            let entry = match self.read_entry(&name) {
+                Ok(entry) if !entry.path.is_empty() => entry,
+                // The file was just listed, so it must be empty.
+                Ok(_)
+                | Err(WorkspaceStoreError::NotFound(_) | WorkspaceStoreError::Corrupt { .. }) => {
+                    diagnosis.corrupt.push(name);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if !entry.renamed_from.is_empty() {
+                diagnosis.interrupted_renames.push(name.clone());
+            }
+            if view.get_wc_commit_id(&name).is_none() && !entry.imported {
+                diagnosis.orphans.push(name.clone());
+            }
+            let mut path = decode_path(&name, &entry.path)?;
+            if entry.path_is_link {
+                // A link that can't be resolved leaves the root missing
+                path = resolve_link(&path).unwrap_or(path);
+            }
+            if !path.is_dir() {
+                diagnosis.missing.push(name.clone());
+            } else if !entry.imported && !is_workspace_of(&path, &repo_path) {
+                diagnosis.foreign.push(name.clone());
+            }
+            names_by_root.entry(path).or_default().push(name);
+        }
+        diagnosis.duplicate_paths = duplicate_paths(names_by_root);
+        diagnosis.unrecorded = unrecorded_names(self, view, &diagnosis.corrupt)?;
+        if self.options.name_encoding == NameEncoding::Plain {
+            diagnosis.case_collisions = view
+                .wc_commit_ids()
+                .keys()
+                .cloned()
+                .chain(self.list()?)
+                .sorted()
+                .dedup()
+                .into_group_map_by(|name| name.as_str().to_lowercase())
+                .into_values()
+                .filter(|names| names.len() > 1)
+                .sorted()
+                .collect();
+        }
+        Ok(diagnosis)
+    }
+}
+
+/// Entry of [`SimpleWorkspaceStore`] in the JSON format.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct JsonEntry {
+    name: String,
+    path: JsonPath,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    created_by_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_by_uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_path: Option<JsonPath>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    renamed_from: String,
+    /// Hex-encoded operation id. Values that aren't valid hex are ignored.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    created_at_op_id: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    imported: bool,
+    path_is_link: bool,
+}
+
+/// Workspace root as recorded in the JSON format. Roots that aren't valid UTF-8
+/// are recorded as an array of bytes.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum JsonPath {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<Vec<u8>> for JsonPath {
+    fn from(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Self::Text(text),
+            Err(err) => Self::Bytes(err.into_bytes()),
+        }
+    }
+}
+
+impl From<JsonPath> for Vec<u8> {
+    fn from(path: JsonPath) -> Self {
+        match path {
+            JsonPath::Text(text) => text.into_bytes(),
+            JsonPath::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// Index file of one repo in [`SimpleWorkspaceStoreOptions::index_dir`].
+#[derive(serde::Deserialize, serde::Serialize)]
+struct JsonIndex {
+    repo_path: JsonPath,
+    workspaces: BTreeMap<String, JsonPath>,
+}
+
+impl From<proto::Workspace> for JsonEntry {
+    fn from(entry: proto::Workspace) -> Self {
+        Self {
+            name: entry.name,
+            path: entry.path.into(),
+            created_by_version: entry.created_by_version,
+            created_by_uid: entry.created_by_uid,
+            display_path: entry.display_path.map(JsonPath::from),
+            renamed_from: entry.renamed_from,
+            created_at_op_id: hex_util::encode_hex(&entry.created_at_op_id),
+            imported: entry.imported,
+            path_is_link: entry.path_is_link,
+        }
+    }
+}
+
+impl From<JsonEntry> for proto::Workspace {
+    fn from(entry: JsonEntry) -> Self {
+        Self {
+            name: entry.name,
+            path: entry.path.into(),
+            format_version: 0,
+            created_by_version: entry.created_by_version,
+            created_by_uid: entry.created_by_uid,
+            display_path: entry.display_path.map(Vec::from),
+            renamed_from: entry.renamed_from,
+            created_at_op_id: hex_util::decode_hex(&entry.created_at_op_id).unwrap_or_default(),
+            imported: entry.imported,
+            path_is_link: entry.path_is_link,
+        }
+    }
+}
+
+/// Workspaces of one repo as recorded in an index directory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexedRepo {
+    /// The repo directory, which is `.jj/repo` in the workspace that created
+    /// the repo.
+    pub repo_path: PathBuf,
+    /// Workspace names and roots, sorted by name.
+    pub workspaces: Vec<(WorkspaceNameBuf, PathBuf)>,
+}
+
+/// Reads the index written by the stores that were loaded with
+/// [`SimpleWorkspaceStoreOptions::index_dir`] set to `index_dir`. Returns the
+/// repos sorted by path. Index files that can't be read, e.g. because they're
+/// being replaced, are skipped. A missing directory is an empty index.
+pub fn read_workspace_index(index_dir: &Path) -> Result<Vec<IndexedRepo>, WorkspaceStoreError> {
+    let dir_entries = match index_dir.read_dir() {
+        Ok(dir_entries) => dir_entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => {
+            return Err(PathError {
+                path: index_dir.to_owned(),
+                source: err,
+            }
+            .into());
+        }
+    };
+    let decode = |bytes: Vec<u8>| file_util::path_from_bytes(&bytes).map(Path::to_owned).ok();
+    let mut repos = vec![];
+    for dir_entry in dir_entries {
+        let file = dir_entry.context(index_dir)?.path();
+        if file.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let index: JsonIndex = match fs::read(&file)
+            .map_err(|err| err.to_string())
+            .and_then(|buf| serde_json::from_slice(&buf).map_err(|err| err.to_string()))
+        {
+            Ok(index) => index,
+            Err(err) => {
+                tracing::warn!(?file, err, "skipping unreadable workspace index file");
+                continue;
+            }
+        };
+        let Some(repo_path) = decode(index.repo_path.into()) else {
+            continue;
+        };
+        let workspaces = index
+            .workspaces
+            .into_iter()
+            .filter_map(|(name, path)| Some((name.into(), decode(path.into())?)))
+            .collect();
+        repos.push(IndexedRepo {
+            repo_path,
+            workspaces,
+        });
+    }
+    repos.sort_by(|a, b| a.repo_path.cmp(&b.repo_path));
+    Ok(repos)
+}
+
+/// Loads the store of the repo at `repo_path` with the implementation recorded
+/// in the store directory. Fails with [`WorkspaceStoreError::NotInitialized`]
+/// if the store wasn't created.
+pub fn load_workspace_store(
+    repo_path: &Path,
+    options: SimpleWorkspaceStoreOptions,
+) -> Result<Box<dyn WorkspaceStore>, WorkspaceStoreError> {
+    let store = SimpleWorkspaceStore::open(repo_path, options, EntryFormat::Proto, false)?;
+    match store.read_type()? {
+        None => Ok(Box::new(store.finish_interrupted_renames())),
+        Some(type_name) if type_name == SimpleWorkspaceStore::json_name() => {
+            let store = SimpleWorkspaceStore {
+                format: EntryFormat::Json,
+                ..store
+            };
+            Ok(Box::new(store.finish_interrupted_renames()))
+        }
+        Some(type_name) => Err(WorkspaceStoreError::UnknownType {
+            dir: store.workspace_store_dir,
+            type_name,
+        }),
+    }
+}
+
+/// Recorded roots of the entries of a [`SimpleWorkspaceStore`], looked up by
+/// root to find duplicates.
+#[derive(Debug, Default)]
+struct RecordedPaths {
+    names_by_path: HashMap<Vec<u8>, BTreeSet<WorkspaceNameBuf>>,
+    paths_by_name: HashMap<WorkspaceNameBuf, Vec<u8>>,
+}
+
+impl RecordedPaths {
+    fn insert(&mut self, workspace_name: WorkspaceNameBuf, path: Vec<u8>) {
+        self.remove(&workspace_name);
+        self.names_by_path
+            .entry(path.clone())
+            .or_default()
+            .insert(workspace_name.clone());
+        self.paths_by_name.insert(workspace_name, path);
+    }
+
+    fn remove(&mut self, workspace_name: &WorkspaceName) {
+        let Some(path) = self.paths_by_name.remove(workspace_name) else {
+            return;
+        };
+        if let Some(names) = self.names_by_path.get_mut(&path) {
+            names.remove(workspace_name);
+            if names.is_empty() {
+                self.names_by_path.remove(&path);
+            }
+        }
+    }
+
+    /// Returns the names of the workspaces whose root is `path`.
+    fn names(&self, path: &[u8]) -> impl Iterator<Item = &WorkspaceNameBuf> {
+        self.names_by_path.get(path).into_iter().flatten()
+    }
+}
+
+/// Transaction of [`SimpleWorkspaceStore`]. The store stays locked until the
+/// transaction is finished or dropped. New entries are written to temporary
+/// files, which are renamed into place when the transaction is finished.
+struct SimpleWorkspaceStoreTxn<'a> {
+    store: &'a SimpleWorkspaceStore,
+    /// New entries by workspace name. `None` means the entry is removed.
+    changes: BTreeMap<WorkspaceNameBuf, Option<NamedTempFile>>,
+    /// Recorded roots including the changes, read by the first `set_path()` if
+    /// duplicate roots are rejected.
+    recorded_paths: Option<RecordedPaths>,
+    // Declared last so that discarded temporary files are deleted before the
+    // lock is released.
+    _lock: FileLock,
+}
+
+impl SimpleWorkspaceStoreTxn<'_> {
+    fn set_entry(&mut self, entry: proto::Workspace) -> Result<(), WorkspaceStoreError> {
+        let workspace_name = WorkspaceName::new(&entry.name);
+        if self.store.options.reject_duplicate_paths {
+            let recorded_paths = match &mut self.recorded_paths {
+                Some(recorded_paths) => recorded_paths,
+                None => self
+                    .recorded_paths
+                    .insert(self.store.read_recorded_paths()?),
+            };
+            self.store.check_duplicate_path(&entry, recorded_paths)?;
+            recorded_paths.insert(workspace_name.to_owned(), entry.path.clone());
+        }
+        let temp_file = self.store.write_temp_entry(&entry)?;
+        self.changes
+            .insert(workspace_name.to_owned(), Some(temp_file));
+        Ok(())
+    }
+}
+
+impl WorkspaceStoreTxn for SimpleWorkspaceStoreTxn<'_> {
+    fn set_path(
+        &mut self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        let entry = self.store.new_entry(workspace_name, path)?;
+        self.set_entry(entry)
+    }
+
+    fn set_imported_path(
+        &mut self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        let mut entry = self.store.new_entry(workspace_name, path)?;
+        entry.imported = true;
+        self.set_entry(entry)
+    }
+
+    fn remove_path(&mut self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        if let Some(recorded_paths) = &mut self.recorded_paths {
+            recorded_paths.remove(workspace_name);
+        }
+        let existed = match self.changes.insert(workspace_name.to_owned(), None) {
+            Some(change) => change.is_some(),
+            None => self.store.stat_entry(workspace_name)?,
+        };
+        Ok(existed)
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), WorkspaceStoreError> {
+        for (name, change) in self.changes {
+            match change {
+                Some(temp_file) => self.store.persist_entry(&name, temp_file)?,
+                None => {
+                    self.store.discard_entry(&name)?;
+                }
+            }
+        }
+        self.store.update_index();
+        Ok(())
+    }
+}
+
+/// Iterator returned by [`SimpleWorkspaceStore::watch()`].
+#[cfg(feature = "watch")]
+struct WorkspaceStoreWatcher<'a> {
+    store: &'a SimpleWorkspaceStore,
+    // Stops sending events when dropped.
+    _watcher: notify::RecommendedWatcher,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    known_names: HashSet<WorkspaceNameBuf>,
+    /// Events derived from the last file system event, in reverse order.
+    pending: Vec<WorkspaceStoreEvent>,
+}
+
+#[cfg(feature = "watch")]
+impl WorkspaceStoreWatcher<'_> {
+    fn classify(&mut self, name: WorkspaceNameBuf) -> Option<WorkspaceStoreEvent> {
+        let exists = match self.store.stat_entry(&name) {
+            Ok(exists) => exists,
+            Err(err) => {
+                tracing::warn!(?err, "failed to check workspace store entry");
+                return None;
+            }
+        };
+        match (exists, self.known_names.contains(&name)) {
+            (true, true) => Some(WorkspaceStoreEvent::Changed(name)),
+            (true, false) => {
+                self.known_names.insert(name.clone());
+                Some(WorkspaceStoreEvent::Added(name))
+            }
+            (false, true) => {
+                self.known_names.remove(&name);
+                Some(WorkspaceStoreEvent::Removed(name))
+            }
+            (false, false) => None,
+        }
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Iterator for WorkspaceStoreWatcher<'_> {
+    type Item = WorkspaceStoreEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop() {
+                return Some(event);
+            }
+            let event = match self.receiver.recv().ok()? {
+                Ok(event) => event,
+                Err(err) => {
+                    tracing::warn!(?err, "failed to watch workspace store");
+                    continue;
+                }
+            };
+            // Renames are also reported as separate events for the source and
+            // the destination.
+            if event.kind.is_access()
+                || event.kind
+                    == notify::EventKind::Modify(notify::event::ModifyKind::Name(
+                        notify::event::RenameMode::Both,
+                    ))
+            {
+                continue;
+            }
+            let names = event
+                .paths
+                .iter()
+                .filter_map(|path| {
+                    let relative_path = path.strip_prefix(&self.store.workspace_store_dir).ok()?;
+                    self.store.file_entry_name(relative_path)
+                })
+                .unique()
+                .collect_vec();
+            let mut events = names
+                .into_iter()
+                .filter_map(|name| self.classify(name))
+                .collect_vec();
+            events.reverse();
+            self.pending = events;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn current_uid() -> Option<u32> {
+    Some(rustix::process::getuid().as_raw())
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> Option<u32> {
+    None
+}
+
+/// Decodes a path recorded in the entry of the workspace. Entries always
+/// record a path, so an empty one means that the entry is corrupt.
+fn decode_path(
+    workspace_name: &WorkspaceName,
+    path_bytes: &[u8],
+) -> Result<PathBuf, WorkspaceStoreError> {
+    let corrupt = |source: Box<dyn std::error::Error + Send + Sync>| WorkspaceStoreError::Corrupt {
+        name: workspace_name.to_owned(),
+        source,
+    };
+    if path_bytes.is_empty() {
+        return Err(corrupt("Empty path".into()));
+    }
+    let path = file_util::path_from_bytes(path_bytes).map_err(|err| corrupt(err.into()))?;
+    Ok(path.to_owned())
+}
+
+/// Resolves the workspace root a link file recorded by
+/// [`SimpleWorkspaceStore::set_link_path()`] points to.
+fn resolve_link(link: &Path) -> Result<PathBuf, WorkspaceStoreError> {
+    let mut path = link.to_owned();
+    for _ in 0..MAX_LINK_DEPTH {
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && path == link => {
+                return Ok(path);
+            }
+            Err(err) => return Err(PathError { path, source: err }.into()),
+        };
+        let target = file_util::path_from_bytes(content.trim_ascii_end())
+            .map_err(io::Error::other)
+            .context(&path)?;
+        // Relative to the directory of the link file
+        let target = path.parent().unwrap_or(Path::new("")).join(target);
+        if !target.is_file() {
+            return Ok(target);
+        }
+        path = target;
+    }
+    Err(WorkspaceStoreError::TooManyRedirects(link.to_owned()))
+}
+
+/// Returns true if `version` is newer than the running version of jj.
+/// Versions that can't be parsed aren't newer.
+fn is_newer_version(version: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        version
+            .split(['.', '-', '+'])
+            .take(3)
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+    match (parse(version), parse(JJ_VERSION)) {
+        (Some(version), Some(current)) => version > current,
+        _ => false,
+    }
+}
+
+/// Replaces all entries of `store` with `entries` in a transaction.
+fn replace_all_in_txn<S: WorkspaceStore + ?Sized>(
+    store: &S,
+    entries: &[(WorkspaceNameBuf, &Path)],
+) -> Result<(), WorkspaceStoreError> {
+    let mut txn = store.begin()?;
+    for name in store.list()? {
+        if !entries.iter().any(|(new_name, _)| *new_name == name) {
+            txn.remove_path(&name)?;
+        }
+    }
+    for (name, path) in entries {
+        txn.set_path(name, path)?;
+    }
+    txn.finish()
+}
+
+/// Creates a temporary file in `dir` and writes its content with `write`. If
+/// that fails, e.g. because the disk is full, the partially written file is
+/// removed right away, so that it can't be persisted.
+fn write_temp_file(
+    dir: &Path,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut fs::File) -> io::Result<()>,
+) -> Result<NamedTempFile, WorkspaceStoreError> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(TEMP_FILE_PREFIX);
+    // Temporary files are only readable by their owner by default, but they
+    // become the files of the store when persisted.
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o666));
+    let mut temp_file = builder.tempfile_in(dir).context(dir)?;
+    if let Err(err) =
+        set_file_mode(temp_file.as_file(), mode).and_then(|()| write(temp_file.as_file_mut()))
+    {
+        let path = temp_file.path().to_owned();
+        if let Err(close_err) = temp_file.close() {
+            tracing::warn!(?path, ?close_err, "failed to remove partially written file");
+        }
+        return Err(PathError { path, source: err }.into());
+    }
+    Ok(temp_file)
+}
+
+/// Sets the permission bits of a new file to `mode`, if set, regardless of the
+/// umask. See [`SimpleWorkspaceStoreOptions::file_mode`].
+#[cfg(unix)]
+fn set_file_mode(file: &fs::File, mode: Option<u32>) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+    match mode {
+        Some(mode) => file.set_permissions(fs::Permissions::from_mode(mode)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_file: &fs::File, _mode: Option<u32>) -> io::Result<()> {
+    Ok(())
+}
+
+fn gzip_compress(data: &[u8]) -> Vec<u8> {
+    let mut buf = vec![0; deflate::compress_bound(data.len()) + GZIP_WRAP_LEN];
+    let config = DeflateConfig {
+        window_bits: GZIP_WINDOW_BITS,
+        ..DeflateConfig::default()
+    };
+    let (compressed, code) = deflate::compress_slice(&mut buf, data, config);
+    assert_eq!(code, ReturnCode::Ok, "the buffer fits the compressed data");
+    let len = compressed.len();
+    buf.truncate(len);
+    buf
+}
+
+fn gzip_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    // The trailer ends with the size of the decompressed data.
+    let (_, &size) = data.split_last_chunk::<4>().ok_or("Truncated gzip data")?;
+    let size = usize::try_from(u32::from_le_bytes(size)).unwrap();
+    if size > MAX_DECOMPRESSED_LEN {
+        return Err("Decompressed entry is too large".to_owned());
+    }
+    let mut buf = vec![0; size];
+    let config = InflateConfig {
+        window_bits: GZIP_WINDOW_BITS,
+    };
+    let (decompressed, code) = inflate::uncompress_slice(&mut buf, data, config);
+    if code != ReturnCode::Ok || decompressed.len() != size {
+        return Err(format!("Invalid gzip data ({code:?})"));
+    }
+    Ok(buf)
+}
+
+/// Returns true if any root recorded in `store` is `path` once both are
+/// canonicalized by `canonicalizer`. Paths that can't be canonicalized, e.g.
+/// because they no longer exist, are compared as they are.
+fn path_recorded(
+    store: &(impl WorkspaceStore + ?Sized),
+    canonicalizer: &dyn PathCanonicalizer,
+    path: &Path,
+) -> Result<bool, WorkspaceStoreError> {
+    let canonicalize = |path: &Path| {
+        canonicalizer
+            .canonicalize(path)
+            .unwrap_or_else(|_| path.to_owned())
+    };
+    let path = canonicalize(path);
+    Ok(store
+        .get_all_paths()?
+        .iter()
+        .any(|(_, root)| canonicalize(root) == path))
+}
+
+/// Returns the path of the directory named like `dir` plus `suffix`.
+fn sibling_dir(dir: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = dir.file_name().unwrap().to_owned();
+    file_name.push(suffix);
+    dir.with_file_name(file_name)
+}
+
+/// Copies the store directory `from` to `to`. The copy is made in a temporary directory, which is renamed to `to` once it's
+/// complete.
+fn copy_store_dir(from: &Path, to: &Path) -> Result<(), WorkspaceStoreError> {
+    fn copy_dir(from: &Path, to: &Path) -> Result<(), PathError> {
+        for dir_entry in from.read_dir().context(from)? {
+            let dir_entry = dir_entry.context(from)?;
+            let source = dir_entry.path();
+            let target = to.join(dir_entry.file_name());
+            let metadata = dir_entry.metadata().context(&source)?;
+            if metadata.is_dir() {
+                fs::create_dir(&target).context(&target)?;
+                copy_dir(&source, &target)?;
+            } else {
+                fs::copy(&source, &target).context(&source)?;
+                // The modification times of entries and tombstones are the
+                // times they were written and removed.
+                let mtime = metadata.modified().context(&source)?;
+                fs::File::options()
+                    .write(true)
+                    .open(&target)
+                    .and_then(|file| file.set_modified(mtime))
+                    .context(&target)?;
+            }
+        }
+        Ok(())
+    }
+
+    let parent_dir = to
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let temp_dir = tempfile::Builder::new()
+        .prefix(TEMP_FILE_PREFIX)
+        .tempdir_in(parent_dir)
+        .context(parent_dir)?;
+    copy_dir(from, temp_dir.path())?;
+    fs::rename(temp_dir.path(), to).context(to)?;
+    Ok(())
+}
+
+fn is_read_only_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// What is known about a workspace.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WorkspaceEntry {
+    /// Name of the workspace.
+    pub name: WorkspaceNameBuf,
+    /// Working-copy commit of the workspace, if the view has one.
+    pub wc_commit_id: Option<CommitId>,
+    /// Root of the workspace, if known.
+    pub path: Option<PathBuf>,
+}
+
+/// Roots longer than this many characters are shortened when a
+/// [`WorkspaceEntry`] is displayed.
+const MAX_DISPLAY_PATH_LEN: usize = 60;
+
+/// Formats the entry as `name (path)`, or just `name` if the root is unknown.
+/// Long roots are shortened to their last components.
+impl fmt::Display for WorkspaceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name.as_symbol())?;
+        if let Some(path) = &self.path {
+            write!(f, " ({})", shorten_path(path, MAX_DISPLAY_PATH_LEN))?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces leading components of `path` with `...` until it's at most
+/// `max_len` characters long. The last component is always kept.
+fn shorten_path(path: &Path, max_len: usize) -> String {
+    let full = path.display().to_string();
+    if full.chars().count() <= max_len {
+        return full;
+    }
+    let ellipsis = "...";
+    let separator = std::path::MAIN_SEPARATOR_STR;
+    let mut len = ellipsis.len();
+    let mut tail = vec![];
+    for component in path.iter().rev() {
+        let component = component.to_string_lossy();
+        let component_len = separator.len() + component.chars().count();
+        if !tail.is_empty() && len + component_len > max_len {
+            break;
+        }
+        len += component_len;
+        tail.push(component);
+    }
+    tail.reverse();
+    format!("{ellipsis}{separator}{}", tail.join(separator))
+}
+
+/// Whether the root of a workspace exists on disk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PathStatus {
+    /// The root is known and is a directory.
+    Exists,
+    /// The root is known but doesn't exist, e.g. because it was deleted.
+    Missing,
+    /// The root isn't known.
+    Unknown,
+}
+
+/// Combines the working-copy commits of the view with the roots recorded in
+/// the workspace store.
+#[derive(Clone, Copy, Debug)]
+pub struct Workspaces<'a> {
+    store: &'a dyn WorkspaceStore,
+    view: &'a View,
+    current: Option<(&'a WorkspaceName, &'a Path)>,
+}
+
+impl<'a> Workspaces<'a> {
+    /// Creates a facade over `store` and `view`.
+    pub fn new(store: &'a dyn WorkspaceStore, view: &'a View) -> Self {
+        Self {
+            store,
+            view,
+            current: None,
+        }
+    }
+
+    /// Sets the currently loaded workspace, whose root is known even if it
+    /// was created before the workspace store existed.
+    pub fn with_current(mut self, name: &'a WorkspaceName, root: &'a Path) -> Self {
+        self.current = Some((name, root));
+        self
+    }
+
+    /// Returns the names of all workspaces known to the view or the store,
+    /// sorted.
+    pub fn all(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let names = self
+            .view
+            .wc_commit_ids()
+            .keys()
+            .cloned()
+            .chain(self.store.list()?)
+            .chain(self.current.map(|(name, _)| name.to_owned()))
+            .sorted()
+            .dedup()
+            .collect();
+        Ok(names)
+    }
+
+    /// Looks up the workspace. Returns `None` if neither the view nor the
+    /// store knows it.
+    pub fn get(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<WorkspaceEntry>, WorkspaceStoreError> {
+        let wc_commit_id = self.view.get_wc_commit_id(workspace_name).cloned();
+        let path = match self.resolve_root(workspace_name) {
+            Ok(path) => Some(path),
+            Err(WorkspaceStoreError::NotFound(_)) => None,
+            Err(err) => return Err(err),
+        };
+        if wc_commit_id.is_none() && path.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(WorkspaceEntry {
+            name: workspace_name.to_owned(),
+            wc_commit_id,
+            path,
+        }))
+    }
+
+    /// Looks up the given workspaces, in the same order. Workspaces unknown to
+    /// both the view and the store map to `None`.
+    pub fn get_many(
+        &self,
+        workspace_names: &[WorkspaceNameBuf],
+    ) -> Result<Vec<Option<WorkspaceEntry>>, WorkspaceStoreError> {
+        let entries = self
+            .store
+            .get_many(workspace_names)?
+            .into_iter()
+            .map(|(name, path)| {
+                let wc_commit_id = self.view.get_wc_commit_id(&name).cloned();
+                let path = path.or_else(|| {
+                    let (current_name, root) = self.current?;
+                    (current_name == name).then(|| root.to_owned())
+                });
+                (wc_commit_id.is_some() || path.is_some()).then_some(WorkspaceEntry {
+                    name,
+                    wc_commit_id,
+                    path,
+                })
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// Returns the root of the workspace, falling back to the root of the
+    /// current workspace if the store has no entry.
+    pub fn resolve_root(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<PathBuf, WorkspaceStoreError> {
+        match (self.store.get_path(workspace_name), self.current) {
+            (Err(WorkspaceStoreError::NotFound(_)), Some((name, root)))
+                if name == workspace_name =>
+            {
+                Ok(root.to_owned())
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Returns whether the root of the workspace exists on disk.
+    pub fn path_status(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<PathStatus, WorkspaceStoreError> {
+        match self.resolve_root(workspace_name) {
+            Ok(path) if path.is_dir() => Ok(PathStatus::Exists),
+            Ok(_) => Ok(PathStatus::Missing),
+            Err(WorkspaceStoreError::NotFound(_)) => Ok(PathStatus::Unknown),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the sorted names of workspaces whose recorded root no longer
+    /// exists.
+    pub fn missing(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let mut names = vec![];
+        for name in self.all()? {
+            if self.path_status(&name)? == PathStatus::Missing {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Returns the sorted names of workspaces that have a recorded root but no
+    /// working-copy commit, e.g. because the operation that added them was
+    /// undone.
+    pub fn orphans(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let names = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|name| self.view.get_wc_commit_id(name).is_none())
+            .collect();
+        Ok(names)
+    }
+
+    /// Returns the overview of the workspace, or `None` if neither the view
+    /// nor the store knows it. `store` is used to look up the working-copy
+    /// commit.
+    pub fn overview(
+        &self,
+        workspace_name: &WorkspaceName,
+        store: &Arc<Store>,
+    ) -> Result<Option<WorkspaceOverview>, WorkspaceOverviewError> {
+        let Some(entry) = self.get(workspace_name)? else {
+            return Ok(None);
+        };
+        let change_id = entry
+            .wc_commit_id
+            .as_ref()
+            .map(|id| store.get_commit(id))
+            .transpose()?
+            .map(|commit| commit.change_id().clone());
+        Ok(Some(WorkspaceOverview {
+            exists: entry.path.as_ref().is_some_and(|path| path.is_dir()),
+            name: entry.name,
+            path: entry.path,
+            wc_commit_id: entry.wc_commit_id,
+            change_id,
+        }))
+    }
+
+    /// Returns the overviews of all workspaces known to the view or the store,
+    /// sorted by name.
+    pub fn overview_all(
+        &self,
+        store: &Arc<Store>,
+    ) -> Result<Vec<WorkspaceOverview>, WorkspaceOverviewError> {
+        let mut overviews = vec![];
+        for name in self.all()? {
+            // Removed from the store since it was listed
+            if let Some(overview) = self.overview(&name, store)? {
+                overviews.push(overview);
+            }
+        }
+        Ok(overviews)
+    }
+}
+
+/// Everything about a workspace that's cheap to look up, computed at once so
+/// that callers such as editor integrations don't need to combine the view and
+/// the store themselves. See [`workspace_overview()`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WorkspaceOverview {
+    /// Name of the workspace.
+    pub name: WorkspaceNameBuf,
+    /// Root of the workspace, if known. It's serialized as a string, with
+    /// invalid UTF-8 replaced.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_path_lossy"))]
+    pub path: Option<PathBuf>,
+    /// Whether the root is known and is a directory.
+    pub exists: bool,
+    /// Working-copy commit of the workspace, if the view has one. Orphans,
+    /// whose root is recorded but which have no working-copy commit, have
+    /// none.
+    pub wc_commit_id: Option<CommitId>,
+    /// Change id of the working-copy commit, if there is one.
+    pub change_id: Option<ChangeId>,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_path_lossy<S: serde::Serializer>(
+    path: &Option<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serde::Serialize::serialize(
+        &path.as_ref().map(|path| path.to_string_lossy()),
+        serializer,
+    )
+}
+
+/// Error from [`workspace_overview()`].
+#[derive(Debug, Error)]
+pub enum WorkspaceOverviewError {
+    /// The workspace store couldn't be read.
+    #[error(transparent)]
+    Store(#[from] WorkspaceStoreError),
+    /// A working-copy commit couldn't be read.
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+}
+
+/// Returns the overviews of all workspaces of `repo`, sorted by name. See
+/// [`Workspaces::overview_all()`] to include the root of the current
+/// workspace when it isn't recorded.
+pub fn workspace_overview(
+    store: &dyn WorkspaceStore,
+    repo: &dyn Repo,
+) -> Result<Vec<WorkspaceOverview>, WorkspaceOverviewError> {
+    Workspaces::new(store, repo.view()).overview_all(repo.store())
+}
+
+/// Which upkeep [`maintain()`] performs.
+#[derive(Clone, Debug, Default)]
+pub struct MaintenanceOptions {
+    /// Move unreadable entries out of the way. See
+    /// [`WorkspaceStore::repair()`].
+    pub repair: bool,
+    /// Remove the entries of workspaces that have no working-copy commit and
+    /// whose root no longer exists. Imported entries are kept, see
+    /// [`WorkspaceStore::set_imported()`].
+    pub prune: bool,
+    /// Remove temporary files left behind by interrupted writes.
+    pub remove_temp_files: bool,
+    /// Record the root of this workspace if it has no entry, e.g. because it
+    /// was created before the store existed.
+    pub backfill: Option<(WorkspaceNameBuf, PathBuf)>,
+    /// Finish renames interrupted by a crash. See
+    /// [`WorkspaceStore::finish_renames()`].
+    pub finish_renames: bool,
+    /// Delete the tombstones of entries removed before this time. See
+    /// [`WorkspaceStore::purge_tombstones()`].
+    pub purge_tombstones: Option<SystemTime>,
+}
+
+/// What [`maintain()`] did.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MaintenanceReport {
+    /// Workspaces whose unreadable entries were moved out of the way.
+    pub repaired: Vec<WorkspaceNameBuf>,
+    /// Workspaces whose entries were removed.
+    pub pruned: Vec<WorkspaceNameBuf>,
+    /// Number of temporary files removed.
+    pub removed_temp_files: usize,
+    /// Workspaces whose roots were recorded.
+    pub backfilled: Vec<WorkspaceNameBuf>,
+    /// New names of workspaces whose interrupted renames were finished.
+    pub finished_renames: Vec<WorkspaceNameBuf>,
+    /// Number of tombstones deleted.
+    pub purged_tombstones: usize,
+}
+
+impl MaintenanceReport {
+    /// Returns true if nothing was done.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Restores the tombstoned entries of the workspaces that `new_view` has but
+/// `old_view` doesn't, e.g. because an operation forgetting them was undone.
+/// Returns the names of the restored workspaces. Workspaces whose entry was
+/// deleted, or recorded again in the meantime, are left alone. See
+/// [`SimpleWorkspaceStore::untombstone()`].
+pub fn restore_entries(
+    store: &dyn WorkspaceStore,
+    old_view: &View,
+    new_view: &View,
+) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+    let mut restored = vec![];
+    for name in new_view.wc_commit_ids().keys() {
+        if old_view.get_wc_commit_id(name).is_none() && store.untombstone(name)? {
+            restored.push(name.clone());
+        }
+    }
+    Ok(restored)
+}
+
+/// Performs the upkeep of the workspace store selected by `options`.
+pub fn maintain(
+    store: &dyn WorkspaceStore,
+    view: &View,
+    options: &MaintenanceOptions,
+) -> Result<MaintenanceReport, WorkspaceStoreError> {
+    let mut report = MaintenanceReport::default();
+    // Repair first so that unreadable entries don't get in the way of the
+    // other actions.
+    if options.repair {
+        report.repaired = store.repair()?;
+    }
+    if options.finish_renames {
+        report.finished_renames = store.finish_renames()?;
+    }
+    if options.prune {
+        let workspaces = Workspaces::new(store, view);
+        let mut txn = store.begin()?;
+        for name in workspaces.missing()? {
+            if view.get_wc_commit_id(&name).is_none() && !store.is_imported(&name)? {
+                txn.remove_path(&name)?;
+                report.pruned.push(name);
+            }
+        }
+        txn.finish()?;
+    }
+    if options.remove_temp_files {
+        report.removed_temp_files = store.remove_temp_files()?;
+    }
+    if let Some(keep_newer) = options.purge_tombstones {
+        report.purged_tombstones = store.purge_tombstones(keep_newer)?;
+    }
+    if let Some((name, path)) = &options.backfill {
+        match store.create_path(name, path) {
+            Ok(()) => report.backfilled.push(name.clone()),
+            Err(WorkspaceStoreError::AlreadyExists(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    if !report.is_empty() {
+        store.flush()?;
+    }
+    Ok(report)
+}
+
+/// Problems found by [`WorkspaceStore::diagnose()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Diagnosis {
+    /// Workspaces whose entries can't be read. See
+    /// [`WorkspaceStore::repair()`].
+    pub corrupt: Vec<WorkspaceNameBuf>,
+    /// New names of workspaces whose renames were interrupted. See
+    /// [`WorkspaceStore::finish_renames()`].
+    pub interrupted_renames: Vec<WorkspaceNameBuf>,
+    /// Workspaces whose recorded root no longer exists.
+    pub missing: Vec<WorkspaceNameBuf>,
+    /// Workspaces whose recorded root exists but isn't a workspace of the
+    /// repo, e.g. because it was replaced by another checkout. Imported roots
+    /// aren't included, since they may be directories like Git worktrees.
+    pub foreign: Vec<WorkspaceNameBuf>,
+    /// Workspaces that have a working-copy commit but no recorded root.
+    pub unrecorded: Vec<WorkspaceNameBuf>,
+    /// Workspaces that have a recorded root but no working-copy commit,
+    /// except for imported roots. See [`WorkspaceStore::set_imported()`].
+    pub orphans: Vec<WorkspaceNameBuf>,
+    /// Roots that are recorded for more than one workspace, with the names of
+    /// those workspaces.
+    pub duplicate_paths: Vec<(PathBuf, Vec<WorkspaceNameBuf>)>,
+    /// Names that differ only in case. With [`NameEncoding::Plain`], their
+    /// entries collide on case-insensitive file systems.
+    pub case_collisions: Vec<Vec<WorkspaceNameBuf>>,
+}
+
+impl Diagnosis {
+    /// Returns the number of problems. A workspace may have more than one.
+    pub fn len(&self) -> usize {
+        self.corrupt.len()
+            + self.interrupted_renames.len()
+            + self.missing.len()
+            + self.foreign.len()
+            + self.unrecorded.len()
+            + self.orphans.len()
+            + self.duplicate_paths.len()
+            + self.case_collisions.len()
+    }
+
+    /// Returns true if no problems were found.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Recorded roots of all workspaces at some point in time. See
+/// [`WorkspaceStore::snapshot()`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WorkspaceStoreSnapshot {
+    /// Recorded root of each workspace.
+    pub paths: BTreeMap<WorkspaceNameBuf, PathBuf>,
+}
+
+/// Changes between two snapshots of the workspace store, as returned by
+/// [`diff()`]. Each list is sorted by name.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WorkspaceStoreDiff {
+    /// Workspaces recorded only in the new snapshot, with their roots.
+    pub added: Vec<(WorkspaceNameBuf, PathBuf)>,
+    /// Workspaces recorded only in the old snapshot, with their roots.
+    pub removed: Vec<(WorkspaceNameBuf, PathBuf)>,
+    /// Workspaces whose root changed, with the old and the new root.
+    pub changed: Vec<(WorkspaceNameBuf, PathBuf, PathBuf)>,
+}
+
+impl WorkspaceStoreDiff {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Compares two snapshots of the workspace store. A workspace that was
+/// renamed shows up as removed under its old name and added under the new one.
+pub fn diff(old: &WorkspaceStoreSnapshot, new: &WorkspaceStoreSnapshot) -> WorkspaceStoreDiff {
+    let mut diff = WorkspaceStoreDiff::default();
+    for (name, old_path) in &old.paths {
+        match new.paths.get(name) {
+            None => diff.removed.push((name.clone(), old_path.clone())),
+            Some(new_path) if new_path != old_path => {
+                diff.changed
+                    .push((name.clone(), old_path.clone(), new_path.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, new_path) in &new.paths {
+        if !old.paths.contains_key(name) {
+            diff.added.push((name.clone(), new_path.clone()));
+        }
+    }
+    diff
+}
+
+/// Returns the roots recorded for more than one workspace, with the names of
+/// those workspaces.
+fn duplicate_paths(
+    names_by_root: BTreeMap<PathBuf, Vec<WorkspaceNameBuf>>,
+) -> Vec<(PathBuf, Vec<WorkspaceNameBuf>)> {
+    names_by_root
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .collect()
+}
+
+/// Returns the workspaces of the view that have no entry in `store`, leaving
+/// out those whose entries are `corrupt`.
+fn unrecorded_names<S: WorkspaceStore + ?Sized>(
+    store: &S,
+    view: &View,
+    corrupt: &[WorkspaceNameBuf],
+) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+    let mut names = vec![];
+    for name in view.wc_commit_ids().keys() {
+        if !corrupt.contains(name) && !store.exists(name)? {
+            names.push(name.clone());
+        }
+    }
+    Ok(names)
+}
+
+/// Returns true if `root` is a workspace of the repo at the canonical
+/// `repo_path`.
+fn is_workspace_of(root: &Path, repo_path: &Path) -> bool {
+    DefaultWorkspaceLoaderFactory
+        .create(root)
+        .is_ok_and(|loader| {
+            dunce::canonicalize(loader.repo_path()).is_ok_and(|path| path == repo_path)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::tests::new_temp_dir;
+
+    #[test]
+    fn test_write_temp_file_removes_partial_file() {
+        let temp_dir = new_temp_dir();
+        let result = write_temp_file(temp_dir.path(), None, |file| {
+            file.write_all(b"partial")?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        });
+        assert_matches!(
+            result,
+            Err(WorkspaceStoreError::Path(PathError { source, .. }))
+                if source.kind() == io::ErrorKind::StorageFull
+        );
+        assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+
+        let temp_file =
+            write_temp_file(temp_dir.path(), None, |file| file.write_all(b"complete")).unwrap();
+        assert_eq!(fs::read(temp_file.path()).unwrap(), b"complete");
+    }
+}
@@ -40,3 +40,5 @@ mod test_signing;
 mod test_ssh_signing;
 mod test_view;
 mod test_workspace;
+mod test_workspace_store;
+mod test_workspace_store_concurrent;
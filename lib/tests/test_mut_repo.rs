@@ -670,6 +670,34 @@ fn test_remove_wc_commit_previous_discardable() {
     assert!(!mut_repo.view().heads().contains(old_wc_commit.id()));
 }
 
+#[test]
+fn test_remove_wc_commit_without_abandoning_discardable() {
+    // Test that MutableRepo::remove_wc_commit_without_abandoning() keeps the
+    // previous commit even if it was discardable.
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let mut tx = repo.start_transaction();
+    let mut_repo = tx.repo_mut();
+    let old_wc_commit = mut_repo
+        .new_commit(
+            vec![repo.store().root_commit_id().clone()],
+            repo.store().empty_merged_tree(),
+        )
+        .write()
+        .unwrap();
+    let ws_name = WorkspaceName::DEFAULT.to_owned();
+    mut_repo.edit(ws_name.clone(), &old_wc_commit).unwrap();
+    let repo = tx.commit("test").unwrap();
+
+    let mut tx = repo.start_transaction();
+    let mut_repo = tx.repo_mut();
+    mut_repo.remove_wc_commit_without_abandoning(&ws_name);
+    mut_repo.rebase_descendants().unwrap();
+    assert!(mut_repo.view().heads().contains(old_wc_commit.id()));
+    assert_eq!(mut_repo.view().get_wc_commit_id(&ws_name), None);
+}
+
 #[test]
 fn test_reparent_descendants() {
     // Test that MutableRepo::reparent_descendants() reparents descendants of
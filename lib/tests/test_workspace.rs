@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
 use std::thread;
 
 use assert_matches::assert_matches;
@@ -21,6 +22,9 @@ use jj_lib::workspace::Workspace;
 use jj_lib::workspace::WorkspaceLoadError;
 use jj_lib::workspace::default_working_copy_factories;
 use jj_lib::workspace::default_working_copy_factory;
+use jj_lib::workspace_store::NormalizingPathCanonicalizer;
+use jj_lib::workspace_store::SimpleWorkspaceStoreOptions;
+use jj_lib::workspace_store::load_workspace_store;
 use testutils::TestEnvironment;
 use testutils::TestWorkspace;
 
@@ -92,6 +96,37 @@ fn test_init_additional_workspace() {
     assert_eq!(same_workspace.workspace_root(), ws2.workspace_root());
 }
 
+#[cfg(unix)]
+#[test]
+fn test_init_additional_workspace_store_options() {
+    let settings = testutils::user_settings();
+    let test_workspace = TestWorkspace::init_with_settings(&settings);
+    let real_dir = test_workspace.env.root().join("real");
+    std::fs::create_dir(&real_dir).unwrap();
+    let link_dir = test_workspace.env.root().join("link");
+    std::os::unix::fs::symlink(&real_dir, &link_dir).unwrap();
+
+    // The root is recorded as given if the store doesn't resolve symlinks
+    let options = SimpleWorkspaceStoreOptions {
+        canonicalizer: Arc::new(NormalizingPathCanonicalizer),
+        ..Default::default()
+    };
+    let ws2_name = WorkspaceNameBuf::from("ws2");
+    let ws2_root = link_dir.join("ws2_root");
+    std::fs::create_dir(&ws2_root).unwrap();
+    Workspace::init_workspace_with_existing_repo_with_workspace_store_options(
+        &ws2_root,
+        test_workspace.repo_path(),
+        &test_workspace.repo,
+        &*default_working_copy_factory(),
+        ws2_name.clone(),
+        &options,
+    )
+    .unwrap();
+    let workspace_store = load_workspace_store(test_workspace.repo_path(), options).unwrap();
+    assert_eq!(workspace_store.get_path(&ws2_name).unwrap(), ws2_root);
+}
+
 #[cfg(unix)]
 #[cfg_attr(target_os = "macos", ignore = "APFS/HFS+ don't like non-UTF-8 paths")]
 #[test]
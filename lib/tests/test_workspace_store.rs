@@ -0,0 +1,2970 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use assert_matches::assert_matches;
+use itertools::Itertools as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::ref_name::WorkspaceName;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::repo::Repo as _;
+use jj_lib::workspace::Workspace;
+use jj_lib::workspace::default_working_copy_factory;
+use jj_lib::workspace_store::Diagnosis;
+use jj_lib::workspace_store::MaintenanceOptions;
+use jj_lib::workspace_store::MaintenanceReport;
+use jj_lib::workspace_store::NameEncoding;
+use jj_lib::workspace_store::NormalizingPathCanonicalizer;
+use jj_lib::workspace_store::PathCanonicalizer;
+use jj_lib::workspace_store::PathStatus;
+use jj_lib::workspace_store::SimpleWorkspaceStore;
+use jj_lib::workspace_store::SimpleWorkspaceStoreOptions;
+use jj_lib::workspace_store::WORKSPACE_LINK_FILE_NAME;
+use jj_lib::workspace_store::WorkspaceEntry;
+use jj_lib::workspace_store::WorkspaceOverview;
+use jj_lib::workspace_store::WorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStoreDiff;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use jj_lib::workspace_store::Workspaces;
+use jj_lib::workspace_store::diff;
+use jj_lib::workspace_store::load_workspace_store;
+use jj_lib::workspace_store::maintain;
+use jj_lib::workspace_store::read_workspace_index;
+use jj_lib::workspace_store::restore_entries;
+use jj_lib::workspace_store::workspace_overview;
+use proptest::prelude::*;
+use testutils::TestWorkspace;
+
+fn sorted_names(store: &SimpleWorkspaceStore) -> Vec<WorkspaceNameBuf> {
+    store.list().unwrap().into_iter().sorted().collect()
+}
+
+#[test]
+fn test_init_records_workspace_paths() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    assert_eq!(
+        sorted_names(&store),
+        ["default"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+        test_workspace.workspace.workspace_root()
+    );
+
+    let ws2_root = test_workspace.root_dir().join("ws2_root");
+    std::fs::create_dir(&ws2_root).unwrap();
+    let (ws2, _repo) = Workspace::init_workspace_with_existing_repo(
+        &ws2_root,
+        test_workspace.repo_path(),
+        &test_workspace.repo,
+        &*default_working_copy_factory(),
+        "ws2".into(),
+    )
+    .unwrap();
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "ws2"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        store.get_path(WorkspaceName::new("ws2")).unwrap(),
+        ws2.workspace_root()
+    );
+}
+
+#[test]
+fn test_set_get_remove_path() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let name = WorkspaceName::new("foo");
+    let foo_root = test_workspace.root_dir().join("foo");
+    std::fs::create_dir(&foo_root).unwrap();
+
+    assert!(!store.exists(name).unwrap());
+    assert_matches!(
+        store.get_path(name),
+        Err(WorkspaceStoreError::NotFound(n)) if n == name
+    );
+
+    // The path is canonicalized
+    store.set_path(name, &foo_root.join(".")).unwrap();
+    assert!(store.exists(name).unwrap());
+    assert_eq!(
+        store.get_path(name).unwrap(),
+        dunce::canonicalize(&foo_root).unwrap()
+    );
+
+    // The entry can be overwritten
+    store.set_path(name, &test_workspace.root_dir()).unwrap();
+    assert_eq!(
+        store.get_path(name).unwrap(),
+        dunce::canonicalize(test_workspace.root_dir()).unwrap()
+    );
+
+    let mtime = store.entry_mtime(name).unwrap();
+    assert!(mtime <= SystemTime::now());
+
+    assert!(store.remove_path(name).unwrap());
+    store.flush().unwrap();
+    assert_matches!(
+        store.entry_mtime(name),
+        Err(WorkspaceStoreError::NotFound(n)) if n == name
+    );
+    assert!(!store.exists(name).unwrap());
+    // Removing an absent entry is fine
+    assert!(!store.remove_path(name).unwrap());
+    assert_eq!(
+        sorted_names(&store),
+        ["default"].map(WorkspaceNameBuf::from)
+    );
+}
+
+#[derive(Debug)]
+struct IdentityCanonicalizer;
+
+impl PathCanonicalizer for IdentityCanonicalizer {
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_owned())
+    }
+}
+
+#[test]
+fn test_created_by_version() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let name = WorkspaceName::new("new");
+    store
+        .set_path(name, test_workspace.workspace.workspace_root())
+        .unwrap();
+    assert_eq!(
+        store.created_by_version(name).unwrap().as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+
+    // Older versions didn't record it
+    std::fs::write(
+        repo_path.join("workspace_store").join("old"),
+        b"\x0a\x03old\x12\x04/old",
+    )
+    .unwrap();
+    assert_eq!(
+        store.created_by_version(WorkspaceName::new("old")).unwrap(),
+        None
+    );
+    assert_matches!(
+        store.created_by_version(WorkspaceName::new("missing")),
+        Err(WorkspaceStoreError::NotFound(_))
+    );
+}
+
+#[test]
+fn test_created_by_uid() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let name = WorkspaceName::new("new");
+    store
+        .set_path(name, test_workspace.workspace.workspace_root())
+        .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt as _;
+        let uid = repo_path.metadata().unwrap().uid();
+        assert_eq!(store.created_by_uid(name).unwrap(), Some(uid));
+    }
+    #[cfg(not(unix))]
+    assert_eq!(store.created_by_uid(name).unwrap(), None);
+
+    // Older versions didn't record it
+    std::fs::write(
+        repo_path.join("workspace_store").join("old"),
+        b"\x0a\x03old\x12\x04/old",
+    )
+    .unwrap();
+    assert_eq!(
+        store.created_by_uid(WorkspaceName::new("old")).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_json_store() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    let default = WorkspaceName::new("default");
+
+    // Entries of the simple store can't be mixed with JSON entries
+    assert_matches!(
+        SimpleWorkspaceStore::init_json(repo_path, Default::default()),
+        Err(WorkspaceStoreError::WrongType { expected: "json", actual, .. })
+            if actual == SimpleWorkspaceStore::name()
+    );
+    assert_matches!(
+        SimpleWorkspaceStore::load_json(repo_path, Default::default()),
+        Err(WorkspaceStoreError::WrongType { .. })
+    );
+    let simple_store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    simple_store.remove_path(default).unwrap();
+
+    let store = SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap();
+    store.set_path(default, root).unwrap();
+    let nested = WorkspaceName::new("team/feature");
+    store.set_path(nested, root).unwrap();
+    assert_eq!(
+        store.list().unwrap().into_iter().sorted().collect_vec(),
+        vec![default.to_owned(), nested.to_owned()]
+    );
+    assert_eq!(store.get_path(default).unwrap(), root);
+    assert_eq!(
+        store.created_by_version(default).unwrap().as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+    #[cfg(unix)]
+    assert!(store.created_by_uid(default).unwrap().is_some());
+
+    // The entries are readable JSON
+    let store_dir = repo_path.join("workspace_store");
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(store_dir.join("default.json")).unwrap()).unwrap();
+    assert_eq!(json["name"], "default");
+    assert_eq!(json["path"], root.to_str().unwrap());
+    assert!(store_dir.join("team").join("feature.json").is_file());
+
+    // Edits by hand are picked up
+    let edited = test_workspace.env.root().join("edited");
+    let mut json = json;
+    json["path"] = edited.to_str().unwrap().into();
+    std::fs::write(store_dir.join("default.json"), json.to_string()).unwrap();
+    assert_eq!(store.get_path(default).unwrap(), edited);
+
+    // The simple store refuses to load the JSON store, but the factory loads it
+    assert_matches!(
+        SimpleWorkspaceStore::load(repo_path),
+        Err(WorkspaceStoreError::WrongType { expected, actual, .. })
+            if expected == SimpleWorkspaceStore::name() && actual == "json"
+    );
+    let store = load_workspace_store(repo_path, Default::default()).unwrap();
+    assert_eq!(store.name(), "json");
+    assert_eq!(store.get_path(default).unwrap(), edited);
+    assert_eq!(
+        SimpleWorkspaceStore::load_json(repo_path, Default::default())
+            .unwrap()
+            .len()
+            .unwrap(),
+        2
+    );
+}
+
+#[test]
+fn test_json_store_maintenance() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    let view = test_workspace.repo.view();
+    let default = WorkspaceName::new("default");
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(default)
+        .unwrap();
+    SimpleWorkspaceStore::init_json(repo_path, Default::default())
+        .unwrap()
+        .set_path(default, root)
+        .unwrap();
+    let store_dir = repo_path.join("workspace_store");
+    std::fs::write(store_dir.join("bad.json"), "").unwrap();
+
+    // The maintenance of the simple store also applies to JSON entries
+    let store = load_workspace_store(repo_path, Default::default()).unwrap();
+    let bad = WorkspaceNameBuf::from("bad");
+    assert_eq!(store.diagnose(view).unwrap().corrupt, vec![bad.clone()]);
+    let options = MaintenanceOptions {
+        repair: true,
+        ..Default::default()
+    };
+    let report = maintain(store.as_ref(), view, &options).unwrap();
+    assert_eq!(report.repaired, vec![bad]);
+    assert!(store.diagnose(view).unwrap().is_empty());
+}
+
+#[test]
+fn test_load_workspace_store() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = load_workspace_store(repo_path, Default::default()).unwrap();
+    assert_eq!(store.name(), SimpleWorkspaceStore::name());
+
+    std::fs::write(repo_path.join("workspace_store").join(".type"), "unknown").unwrap();
+    assert_matches!(
+        load_workspace_store(repo_path, Default::default()),
+        Err(WorkspaceStoreError::UnknownType { type_name, .. }) if type_name == "unknown"
+    );
+}
+
+#[test]
+fn test_load_not_initialized() {
+    let temp_dir = testutils::new_temp_dir();
+    let repo_path = temp_dir.path().join("repo");
+    std::fs::create_dir(&repo_path).unwrap();
+    let store_dir = repo_path.join("workspace_store");
+
+    // Loading doesn't create the store
+    assert_matches!(
+        SimpleWorkspaceStore::load(&repo_path),
+        Err(WorkspaceStoreError::NotInitialized(path)) if path == store_dir
+    );
+    assert_matches!(
+        load_workspace_store(&repo_path, Default::default()),
+        Err(WorkspaceStoreError::NotInitialized(_))
+    );
+    assert_matches!(
+        SimpleWorkspaceStore::load_json(&repo_path, Default::default()),
+        Err(WorkspaceStoreError::NotInitialized(_))
+    );
+    assert!(!store_dir.exists());
+
+    SimpleWorkspaceStore::init(&repo_path).unwrap();
+    assert!(store_dir.is_dir());
+    let store = SimpleWorkspaceStore::load(&repo_path).unwrap();
+    assert!(store.list().unwrap().is_empty());
+    // Initializing again reuses the store
+    SimpleWorkspaceStore::init(&repo_path).unwrap();
+}
+
+#[test]
+fn test_custom_canonicalizer() {
+    let test_workspace = TestWorkspace::init();
+    let options = SimpleWorkspaceStoreOptions {
+        canonicalizer: Arc::new(IdentityCanonicalizer),
+        ..Default::default()
+    };
+    let store =
+        SimpleWorkspaceStore::load_with_options(test_workspace.repo_path(), options).unwrap();
+    let name = WorkspaceName::new("foo");
+
+    // The path is recorded as is, even if it doesn't exist
+    let path = test_workspace
+        .root_dir()
+        .join("missing")
+        .join("..")
+        .join("foo");
+    store.set_path(name, &path).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), path);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_normalizing_canonicalizer() {
+    let test_workspace = TestWorkspace::init();
+    let options = SimpleWorkspaceStoreOptions {
+        canonicalizer: Arc::new(NormalizingPathCanonicalizer),
+        ..Default::default()
+    };
+    let store =
+        SimpleWorkspaceStore::load_with_options(test_workspace.repo_path(), options).unwrap();
+    let name = WorkspaceName::new("foo");
+    let target = test_workspace.env.root().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let link = test_workspace.env.root().join("link");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    // Symlinks are kept, but `.` and `..` components are removed
+    store
+        .set_path(name, &link.join(".").join("missing").join(".."))
+        .unwrap();
+    assert_eq!(store.get_path(name).unwrap(), link);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_duplicate_path() {
+    let test_workspace = TestWorkspace::init();
+    let target = test_workspace.env.root().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let link1 = test_workspace.env.root().join("link1");
+    let link2 = test_workspace.env.root().join("link2");
+    std::os::unix::fs::symlink(&target, &link1).unwrap();
+    std::os::unix::fs::symlink(&target, &link2).unwrap();
+    let foo = WorkspaceName::new("foo");
+    let bar = WorkspaceName::new("bar");
+
+    // By default, duplicates aren't checked
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    store.set_path(foo, &link1).unwrap();
+    store.set_path(bar, &link2).unwrap();
+    assert_eq!(store.get_path(bar).unwrap(), store.get_path(foo).unwrap());
+    store.remove_path(bar).unwrap();
+
+    let options = SimpleWorkspaceStoreOptions {
+        reject_duplicate_paths: true,
+        ..Default::default()
+    };
+    let store =
+        SimpleWorkspaceStore::load_with_options(test_workspace.repo_path(), options).unwrap();
+    let target = dunce::canonicalize(&target).unwrap();
+    assert_matches!(
+        store.create_path(bar, &link2),
+        Err(WorkspaceStoreError::DuplicatePath { name, existing_name, path })
+            if name == *bar && existing_name == *foo && path == target
+    );
+    assert_matches!(
+        store.begin().unwrap().set_path(bar, &link2),
+        Err(WorkspaceStoreError::DuplicatePath { .. })
+    );
+    assert!(!store.exists(bar).unwrap());
+    // Recording the same root for the same workspace again is fine
+    store.set_path(foo, &link2).unwrap();
+
+    // Earlier changes in the same transaction are taken into account
+    let baz = WorkspaceName::new("baz");
+    let mut txn = store.begin().unwrap();
+    txn.set_path(baz, test_workspace.env.root()).unwrap();
+    assert_matches!(
+        txn.set_path(bar, test_workspace.env.root()),
+        Err(WorkspaceStoreError::DuplicatePath { existing_name, .. }) if existing_name == *baz
+    );
+    txn.remove_path(foo).unwrap();
+    txn.set_path(bar, &link2).unwrap();
+    txn.finish().unwrap();
+    assert_eq!(sorted_names(&store), [bar, baz, WorkspaceName::DEFAULT]);
+
+    // An entry that can't be read doesn't prevent recording other roots
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    std::os::unix::fs::symlink(store_dir.join("loop"), store_dir.join("loop")).unwrap();
+    let other = test_workspace.env.root().join("other");
+    std::fs::create_dir(&other).unwrap();
+    store.set_path(foo, &other).unwrap();
+}
+
+#[test]
+fn test_encoded_size() {
+    fn check(store: &dyn WorkspaceStore, file_name: &str, root: &Path) {
+        let foo = WorkspaceName::new("foo");
+        let size = store.encoded_size(foo, root).unwrap();
+        assert!(!store.exists(foo).unwrap());
+        store.set_path(foo, root).unwrap();
+        let file = store.store_dir().unwrap().join(file_name);
+        assert_eq!(std::fs::metadata(file).unwrap().len(), size as u64);
+
+        // Paths that can't be recorded are rejected up front
+        assert_matches!(
+            store.encoded_size(foo, &root.join(".jj")),
+            Err(WorkspaceStoreError::InvalidPath { .. })
+        );
+    }
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    check(&SimpleWorkspaceStore::load(repo_path).unwrap(), "foo", root);
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    let options = SimpleWorkspaceStoreOptions {
+        checksum: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    check(&store, "foo", root);
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(WorkspaceName::DEFAULT)
+        .unwrap();
+    let store = SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap();
+    check(&store, "foo.json", root);
+}
+
+#[test]
+fn test_entry_file() {
+    fn check(store: &dyn WorkspaceStore, file_name: &str, root: &Path) {
+        let foo = WorkspaceName::new("foo");
+        let file = store.entry_file(foo).unwrap().unwrap();
+        assert_eq!(file, store.store_dir().unwrap().join(file_name));
+        assert!(!file.exists());
+        store.set_path(foo, root).unwrap();
+        assert!(file.is_file());
+        assert_matches!(
+            store.entry_file(WorkspaceName::new("")),
+            Err(WorkspaceStoreError::InvalidName(_))
+        );
+    }
+
+    let test_workspace = TestWorkspace::init();
+    let root = test_workspace.workspace.workspace_root();
+    check(
+        &SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap(),
+        "foo",
+        root,
+    );
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(WorkspaceName::DEFAULT)
+        .unwrap();
+    check(
+        &SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap(),
+        "foo.json",
+        root,
+    );
+}
+
+#[test]
+fn test_created_at_op_id() {
+    fn check(store: &dyn WorkspaceStore, root: &Path) {
+        let foo = WorkspaceName::new("foo");
+        let bar = WorkspaceName::new("bar");
+        let op_id = OperationId::from_hex("0123456789abcdef");
+        assert_matches!(
+            store.set_created_at_op_id(foo, &op_id),
+            Err(WorkspaceStoreError::NotFound(_))
+        );
+
+        store.set_path(foo, root).unwrap();
+        assert_eq!(store.created_at_op_id(foo).unwrap(), None);
+        let mtime = store.entry_mtime(foo).unwrap();
+        store.set_created_at_op_id(foo, &op_id).unwrap();
+        assert_eq!(store.created_at_op_id(foo).unwrap(), Some(op_id.clone()));
+        assert_eq!(store.entry_mtime(foo).unwrap(), mtime);
+
+        // Kept when renamed, but not when the root is recorded again
+        store.rename(foo, bar).unwrap();
+        assert_eq!(store.created_at_op_id(bar).unwrap(), Some(op_id));
+        store.set_path(bar, root).unwrap();
+        assert_eq!(store.created_at_op_id(bar).unwrap(), None);
+    }
+
+    let test_workspace = TestWorkspace::init();
+    let root = test_workspace.workspace.workspace_root();
+    check(
+        &SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap(),
+        root,
+    );
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(WorkspaceName::DEFAULT)
+        .unwrap();
+    check(
+        &SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap(),
+        root,
+    );
+}
+
+#[test]
+fn test_imported() {
+    fn check(store: &dyn WorkspaceStore, root: &Path) {
+        let foo = WorkspaceName::new("foo");
+        let bar = WorkspaceName::new("bar");
+        assert_matches!(
+            store.set_imported(foo),
+            Err(WorkspaceStoreError::NotFound(_))
+        );
+
+        store.set_path(foo, root).unwrap();
+        assert!(!store.is_imported(foo).unwrap());
+        let mtime = store.entry_mtime(foo).unwrap();
+        store.set_imported(foo).unwrap();
+        assert!(store.is_imported(foo).unwrap());
+        assert_eq!(store.entry_mtime(foo).unwrap(), mtime);
+
+        // Kept when renamed, but not when the root is recorded again
+        store.rename(foo, bar).unwrap();
+        assert!(store.is_imported(bar).unwrap());
+        store.set_path(bar, root).unwrap();
+        assert!(!store.is_imported(bar).unwrap());
+
+        // Marked along with recording the root in a transaction
+        store.remove_path(bar).unwrap();
+        let mut txn = store.begin().unwrap();
+        txn.set_imported_path(foo, root).unwrap();
+        txn.finish().unwrap();
+        assert!(store.is_imported(foo).unwrap());
+        assert_eq!(store.get_path(foo).unwrap(), root);
+    }
+
+    let test_workspace = TestWorkspace::init();
+    let root = test_workspace.workspace.workspace_root();
+    check(
+        &SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap(),
+        root,
+    );
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let root = test_workspace.workspace.workspace_root();
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(WorkspaceName::DEFAULT)
+        .unwrap();
+    check(
+        &SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap(),
+        root,
+    );
+}
+
+#[test]
+fn test_index() {
+    let test_workspace1 = TestWorkspace::init();
+    let test_workspace2 = TestWorkspace::init();
+    let index_dir = test_workspace1.env.root().join("index");
+    let options = SimpleWorkspaceStoreOptions {
+        index_dir: Some(index_dir.clone()),
+        ..Default::default()
+    };
+    let load = |test_workspace: &TestWorkspace| {
+        SimpleWorkspaceStore::load_with_options(test_workspace.repo_path(), options.clone())
+            .unwrap()
+    };
+    let store1 = load(&test_workspace1);
+    let store2 = load(&test_workspace2);
+    let foo = WorkspaceName::new("foo");
+    let bar = WorkspaceName::new("bar");
+    let foo_root = test_workspace1.env.root().join("foo");
+    let bar_root = test_workspace2.env.root().join("bar");
+    std::fs::create_dir(&foo_root).unwrap();
+    std::fs::create_dir(&bar_root).unwrap();
+    let canonical = |path: &Path| dunce::canonicalize(path).unwrap();
+    let default_root =
+        |test_workspace: &TestWorkspace| canonical(test_workspace.workspace.workspace_root());
+
+    // Nothing is indexed until the store is written
+    assert_eq!(read_workspace_index(&index_dir).unwrap(), []);
+
+    store1.set_path(foo, &foo_root).unwrap();
+    store2.set_path(bar, &bar_root).unwrap();
+    let repos = read_workspace_index(&index_dir).unwrap();
+    let repo_of = |test_workspace: &TestWorkspace| {
+        let repo_path = canonical(test_workspace.repo_path());
+        repos
+            .iter()
+            .find(|repo| repo.repo_path == repo_path)
+            .unwrap()
+            .workspaces
+            .clone()
+    };
+    assert_eq!(repos.len(), 2);
+    assert_eq!(
+        repo_of(&test_workspace1),
+        [
+            ("default".into(), default_root(&test_workspace1)),
+            ("foo".into(), canonical(&foo_root)),
+        ]
+    );
+    assert_eq!(
+        repo_of(&test_workspace2),
+        [
+            ("bar".into(), canonical(&bar_root)),
+            ("default".into(), default_root(&test_workspace2)),
+        ]
+    );
+
+    // Removals and transactions update the index
+    store1.remove_path(foo).unwrap();
+    let mut txn = store2.begin().unwrap();
+    txn.remove_path(bar).unwrap();
+    txn.remove_path(WorkspaceName::DEFAULT).unwrap();
+    txn.finish().unwrap();
+    let repos = read_workspace_index(&index_dir).unwrap();
+    assert_eq!(repos.len(), 1);
+    assert_eq!(repos[0].repo_path, canonical(test_workspace1.repo_path()));
+    assert_eq!(
+        repos[0].workspaces,
+        [("default".into(), default_root(&test_workspace1))]
+    );
+
+    // Failing to update the index doesn't fail the write
+    std::fs::remove_dir_all(&index_dir).unwrap();
+    std::fs::write(&index_dir, "").unwrap();
+    store1.set_path(foo, &foo_root).unwrap();
+    assert_eq!(store1.get_path(foo).unwrap(), canonical(&foo_root));
+    assert_matches!(read_workspace_index(&index_dir), Err(_));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_display_path() {
+    fn check(store: &dyn WorkspaceStore, dir: &Path) {
+        let target = dir.join("target");
+        std::fs::create_dir(&target).unwrap();
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let target = dunce::canonicalize(&target).unwrap();
+        let foo = WorkspaceName::new("foo");
+        let bar = WorkspaceName::new("bar");
+
+        // The root is resolved, but the path it was given as is kept
+        store.set_path(foo, &dir.join("target/../link")).unwrap();
+        assert_eq!(store.get_path(foo).unwrap(), target);
+        assert_eq!(store.get_display_path(foo).unwrap(), link);
+
+        // Without symlinks, the display path is the same as the root
+        store.set_path(bar, &target).unwrap();
+        assert_eq!(store.get_display_path(bar).unwrap(), target);
+
+        assert_matches!(
+            store.get_display_path(WorkspaceName::new("baz")),
+            Err(WorkspaceStoreError::NotFound(_))
+        );
+    }
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    check(&store, test_workspace.env.root());
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(WorkspaceName::DEFAULT)
+        .unwrap();
+    let store = SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap();
+    check(&store, test_workspace.env.root());
+}
+
+/// Store whose entry of `victim` is removed, as if by another process, right
+/// after the entries are listed.
+#[derive(Debug)]
+struct RacingStore {
+    inner: SimpleWorkspaceStore,
+    victim: WorkspaceNameBuf,
+}
+
+impl WorkspaceStore for RacingStore {
+    fn name(&self) -> &str {
+        "racing"
+    }
+
+    fn get_path(&self, workspace_name: &WorkspaceName) -> Result<PathBuf, WorkspaceStoreError> {
+        self.inner.get_path(workspace_name)
+    }
+
+    fn set_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.inner.set_path(workspace_name, path)
+    }
+
+    fn create_path(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.inner.create_path(workspace_name, path)
+    }
+
+    fn encoded_size(
+        &self,
+        workspace_name: &WorkspaceName,
+        path: &Path,
+    ) -> Result<usize, WorkspaceStoreError> {
+        self.inner.encoded_size(workspace_name, path)
+    }
+
+    fn remove_path(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        self.inner.remove_path(workspace_name)
+    }
+
+    fn rename(
+        &self,
+        old_name: &WorkspaceName,
+        new_name: &WorkspaceName,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.inner.rename(old_name, new_name)
+    }
+
+    fn list(&self) -> Result<Vec<WorkspaceNameBuf>, WorkspaceStoreError> {
+        let names = self.inner.list()?;
+        std::fs::remove_file(self.inner.store_dir().join(self.victim.as_str())).unwrap();
+        Ok(names)
+    }
+
+    fn exists(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        self.inner.exists(workspace_name)
+    }
+
+    fn entry_mtime(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<SystemTime, WorkspaceStoreError> {
+        self.inner.entry_mtime(workspace_name)
+    }
+
+    fn created_by_version(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<String>, WorkspaceStoreError> {
+        self.inner.created_by_version(workspace_name)
+    }
+
+    fn created_by_uid(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<u32>, WorkspaceStoreError> {
+        self.inner.created_by_uid(workspace_name)
+    }
+
+    fn created_at_op_id(
+        &self,
+        workspace_name: &WorkspaceName,
+    ) -> Result<Option<OperationId>, WorkspaceStoreError> {
+        self.inner.created_at_op_id(workspace_name)
+    }
+
+    fn set_created_at_op_id(
+        &self,
+        workspace_name: &WorkspaceName,
+        op_id: &OperationId,
+    ) -> Result<(), WorkspaceStoreError> {
+        self.inner.set_created_at_op_id(workspace_name, op_id)
+    }
+
+    fn is_imported(&self, workspace_name: &WorkspaceName) -> Result<bool, WorkspaceStoreError> {
+        self.inner.is_imported(workspace_name)
+    }
+
+    fn set_imported(&self, workspace_name: &WorkspaceName) -> Result<(), WorkspaceStoreError> {
+        self.inner.set_imported(workspace_name)
+    }
+}
+
+#[test]
+fn test_entry_removed_while_reading() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let inner = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let root = test_workspace.workspace.workspace_root();
+    inner.set_path(WorkspaceName::new("other"), root).unwrap();
+    let store = RacingStore {
+        inner,
+        victim: "other".into(),
+    };
+
+    // The removed entry is skipped rather than failing the whole read
+    assert_eq!(
+        store.get_all_paths().unwrap(),
+        vec![(WorkspaceNameBuf::from("default"), root.to_owned())]
+    );
+    assert!(!store.exists(WorkspaceName::new("other")).unwrap());
+
+    store.set_path(WorkspaceName::new("other"), root).unwrap();
+    let view = test_workspace.repo.view();
+    let workspaces = Workspaces::new(&store, view);
+    let names = workspaces.all().unwrap();
+    assert_eq!(names, ["default", "other"].map(WorkspaceNameBuf::from));
+    assert_eq!(
+        workspaces.get_many(&names).unwrap()[1],
+        None,
+        "the removed orphan is no longer known"
+    );
+    assert_eq!(
+        workspaces.path_status(WorkspaceName::new("other")).unwrap(),
+        PathStatus::Unknown
+    );
+}
+
+#[test]
+fn test_try_get_path() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    assert_eq!(
+        store
+            .try_get_path(WorkspaceName::DEFAULT)
+            .unwrap()
+            .as_deref(),
+        Some(workspace_root)
+    );
+    assert_eq!(
+        store.try_get_path(WorkspaceName::new("missing")).unwrap(),
+        None
+    );
+    assert_matches!(
+        store.get_path(WorkspaceName::new("missing")),
+        Err(WorkspaceStoreError::NotFound(_))
+    );
+    // Other errors are still reported
+    std::fs::write(repo_path.join("workspace_store").join("corrupt"), b"\xff").unwrap();
+    assert_matches!(
+        store.try_get_path(WorkspaceName::new("corrupt")),
+        Err(WorkspaceStoreError::Corrupt { .. })
+    );
+}
+
+#[test]
+fn test_link_path() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let temp_dir = testutils::new_temp_dir();
+    let temp_dir = dunce::canonicalize(temp_dir.path()).unwrap();
+    let first_root = temp_dir.join("first");
+    let second_root = temp_dir.join("second");
+    std::fs::create_dir(&first_root).unwrap();
+    std::fs::create_dir(&second_root).unwrap();
+    let link = temp_dir.join(WORKSPACE_LINK_FILE_NAME);
+    let name = WorkspaceName::new("linked");
+
+    // Relative to the directory of the link file
+    std::fs::write(&link, "first\n").unwrap();
+    store.set_link_path(name, &link).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), first_root);
+    assert_eq!(store.get_display_path(name).unwrap(), first_root);
+
+    // Moving the workspace only updates the link file
+    std::fs::write(&link, second_root.as_os_str().as_encoded_bytes()).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), second_root);
+
+    // Links to links are followed
+    let nested_link = temp_dir.join("nested-link");
+    std::fs::write(&nested_link, "first").unwrap();
+    std::fs::write(&link, "nested-link").unwrap();
+    assert_eq!(store.get_path(name).unwrap(), first_root);
+
+    // Loops are detected
+    std::fs::write(&nested_link, WORKSPACE_LINK_FILE_NAME).unwrap();
+    assert_matches!(
+        store.get_path(name),
+        Err(WorkspaceStoreError::TooManyRedirects(path)) if path == link
+    );
+
+    // A root whose link file is gone is reported as missing, and roots are
+    // checked through the link
+    let view = test_workspace.repo.view();
+    std::fs::remove_file(&link).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), link);
+    assert!(
+        store
+            .diagnose(view)
+            .unwrap()
+            .missing
+            .contains(&name.to_owned())
+    );
+    std::fs::write(&link, "first").unwrap();
+    assert!(
+        !store
+            .diagnose(view)
+            .unwrap()
+            .missing
+            .contains(&name.to_owned())
+    );
+
+    // Other entries are unaffected
+    assert_eq!(
+        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+        test_workspace.workspace.workspace_root()
+    );
+}
+
+#[test]
+fn test_diff() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let temp_dir = testutils::new_temp_dir();
+    let temp_dir = dunce::canonicalize(temp_dir.path()).unwrap();
+    let roots = ["first", "second", "third"].map(|name| {
+        let root = temp_dir.join(name);
+        std::fs::create_dir(&root).unwrap();
+        root
+    });
+    let default_root = test_workspace.workspace.workspace_root().to_owned();
+    store
+        .set_path(WorkspaceName::new("first"), &roots[0])
+        .unwrap();
+    store
+        .set_path(WorkspaceName::new("second"), &roots[1])
+        .unwrap();
+    let old = store.snapshot().unwrap();
+    assert_eq!(diff(&old, &old), WorkspaceStoreDiff::default());
+    assert!(diff(&old, &old).is_empty());
+
+    store.remove_path(WorkspaceName::new("first")).unwrap();
+    store
+        .set_path(WorkspaceName::new("second"), &roots[2])
+        .unwrap();
+    store
+        .set_path(WorkspaceName::new("third"), &roots[0])
+        .unwrap();
+    let new = store.snapshot().unwrap();
+    assert_eq!(
+        diff(&old, &new),
+        WorkspaceStoreDiff {
+            added: vec![("third".into(), roots[0].clone())],
+            removed: vec![("first".into(), roots[0].clone())],
+            changed: vec![("second".into(), roots[1].clone(), roots[2].clone())],
+        }
+    );
+    assert_eq!(
+        diff(&new, &old),
+        WorkspaceStoreDiff {
+            added: vec![("first".into(), roots[0].clone())],
+            removed: vec![("third".into(), roots[0].clone())],
+            changed: vec![("second".into(), roots[2].clone(), roots[1].clone())],
+        }
+    );
+    // The unchanged default workspace isn't reported
+    assert_eq!(new.paths[WorkspaceName::DEFAULT], default_root);
+}
+
+#[test]
+fn test_get_many() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let names = ["missing", "default", "missing"].map(WorkspaceNameBuf::from);
+    assert_eq!(
+        store.get_many(&names).unwrap(),
+        vec![
+            (names[0].clone(), None),
+            (
+                names[1].clone(),
+                Some(test_workspace.workspace.workspace_root().to_owned())
+            ),
+            (names[2].clone(), None),
+        ]
+    );
+    assert_eq!(store.get_many(&[]).unwrap(), vec![]);
+}
+
+#[test]
+fn test_get_all_paths() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let other_root = dunce::canonicalize(test_workspace.env.root()).unwrap();
+    store
+        .set_path(WorkspaceName::new("other"), &other_root)
+        .unwrap();
+    // Not an entry
+    std::fs::write(repo_path.join("workspace_store").join(".tmp123"), b"").unwrap();
+
+    assert_eq!(
+        store.get_all_paths().unwrap(),
+        [
+            (WorkspaceNameBuf::from("default"), workspace_root.to_owned()),
+            (WorkspaceNameBuf::from("other"), other_root),
+        ]
+    );
+}
+
+#[test]
+fn test_find_by_path() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    store
+        .set_path(WorkspaceName::new("other"), workspace_root)
+        .unwrap();
+
+    assert_eq!(
+        store.find_by_path(workspace_root).unwrap(),
+        ["default", "other"].map(WorkspaceNameBuf::from)
+    );
+    // The path is canonicalized like recorded roots
+    let parent_dir = workspace_root.parent().unwrap();
+    let file_name = workspace_root.file_name().unwrap();
+    let indirect_path = workspace_root.join("..").join(file_name);
+    assert_eq!(store.find_by_path(&indirect_path).unwrap().len(), 2);
+    // Only exact matches are found
+    assert!(store.find_by_path(parent_dir).unwrap().is_empty());
+    assert!(
+        store
+            .find_by_path(&workspace_root.join("nonexistent"))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_path_exists() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        canonicalizer: Arc::new(NormalizingPathCanonicalizer),
+        ..Default::default()
+    };
+    let normalizing_store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let target = test_workspace.env.root().join("target");
+    std::fs::create_dir(&target).unwrap();
+    let link = test_workspace.env.root().join("link");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    normalizing_store
+        .set_path(WorkspaceName::new("foo"), &link)
+        .unwrap();
+
+    // The configured canonicalizer doesn't resolve symlinks, so the store
+    // agrees with `find_by_path()`
+    assert!(normalizing_store.path_exists(&link).unwrap());
+    assert!(normalizing_store.find_by_path(&target).unwrap().is_empty());
+    assert!(!normalizing_store.path_exists(&target).unwrap());
+
+    // The default canonicalizer resolves symlinks in the recorded root too
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    assert!(store.find_by_path(&target).unwrap().is_empty());
+    assert!(store.path_exists(&target).unwrap());
+    assert!(store.path_exists(&link).unwrap());
+    assert!(
+        store
+            .path_exists(test_workspace.workspace.workspace_root())
+            .unwrap()
+    );
+    assert!(!store.path_exists(test_workspace.env.root()).unwrap());
+    assert!(!store.path_exists(&target.join("nonexistent")).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_mode() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    let roots = ["a", "b"].map(|name| {
+        let root = test_workspace.env.root().join(name);
+        std::fs::create_dir(&root).unwrap();
+        root
+    });
+
+    // Entries are created like other files by default
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    store.set_path(WorkspaceName::new("a"), &roots[0]).unwrap();
+    let other_file = test_workspace.env.root().join("other");
+    std::fs::File::create(&other_file).unwrap();
+    let entry_file = |store: &SimpleWorkspaceStore, name: &str| {
+        store.entry_file(WorkspaceName::new(name)).unwrap().unwrap()
+    };
+    assert_eq!(mode(&entry_file(&store, "a")), mode(&other_file));
+
+    // The configured mode isn't restricted by the umask
+    let options = SimpleWorkspaceStoreOptions {
+        file_mode: Some(0o666),
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    store.set_path(WorkspaceName::new("b"), &roots[1]).unwrap();
+    assert_eq!(mode(&entry_file(&store, "b")), 0o666);
+    store
+        .replace_all(&[
+            ("a".into(), roots[0].as_path()),
+            ("b".into(), roots[1].as_path()),
+        ])
+        .unwrap();
+    assert_eq!(mode(&entry_file(&store, "a")), 0o666);
+    assert_eq!(mode(&entry_file(&store, "b")), 0o666);
+}
+
+#[test]
+fn test_set_path_inside_repo_dir() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let name = WorkspaceName::new("foo");
+    let dot_jj_dir = dunce::canonicalize(repo_path.parent().unwrap()).unwrap();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+
+    // Roots inside the .jj directory are rejected
+    for path in [
+        repo_path.to_owned(),
+        repo_path.join("store"),
+        dot_jj_dir.clone(),
+    ] {
+        assert_matches!(
+            store.set_path(name, &path),
+            Err(WorkspaceStoreError::InvalidPath { repo_dir, .. }) if repo_dir == dot_jj_dir
+        );
+    }
+    assert!(!store.exists(name).unwrap());
+    // The workspace containing the repo is fine
+    store
+        .set_path(name, test_workspace.workspace.workspace_root())
+        .unwrap();
+
+    // The check can be narrowed or disabled
+    let options = SimpleWorkspaceStoreOptions {
+        repo_internal_depth: Some(0),
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    store.set_path(name, &dot_jj_dir).unwrap();
+    assert_matches!(
+        store.set_path(name, repo_path),
+        Err(WorkspaceStoreError::InvalidPath { .. })
+    );
+    let options = SimpleWorkspaceStoreOptions {
+        repo_internal_depth: None,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    store.set_path(name, repo_path).unwrap();
+}
+
+#[test]
+fn test_transaction() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    let foo_root = test_workspace.root_dir().join("foo");
+    std::fs::create_dir(&foo_root).unwrap();
+
+    // Nothing is applied until the transaction is finished
+    let mut txn = store.begin().unwrap();
+    txn.set_path(WorkspaceName::new("foo"), &foo_root).unwrap();
+    txn.set_path(WorkspaceName::new("bar"), &foo_root).unwrap();
+    assert!(txn.remove_path(WorkspaceName::DEFAULT).unwrap());
+    assert!(!txn.remove_path(WorkspaceName::DEFAULT).unwrap());
+    assert!(txn.remove_path(WorkspaceName::new("bar")).unwrap());
+    assert!(!txn.remove_path(WorkspaceName::new("missing")).unwrap());
+    assert_eq!(
+        sorted_names(&store),
+        ["default"].map(WorkspaceNameBuf::from)
+    );
+    txn.finish().unwrap();
+    assert_eq!(sorted_names(&store), ["foo"].map(WorkspaceNameBuf::from));
+    assert_eq!(
+        store.get_path(WorkspaceName::new("foo")).unwrap(),
+        dunce::canonicalize(&foo_root).unwrap()
+    );
+
+    // Dropping the transaction discards the mutations
+    let mut txn = store.begin().unwrap();
+    txn.set_path(WorkspaceName::new("bar"), &foo_root).unwrap();
+    assert!(txn.remove_path(WorkspaceName::new("foo")).unwrap());
+    drop(txn);
+    assert_eq!(sorted_names(&store), ["foo"].map(WorkspaceNameBuf::from));
+
+    // No temporary files or lock files were left behind
+    let file_names = std::fs::read_dir(&store_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect_vec();
+    assert_eq!(file_names, ["foo"]);
+}
+
+#[test]
+fn test_rename() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let old_name = WorkspaceName::DEFAULT;
+    let new_name = WorkspaceName::new("new");
+
+    store.rename(old_name, new_name).unwrap();
+    assert_eq!(sorted_names(&store), ["new"].map(WorkspaceNameBuf::from));
+    assert_eq!(
+        store.get_path(new_name).unwrap(),
+        test_workspace.workspace.workspace_root()
+    );
+
+    assert_matches!(
+        store.rename(old_name, new_name),
+        Err(WorkspaceStoreError::NotFound(n)) if n == old_name
+    );
+
+    // The entry of the destination isn't replaced
+    store.set_path(old_name, test_workspace.env.root()).unwrap();
+    assert_matches!(
+        store.rename(old_name, new_name),
+        Err(WorkspaceStoreError::AlreadyExists(n)) if n == new_name
+    );
+    assert_eq!(
+        store.get_path(new_name).unwrap(),
+        test_workspace.workspace.workspace_root()
+    );
+}
+
+#[test]
+fn test_rename_keeps_metadata() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        checksum: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let old_name = WorkspaceName::new("old");
+    let new_name = WorkspaceName::new("new");
+
+    // Entry written by an older version, a while ago
+    let old_file = repo_path.join("workspace_store").join("old");
+    std::fs::write(&old_file, b"\x0a\x03old\x12\x04/old\x22\x050.1.0").unwrap();
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(&old_file)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+
+    store.rename(old_name, new_name).unwrap();
+    assert_eq!(store.get_path(new_name).unwrap(), Path::new("/old"));
+    assert_eq!(
+        store.created_by_version(new_name).unwrap().as_deref(),
+        Some("0.1.0")
+    );
+    assert_eq!(store.entry_mtime(new_name).unwrap(), mtime);
+    assert!(!store.exists(old_name).unwrap());
+}
+
+#[test]
+fn test_finish_interrupted_rename() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let store_dir = repo_path.join("workspace_store");
+    let old_name = WorkspaceName::new("old");
+    let new_name = WorkspaceName::new("new");
+
+    // Renamed from "old" to "new", but crashed before the old entry was
+    // removed
+    std::fs::write(store_dir.join("old"), b"\x0a\x03old\x12\x04/old").unwrap();
+    std::fs::write(store_dir.join("new"), b"\x0a\x03new\x12\x04/old\x3a\x03old").unwrap();
+    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(store_dir.join("new"))
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    // Renamed from "other" to "third", and "other" was recorded again since
+    std::fs::write(store_dir.join("other"), b"\x0a\x05other\x12\x06/other").unwrap();
+    std::fs::write(
+        store_dir.join("third"),
+        b"\x0a\x05third\x12\x04/old\x3a\x05other",
+    )
+    .unwrap();
+
+    assert_eq!(
+        store.finish_renames().unwrap(),
+        ["new", "third"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "new", "other", "third"].map(WorkspaceNameBuf::from)
+    );
+    assert!(!store.exists(old_name).unwrap());
+    assert_eq!(store.get_path(new_name).unwrap(), Path::new("/old"));
+    assert_eq!(store.entry_mtime(new_name).unwrap(), mtime);
+    assert_eq!(
+        store.get_path(WorkspaceName::new("other")).unwrap(),
+        Path::new("/other")
+    );
+
+    // The markers were cleared
+    assert!(store.finish_renames().unwrap().is_empty());
+
+    // Completed renames leave no marker behind
+    store.rename(new_name, old_name).unwrap();
+    assert!(store.finish_renames().unwrap().is_empty());
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "old", "other", "third"].map(WorkspaceNameBuf::from)
+    );
+}
+
+#[test]
+fn test_load_finishes_interrupted_rename() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store_dir = repo_path.join("workspace_store");
+
+    // Renamed from "old" to "new", but crashed before the old entry was
+    // removed, leaving the marker of the rename behind
+    std::fs::write(store_dir.join("old"), b"\x0a\x03old\x12\x04/old").unwrap();
+    std::fs::write(store_dir.join("new"), b"\x0a\x03new\x12\x04/old\x3a\x03old").unwrap();
+    std::fs::write(store_dir.join(".renaming"), b"").unwrap();
+
+    // A read-only store is left as is
+    let store = SimpleWorkspaceStore::load_readonly(repo_path).unwrap();
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "new", "old"].map(WorkspaceNameBuf::from)
+    );
+
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "new"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        store.get_path(WorkspaceName::new("new")).unwrap(),
+        Path::new("/old")
+    );
+    assert!(!store_dir.join(".renaming").exists());
+    assert!(store.finish_renames().unwrap().is_empty());
+}
+
+#[test]
+fn test_create_path() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let name = WorkspaceName::new("new");
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    store.create_path(name, workspace_root).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+
+    // Unlike set_path(), the existing entry isn't replaced
+    assert_matches!(
+        store.create_path(name, test_workspace.env.root()),
+        Err(WorkspaceStoreError::AlreadyExists(n)) if n == name
+    );
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+
+    store.remove_path(name).unwrap();
+    store.create_path(name, test_workspace.env.root()).unwrap();
+    assert_eq!(
+        store.get_path(name).unwrap(),
+        dunce::canonicalize(test_workspace.env.root()).unwrap()
+    );
+}
+
+#[test]
+fn test_store_dir() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    assert_eq!(store.store_dir(), repo_path.join("workspace_store"));
+    let dyn_store: &dyn WorkspaceStore = &store;
+    assert_eq!(dyn_store.store_dir(), Some(store.store_dir()));
+}
+
+#[test]
+fn test_len() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    assert_eq!(store.len().unwrap(), 1);
+    assert!(!store.is_empty().unwrap());
+
+    // Temporary files and nested names are handled like by list()
+    store
+        .set_path(WorkspaceName::new("team/feature"), workspace_root)
+        .unwrap();
+    std::fs::write(repo_path.join("workspace_store").join(".tmpXYZ"), b"").unwrap();
+    assert_eq!(store.len().unwrap(), store.list().unwrap().len());
+    assert_eq!(store.len().unwrap(), 2);
+
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+    store
+        .remove_path(WorkspaceName::new("team/feature"))
+        .unwrap();
+    assert_eq!(store.len().unwrap(), 0);
+    assert!(store.is_empty().unwrap());
+}
+
+#[test]
+fn test_existing() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    store
+        .set_path(WorkspaceName::new("team/feature"), workspace_root)
+        .unwrap();
+    std::fs::write(repo_path.join("workspace_store").join("empty"), b"").unwrap();
+
+    let names = ["default", "team/feature", "empty", "unknown", "team"].map(WorkspaceNameBuf::from);
+    assert_eq!(
+        store.existing(&names).unwrap(),
+        HashSet::from(["default", "team/feature"].map(WorkspaceNameBuf::from))
+    );
+    assert_matches!(
+        store.existing(&["unknown", ""].map(WorkspaceNameBuf::from)),
+        Err(WorkspaceStoreError::InvalidName(name)) if name.as_str().is_empty()
+    );
+}
+
+#[test]
+fn test_existing_listing_ttl() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        listing_ttl: Some(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let other_store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let names = ["default", "a", "b"].map(WorkspaceNameBuf::from);
+    assert_eq!(
+        store.existing(&names).unwrap(),
+        HashSet::from([WorkspaceNameBuf::from("default")])
+    );
+
+    // Entries added by another store instance aren't seen until the listing
+    // is discarded
+    other_store
+        .set_path(WorkspaceName::new("a"), workspace_root)
+        .unwrap();
+    assert!(!store.exists(WorkspaceName::new("a")).unwrap());
+    assert_eq!(store.existing(&names).unwrap().len(), 1);
+
+    // Mutations through the same instance discard the listing
+    store
+        .set_path(WorkspaceName::new("b"), workspace_root)
+        .unwrap();
+    assert!(store.exists(WorkspaceName::new("a")).unwrap());
+    assert_eq!(store.existing(&names).unwrap().len(), 3);
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+    assert!(!store.exists(WorkspaceName::DEFAULT).unwrap());
+
+    // Entries removed by another store instance are noticed anyway
+    other_store.remove_path(WorkspaceName::new("a")).unwrap();
+    assert!(!store.exists(WorkspaceName::new("a")).unwrap());
+}
+
+#[test]
+fn test_relocate_store() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let old_dir = repo_path.join("workspace_store");
+    let new_dir = test_workspace.root_dir().join("moved_store");
+    let mut store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    store
+        .set_path(WorkspaceName::new("team/feature"), workspace_root)
+        .unwrap();
+    let mtime = store.entry_mtime(WorkspaceName::DEFAULT).unwrap();
+
+    // The target must not exist
+    std::fs::create_dir(&new_dir).unwrap();
+    assert_matches!(
+        store.relocate_store(&new_dir),
+        Err(WorkspaceStoreError::Path(err)) if err.source.kind() == io::ErrorKind::AlreadyExists
+    );
+    assert_eq!(store.store_dir(), old_dir);
+    std::fs::remove_dir(&new_dir).unwrap();
+
+    store.relocate_store(&new_dir).unwrap();
+    assert_eq!(store.store_dir(), new_dir);
+    assert!(!old_dir.exists());
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "team/feature"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(store.entry_mtime(WorkspaceName::DEFAULT).unwrap(), mtime);
+
+    // The store keeps working at the new location
+    store
+        .set_path(WorkspaceName::new("second"), workspace_root)
+        .unwrap();
+    assert!(new_dir.join("second").is_file());
+    assert!(!old_dir.exists());
+}
+
+#[test]
+fn test_replace_all() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let root_a = test_workspace.root_dir().join("a");
+    let root_b = test_workspace.root_dir().join("b");
+    std::fs::create_dir(&root_a).unwrap();
+    std::fs::create_dir(&root_b).unwrap();
+    let root_a = dunce::canonicalize(root_a).unwrap();
+    let root_b = dunce::canonicalize(root_b).unwrap();
+    store.set_path(WorkspaceName::new("a"), &root_a).unwrap();
+
+    store
+        .replace_all(&[
+            ("team/x".into(), root_a.as_path()),
+            ("b".into(), &root_a),
+            ("b".into(), &root_b),
+        ])
+        .unwrap();
+    assert_eq!(
+        sorted_names(&store),
+        ["b", "team/x"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(store.get_path(WorkspaceName::new("b")).unwrap(), root_b);
+    assert_eq!(
+        store.get_path(WorkspaceName::new("team/x")).unwrap(),
+        root_a
+    );
+    let leftovers = std::fs::read_dir(repo_path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("workspace_store") || name.starts_with(".tmp"))
+        .collect_vec();
+    assert_eq!(leftovers, ["workspace_store"]);
+
+    // Nothing is replaced if any entry can't be recorded
+    assert_matches!(
+        store.replace_all(&[("c".into(), &root_a), ("c/d".into(), &root_b)]),
+        Err(WorkspaceStoreError::InvalidName(name)) if name == "c/d"
+    );
+    assert_matches!(
+        store.replace_all(&[("c".into(), &root_a.join("nonexistent"))]),
+        Err(WorkspaceStoreError::Path(_))
+    );
+    assert_eq!(
+        sorted_names(&store),
+        ["b", "team/x"].map(WorkspaceNameBuf::from)
+    );
+
+    // The store keeps working, and the type of the store is kept
+    store.remove_path(WorkspaceName::new("b")).unwrap();
+    store.remove_path(WorkspaceName::new("team/x")).unwrap();
+    let store = SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap();
+    store.replace_all(&[("c".into(), &root_a)]).unwrap();
+    let store = load_workspace_store(repo_path, Default::default()).unwrap();
+    assert_eq!(store.name(), "json");
+    assert_eq!(store.list().unwrap(), ["c"].map(WorkspaceNameBuf::from));
+}
+
+#[test]
+fn test_replace_all_interrupted() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store_dir = repo_path.join("workspace_store");
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    store
+        .set_path(WorkspaceName::new("new"), workspace_root)
+        .unwrap();
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+
+    // Interrupted after the old store was moved aside
+    std::fs::rename(&store_dir, repo_path.join("workspace_store.new")).unwrap();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    assert_eq!(store.list().unwrap(), ["new"].map(WorkspaceNameBuf::from));
+    assert!(!repo_path.join("workspace_store.new").exists());
+
+    // Interrupted before the old store was removed
+    std::fs::create_dir(repo_path.join("workspace_store.old")).unwrap();
+    store.replace_all(&[]).unwrap();
+    assert!(store.list().unwrap().is_empty());
+    assert!(!repo_path.join("workspace_store.old").exists());
+}
+
+#[test]
+fn test_store_dir_removed() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let name = WorkspaceName::new("new");
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    // The directory is recreated when the store is mutated
+    std::fs::remove_dir_all(repo_path.join("workspace_store")).unwrap();
+    store.set_path(name, workspace_root).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+    assert_eq!(sorted_names(&store), ["new"].map(WorkspaceNameBuf::from));
+
+    // Removing an entry doesn't need the directory, since the lock file is
+    // outside of it
+    std::fs::remove_dir_all(repo_path.join("workspace_store")).unwrap();
+    assert!(!store.remove_path(name).unwrap());
+    store.set_path(name, workspace_root).unwrap();
+    assert!(repo_path.join("workspace_store").is_dir());
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_watch() {
+    use jj_lib::workspace_store::WorkspaceStoreEvent;
+
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let name = WorkspaceName::new("new");
+    let mut events = store.watch().unwrap();
+
+    store
+        .set_path(name, test_workspace.workspace.workspace_root())
+        .unwrap();
+    assert_eq!(
+        events.next(),
+        Some(WorkspaceStoreEvent::Added(name.to_owned()))
+    );
+
+    // Replacing the entry through a temporary file is reported once
+    store.set_path(name, test_workspace.env.root()).unwrap();
+    assert_eq!(
+        events.next(),
+        Some(WorkspaceStoreEvent::Changed(name.to_owned()))
+    );
+
+    store.remove_path(name).unwrap();
+    assert_eq!(
+        events.next(),
+        Some(WorkspaceStoreEvent::Removed(name.to_owned()))
+    );
+}
+
+#[test]
+fn test_case_sensitive_name_encoding() {
+    let test_workspace = TestWorkspace::init();
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    std::fs::remove_dir_all(&store_dir).unwrap();
+    let options = SimpleWorkspaceStoreOptions {
+        name_encoding: NameEncoding::CaseSensitive,
+        ..Default::default()
+    };
+    let store =
+        SimpleWorkspaceStore::init_with_options(test_workspace.repo_path(), options).unwrap();
+    let upper_root = test_workspace.root_dir().join("upper");
+    let lower_root = test_workspace.root_dir().join("lower");
+    std::fs::create_dir(&upper_root).unwrap();
+    std::fs::create_dir(&lower_root).unwrap();
+
+    let names = ["WS", "ws", "Ws%2", "Ärger"].map(WorkspaceNameBuf::from);
+    for (i, name) in names.iter().enumerate() {
+        let root = if i % 2 == 0 { &upper_root } else { &lower_root };
+        store.set_path(name, root).unwrap();
+    }
+    assert_eq!(
+        sorted_names(&store),
+        names.iter().cloned().sorted().collect_vec()
+    );
+    for (i, name) in names.iter().enumerate() {
+        let root = if i % 2 == 0 { &upper_root } else { &lower_root };
+        assert_eq!(
+            store.get_path(name).unwrap(),
+            dunce::canonicalize(root).unwrap()
+        );
+    }
+
+    let file_names = std::fs::read_dir(&store_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .sorted()
+        .collect_vec();
+    assert_eq!(file_names, ["%57%53", "%57s%252", "%C3%84rger", "ws"]);
+
+    store
+        .rename(WorkspaceName::new("WS"), WorkspaceName::new("W"))
+        .unwrap();
+    assert!(!store.exists(WorkspaceName::new("WS")).unwrap());
+    assert!(store.exists(WorkspaceName::new("ws")).unwrap());
+    assert!(store.exists(WorkspaceName::new("W")).unwrap());
+}
+
+#[test]
+fn test_illegal_file_name_chars() {
+    let test_workspace = TestWorkspace::init();
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    // Characters that are illegal on some platforms are encoded everywhere
+    let names = ["a:b", "what?", "a\\b", "100%", ".lock", "x.y"].map(WorkspaceNameBuf::from);
+    for name in &names {
+        store.set_path(name, workspace_root).unwrap();
+        assert_eq!(store.get_path(name).unwrap(), workspace_root);
+    }
+    assert_eq!(
+        sorted_names(&store),
+        names
+            .iter()
+            .cloned()
+            .chain([WorkspaceNameBuf::from("default")])
+            .sorted()
+            .collect_vec()
+    );
+    let file_names = std::fs::read_dir(&store_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .sorted()
+        .collect_vec();
+    assert_eq!(
+        file_names,
+        [
+            "%2Elock", "100%25", "a%3Ab", "a%5Cb", "default", "what%3F", "x.y"
+        ]
+    );
+
+    // Names that can't be represented are rejected
+    for name in ["", &"x".repeat(256)] {
+        assert_matches!(
+            store.set_path(WorkspaceName::new(name), workspace_root),
+            Err(WorkspaceStoreError::InvalidName(n)) if n == name
+        );
+    }
+}
+
+#[test]
+fn test_reserved_file_names() {
+    let test_workspace = TestWorkspace::init();
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    // Device names are escaped on every platform, so that they can be stored
+    // as files on Windows too
+    let names = ["CON", "nul.txt", "Com1", "lpt9", "console"].map(WorkspaceNameBuf::from);
+    for name in &names {
+        store.set_path(name, workspace_root).unwrap();
+        assert_eq!(store.get_path(name).unwrap(), workspace_root);
+    }
+    assert_eq!(
+        sorted_names(&store),
+        names
+            .iter()
+            .cloned()
+            .chain([WorkspaceNameBuf::from("default")])
+            .sorted()
+            .collect_vec()
+    );
+    let file_names = std::fs::read_dir(&store_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .sorted()
+        .collect_vec();
+    assert_eq!(
+        file_names,
+        [
+            "%43ON",
+            "%43om1",
+            "%6Cpt9",
+            "%6Eul.txt",
+            "console",
+            "default"
+        ]
+    );
+
+    assert!(store.remove_path(WorkspaceName::new("CON")).unwrap());
+    assert!(!store.exists(WorkspaceName::new("CON")).unwrap());
+}
+
+#[test]
+fn test_nested_names() {
+    let test_workspace = TestWorkspace::init();
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    // Namespaces are stored in subdirectories
+    let names = ["team/feature", "team/.hidden/x", "team:a/b"].map(WorkspaceNameBuf::from);
+    for name in &names {
+        store.set_path(name, workspace_root).unwrap();
+        assert_eq!(store.get_path(name).unwrap(), workspace_root);
+    }
+    assert!(store_dir.join("team").join("feature").is_file());
+    assert!(store_dir.join("team").join("%2Ehidden").join("x").is_file());
+    assert!(store_dir.join("team%3Aa").join("b").is_file());
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "team/.hidden/x", "team/feature", "team:a/b"].map(WorkspaceNameBuf::from)
+    );
+
+    // A namespace isn't an entry, and an entry can't be a namespace
+    assert!(!store.exists(WorkspaceName::new("team")).unwrap());
+    assert_matches!(
+        store.get_path(WorkspaceName::new("team")),
+        Err(WorkspaceStoreError::NotFound(_))
+    );
+    assert_matches!(
+        store.set_path(WorkspaceName::new("team"), workspace_root),
+        Err(WorkspaceStoreError::InvalidName(_))
+    );
+    assert_matches!(
+        store.set_path(WorkspaceName::new("team/feature/x"), workspace_root),
+        Err(WorkspaceStoreError::InvalidName(_))
+    );
+    store
+        .rename(
+            WorkspaceName::new("team/feature"),
+            WorkspaceName::new("other/feature"),
+        )
+        .unwrap();
+    assert!(!store_dir.join("team").join("feature").exists());
+
+    // Namespaces are removed once they're empty
+    store
+        .remove_path(WorkspaceName::new("team/.hidden/x"))
+        .unwrap();
+    store.remove_path(WorkspaceName::new("team:a/b")).unwrap();
+    assert!(!store_dir.join("team").exists());
+    assert!(!store_dir.join("team%3Aa").exists());
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "other/feature"].map(WorkspaceNameBuf::from)
+    );
+
+    // Empty, `.`, and `..` components are rejected
+    for name in ["/a", "a/", "a//b", "./a", "a/../b", ".."] {
+        assert_matches!(
+            store.set_path(WorkspaceName::new(name), workspace_root),
+            Err(WorkspaceStoreError::InvalidName(n)) if n == name
+        );
+    }
+}
+
+#[test]
+fn test_validate_name() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let case_sensitive_store = SimpleWorkspaceStore::load_with_options(
+        test_workspace.repo_path(),
+        SimpleWorkspaceStoreOptions {
+            name_encoding: NameEncoding::CaseSensitive,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+
+    let long_name = "x".repeat(255);
+    let too_long_name = "x".repeat(256);
+    let long_escaped_name = "%".repeat(85);
+    let too_long_escaped_name = "%".repeat(86);
+    let upper_name = "X".repeat(100);
+    let cases = [
+        // (name, valid, valid with the case-sensitive encoding)
+        ("default", true, true),
+        ("team/feature", true, true),
+        (".hidden", true, true),
+        ("a\\b", true, true),
+        ("CON", true, true),
+        ("a.", true, true),
+        ("...", true, true),
+        (&long_name, true, true),
+        (&long_escaped_name, true, true),
+        (&upper_name, true, false),
+        ("", false, false),
+        ("/", false, false),
+        ("/a", false, false),
+        ("a/", false, false),
+        ("a//b", false, false),
+        (".", false, false),
+        ("..", false, false),
+        ("a/./b", false, false),
+        ("a/..", false, false),
+        (&too_long_name, false, false),
+        (&too_long_escaped_name, false, false),
+    ];
+    for (name, valid, case_sensitive_valid) in cases {
+        let name = WorkspaceName::new(name);
+        for (store, valid) in [
+            (&store, valid),
+            (&case_sensitive_store, case_sensitive_valid),
+        ] {
+            if valid {
+                assert_matches!(store.validate_name(name), Ok(()), "{name:?}");
+            } else {
+                assert_matches!(
+                    store.validate_name(name),
+                    Err(WorkspaceStoreError::InvalidName(n)) if n == name,
+                    "{name:?}"
+                );
+            }
+        }
+    }
+
+    // Invalid names are rejected before the path is looked at
+    let missing_root = test_workspace.root_dir().join("missing");
+    assert_matches!(
+        store.set_path(WorkspaceName::new("a//b"), &missing_root),
+        Err(WorkspaceStoreError::InvalidName(_))
+    );
+    assert_matches!(
+        store.create_path(WorkspaceName::new(".."), &missing_root),
+        Err(WorkspaceStoreError::InvalidName(_))
+    );
+    assert_matches!(
+        store.rename(WorkspaceName::new("missing"), WorkspaceName::new("")),
+        Err(WorkspaceStoreError::InvalidName(_))
+    );
+    assert_eq!(store.list().unwrap(), [WorkspaceNameBuf::from("default")]);
+    store
+        .set_path(WorkspaceName::new("team/feature"), workspace_root)
+        .unwrap();
+}
+
+#[test]
+fn test_workspaces() {
+    let test_workspace = TestWorkspace::init();
+    let repo = &test_workspace.repo;
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let default_root = test_workspace.workspace.workspace_root();
+    let wc_commit_id = repo
+        .view()
+        .get_wc_commit_id(WorkspaceName::DEFAULT)
+        .unwrap();
+    // A workspace whose working-copy commit is gone
+    let orphan_root = test_workspace.root_dir().join("orphan");
+    std::fs::create_dir(&orphan_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("orphan"), &orphan_root)
+        .unwrap();
+
+    let workspaces = Workspaces::new(&store, repo.view());
+    assert_eq!(
+        workspaces.all().unwrap(),
+        ["default", "orphan"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        workspaces.orphans().unwrap(),
+        ["orphan"].map(WorkspaceNameBuf::from)
+    );
+    assert!(workspaces.missing().unwrap().is_empty());
+    assert_eq!(
+        workspaces.get(WorkspaceName::DEFAULT).unwrap(),
+        Some(WorkspaceEntry {
+            name: WorkspaceName::DEFAULT.to_owned(),
+            wc_commit_id: Some(wc_commit_id.clone()),
+            path: Some(default_root.to_owned()),
+        })
+    );
+    assert_eq!(
+        workspaces.get(WorkspaceName::new("orphan")).unwrap(),
+        Some(WorkspaceEntry {
+            name: WorkspaceName::new("orphan").to_owned(),
+            wc_commit_id: None,
+            path: Some(dunce::canonicalize(&orphan_root).unwrap()),
+        })
+    );
+    assert_eq!(workspaces.get(WorkspaceName::new("unknown")).unwrap(), None);
+
+    // The root of the current workspace is known even without an entry
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+    assert_matches!(
+        workspaces.resolve_root(WorkspaceName::DEFAULT),
+        Err(WorkspaceStoreError::NotFound(_))
+    );
+    let workspaces = workspaces.with_current(WorkspaceName::DEFAULT, default_root);
+    assert_eq!(
+        workspaces.resolve_root(WorkspaceName::DEFAULT).unwrap(),
+        default_root
+    );
+    assert_matches!(
+        workspaces.resolve_root(WorkspaceName::new("unknown")),
+        Err(WorkspaceStoreError::NotFound(_))
+    );
+
+    // Workspaces whose root was removed
+    std::fs::remove_dir(&orphan_root).unwrap();
+    assert_eq!(
+        workspaces.missing().unwrap(),
+        ["orphan"].map(WorkspaceNameBuf::from)
+    );
+}
+
+#[test]
+fn test_workspace_overview() {
+    let test_workspace = TestWorkspace::init();
+    let repo = &test_workspace.repo;
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    let default_root = test_workspace.workspace.workspace_root();
+    let wc_commit_id = repo
+        .view()
+        .get_wc_commit_id(WorkspaceName::DEFAULT)
+        .unwrap();
+    let wc_commit = repo.store().get_commit(wc_commit_id).unwrap();
+    let orphan_root = test_workspace.root_dir().join("orphan");
+    std::fs::create_dir(&orphan_root).unwrap();
+    let orphan_root = dunce::canonicalize(&orphan_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("orphan"), &orphan_root)
+        .unwrap();
+    std::fs::remove_dir(&orphan_root).unwrap();
+
+    let overviews = workspace_overview(&store, repo.as_ref()).unwrap();
+    assert_eq!(
+        overviews,
+        [
+            WorkspaceOverview {
+                name: WorkspaceName::DEFAULT.to_owned(),
+                path: Some(default_root.to_owned()),
+                exists: true,
+                wc_commit_id: Some(wc_commit_id.clone()),
+                change_id: Some(wc_commit.change_id().clone()),
+            },
+            WorkspaceOverview {
+                name: "orphan".into(),
+                path: Some(orphan_root.clone()),
+                exists: false,
+                wc_commit_id: None,
+                change_id: None,
+            },
+        ]
+    );
+
+    // The root of the current workspace is known even without an entry
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+    let workspaces = Workspaces::new(&store, repo.view());
+    let overview = workspaces
+        .overview(WorkspaceName::DEFAULT, repo.store())
+        .unwrap()
+        .unwrap();
+    assert_eq!(overview.path, None);
+    assert!(!overview.exists);
+    let workspaces = workspaces.with_current(WorkspaceName::DEFAULT, default_root);
+    let overview = workspaces
+        .overview(WorkspaceName::DEFAULT, repo.store())
+        .unwrap()
+        .unwrap();
+    assert_eq!(overview.path.as_deref(), Some(default_root));
+    assert!(overview.exists);
+    assert_eq!(
+        workspaces
+            .overview(WorkspaceName::new("unknown"), repo.store())
+            .unwrap(),
+        None
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_workspace_overview_serialize() {
+    let overview = WorkspaceOverview {
+        name: "orphan".into(),
+        path: Some("/orphan".into()),
+        exists: false,
+        wc_commit_id: None,
+        change_id: None,
+    };
+    let json = serde_json::to_value(&overview).unwrap();
+    assert_eq!(json["name"], "orphan");
+    assert_eq!(json["path"], "/orphan");
+    assert_eq!(json["exists"], false);
+    assert_eq!(json["change_id"], serde_json::Value::Null);
+
+    // Roots that aren't valid UTF-8 are serialized lossily
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt as _;
+        let overview = WorkspaceOverview {
+            path: Some(OsStr::from_bytes(b"/\xff").into()),
+            ..overview
+        };
+        let json = serde_json::to_value(&overview).unwrap();
+        assert_eq!(json["path"], "/\u{fffd}");
+    }
+}
+
+#[test]
+fn test_workspace_entry_display() {
+    let entry = |path: Option<&str>| WorkspaceEntry {
+        name: "ws".into(),
+        wc_commit_id: None,
+        path: path.map(PathBuf::from),
+    };
+    assert_eq!(entry(None).to_string(), "ws");
+    let short_path = ["", "a", "b"].iter().collect::<PathBuf>();
+    assert_eq!(
+        entry(short_path.to_str()).to_string(),
+        format!("ws ({})", short_path.display())
+    );
+
+    // Long paths are shortened to their last components
+    let long_component = "x".repeat(60);
+    let long_path = ["", &long_component, "c", "d", "e"]
+        .iter()
+        .collect::<PathBuf>();
+    let sep = std::path::MAIN_SEPARATOR;
+    assert_eq!(
+        entry(long_path.to_str()).to_string(),
+        format!("ws (...{sep}c{sep}d{sep}e)")
+    );
+    let long_path = ["", "a", &"y".repeat(80)].iter().collect::<PathBuf>();
+    assert_eq!(
+        entry(long_path.to_str()).to_string(),
+        format!("ws (...{sep}{})", "y".repeat(80))
+    );
+}
+
+#[test]
+fn test_maintain() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store_dir = repo_path.join("workspace_store");
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let view = test_workspace.repo.view();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let gone_root = test_workspace.root_dir().join("gone");
+    std::fs::create_dir(&gone_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("gone"), &gone_root)
+        .unwrap();
+    std::fs::remove_dir(&gone_root).unwrap();
+    // Imported roots may never have been added to the repo
+    let imported_root = test_workspace.root_dir().join("imported");
+    std::fs::create_dir(&imported_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("imported"), &imported_root)
+        .unwrap();
+    store.set_imported(WorkspaceName::new("imported")).unwrap();
+    std::fs::remove_dir(&imported_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("kept"), workspace_root)
+        .unwrap();
+    std::fs::write(store_dir.join("bad"), b"\xff\xff\xff").unwrap();
+    std::fs::write(store_dir.join(".tmp123"), b"").unwrap();
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+
+    // Nothing is done unless requested
+    let report = maintain(&store, view, &MaintenanceOptions::default()).unwrap();
+    assert!(report.is_empty());
+    assert_eq!(
+        sorted_names(&store),
+        ["bad", "gone", "imported", "kept"].map(WorkspaceNameBuf::from)
+    );
+
+    let options = MaintenanceOptions {
+        repair: true,
+        prune: true,
+        remove_temp_files: true,
+        backfill: Some((WorkspaceNameBuf::from("default"), workspace_root.to_owned())),
+        finish_renames: true,
+        purge_tombstones: None,
+    };
+    let report = maintain(&store, view, &options).unwrap();
+    assert_eq!(
+        report,
+        MaintenanceReport {
+            repaired: vec!["bad".into()],
+            pruned: vec!["gone".into()],
+            removed_temp_files: 1,
+            backfilled: vec!["default".into()],
+            finished_renames: vec![],
+            purged_tombstones: 0,
+        }
+    );
+    assert_eq!(
+        sorted_names(&store),
+        ["default", "imported", "kept"].map(WorkspaceNameBuf::from)
+    );
+    assert!(!store_dir.join(".tmp123").exists());
+
+    assert!(maintain(&store, view, &options).unwrap().is_empty());
+}
+
+#[test]
+fn test_diagnose() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    assert!(
+        store
+            .diagnose(test_workspace.repo.view())
+            .unwrap()
+            .is_empty()
+    );
+
+    // A name that differs only in case from a recorded one
+    let wc_commit_id = test_workspace
+        .repo
+        .view()
+        .get_wc_commit_id(WorkspaceName::DEFAULT)
+        .unwrap()
+        .clone();
+    let mut tx = test_workspace.repo.start_transaction();
+    tx.repo_mut()
+        .set_wc_commit("Kept".into(), wc_commit_id)
+        .unwrap();
+    let repo = tx.commit("test").unwrap();
+    let view = repo.view();
+
+    let gone_root = test_workspace.root_dir().join("gone");
+    std::fs::create_dir(&gone_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("gone"), &gone_root)
+        .unwrap();
+    std::fs::remove_dir(&gone_root).unwrap();
+    let other_root = test_workspace.root_dir().join("other");
+    std::fs::create_dir(&other_root).unwrap();
+    store
+        .set_path(WorkspaceName::new("other"), &other_root)
+        .unwrap();
+    // Imported roots needn't be workspaces of the repo
+    let imported_root = test_workspace.root_dir().join("imported");
+    std::fs::create_dir(&imported_root).unwrap();
+    let mut txn = store.begin().unwrap();
+    txn.set_imported_path(WorkspaceName::new("imported"), &imported_root)
+        .unwrap();
+    txn.finish().unwrap();
+    for name in ["dup", "kept"] {
+        store
+            .set_path(WorkspaceName::new(name), workspace_root)
+            .unwrap();
+    }
+    std::fs::write(store.store_dir().join("bad"), b"\xff\xff\xff").unwrap();
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+
+    let names = |names: &[&str]| {
+        names
+            .iter()
+            .copied()
+            .map(WorkspaceNameBuf::from)
+            .collect_vec()
+    };
+    let diagnosis = store.diagnose(view).unwrap();
+    assert_eq!(
+        diagnosis,
+        Diagnosis {
+            corrupt: names(&["bad"]),
+            interrupted_renames: vec![],
+            missing: names(&["gone"]),
+            foreign: names(&["other"]),
+            unrecorded: names(&["Kept", "default"]),
+            orphans: names(&["dup", "gone", "kept", "other"]),
+            duplicate_paths: vec![(
+                dunce::canonicalize(workspace_root).unwrap(),
+                names(&["dup", "kept"])
+            )],
+            case_collisions: vec![names(&["Kept", "kept"])],
+        }
+    );
+    assert_eq!(diagnosis.len(), 11);
+
+    // The repairs of maintain() resolve some of the problems
+    let options = MaintenanceOptions {
+        repair: true,
+        prune: true,
+        backfill: Some((WorkspaceNameBuf::from("default"), workspace_root.to_owned())),
+        ..Default::default()
+    };
+    maintain(&store, view, &options).unwrap();
+    let diagnosis = store.diagnose(view).unwrap();
+    assert!(diagnosis.corrupt.is_empty());
+    assert!(diagnosis.missing.is_empty());
+    assert_eq!(diagnosis.unrecorded, names(&["Kept"]));
+    assert_eq!(diagnosis.orphans, names(&["dup", "kept", "other"]));
+    assert_eq!(
+        diagnosis.duplicate_paths[0].1,
+        names(&["default", "dup", "kept"])
+    );
+
+    // Names that differ in case don't collide with the case-sensitive encoding
+    let options = SimpleWorkspaceStoreOptions {
+        name_encoding: NameEncoding::CaseSensitive,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    assert!(store.diagnose(view).unwrap().case_collisions.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_only_repo() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    std::fs::remove_dir_all(repo_path.join("workspace_store")).unwrap();
+    // Loading doesn't create the store
+    assert_matches!(
+        SimpleWorkspaceStore::load(repo_path),
+        Err(WorkspaceStoreError::NotInitialized(_))
+    );
+    std::fs::set_permissions(repo_path, std::fs::Permissions::from_mode(0o555)).unwrap();
+    let is_writable = std::fs::create_dir(repo_path.join("probe")).is_ok();
+    let result = SimpleWorkspaceStore::init(repo_path);
+    std::fs::set_permissions(repo_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    if is_writable {
+        // Permissions aren't enforced, e.g. when running as root
+        return;
+    }
+
+    // The store reads as empty, and mutations fail
+    let store = result.unwrap();
+    assert!(store.is_read_only());
+    assert!(store.list().unwrap().is_empty());
+    assert_matches!(
+        store.get_path(WorkspaceName::DEFAULT),
+        Err(WorkspaceStoreError::NotFound(_))
+    );
+    assert_matches!(
+        store.set_path(
+            WorkspaceName::DEFAULT,
+            test_workspace.workspace.workspace_root()
+        ),
+        Err(WorkspaceStoreError::ReadOnly(_))
+    );
+    assert_matches!(
+        store.remove_path(WorkspaceName::DEFAULT),
+        Err(WorkspaceStoreError::ReadOnly(_))
+    );
+    store.flush().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_only_store_dir() {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let store_dir = repo_path.join("workspace_store");
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .set_path(WorkspaceName::DEFAULT, workspace_root)
+        .unwrap();
+    std::fs::set_permissions(&store_dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+    let is_writable = std::fs::create_dir(store_dir.join("probe")).is_ok();
+    let result = SimpleWorkspaceStore::load(repo_path);
+    std::fs::set_permissions(&store_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    if is_writable {
+        // Permissions aren't enforced, e.g. when running as root
+        return;
+    }
+
+    // An existing store that can't be written is read-only too
+    let store = result.unwrap();
+    assert!(store.is_read_only());
+    assert_eq!(
+        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+        workspace_root
+    );
+    assert_matches!(
+        store.set_path(WorkspaceName::new("second"), workspace_root),
+        Err(WorkspaceStoreError::ReadOnly(_))
+    );
+}
+
+#[test]
+fn test_load_readonly() {
+    let test_workspace = TestWorkspace::init();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    // A directory with nothing but the store, so that nothing else of the repo
+    // can be loaded
+    let temp_dir = testutils::new_temp_dir();
+    let repo_path = temp_dir.path().join("bare");
+    std::fs::create_dir(&repo_path).unwrap();
+
+    // A repo without a store has no entries, and no store is created
+    let store = SimpleWorkspaceStore::load_readonly(&repo_path).unwrap();
+    assert!(store.is_read_only());
+    assert!(store.list().unwrap().is_empty());
+    assert!(!repo_path.join("workspace_store").exists());
+
+    let names = ["default", "team/feature"].map(WorkspaceNameBuf::from);
+    let writable_store = SimpleWorkspaceStore::init(&repo_path).unwrap();
+    for name in &names {
+        writable_store.set_path(name, workspace_root).unwrap();
+    }
+    let store = SimpleWorkspaceStore::load_readonly(&repo_path).unwrap();
+    assert_eq!(store.list().unwrap(), names);
+    assert_eq!(
+        store.get_all_paths().unwrap(),
+        names
+            .iter()
+            .map(|name| (name.clone(), workspace_root.to_owned()))
+            .collect_vec()
+    );
+    assert_matches!(
+        store.set_path(WorkspaceName::new("new"), workspace_root),
+        Err(WorkspaceStoreError::ReadOnly(_))
+    );
+    assert_matches!(
+        store.remove_path(WorkspaceName::DEFAULT),
+        Err(WorkspaceStoreError::ReadOnly(_))
+    );
+    assert_eq!(writable_store.list().unwrap(), names);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_timeout() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        read_timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    assert_eq!(
+        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+        test_workspace.workspace.workspace_root()
+    );
+
+    // Opening a FIFO blocks until there's a writer, like a hung file system
+    let fifo = repo_path.join("workspace_store").join("hung");
+    nix::unistd::mkfifo(&fifo, nix::sys::stat::Mode::S_IRWXU).unwrap();
+    assert_matches!(
+        store.get_path(WorkspaceName::new("hung")),
+        Err(WorkspaceStoreError::Timeout(path)) if path == fifo
+    );
+    // Unblock the reader
+    drop(std::fs::OpenOptions::new().write(true).open(&fifo).unwrap());
+}
+
+#[test]
+fn test_corrupt_entry() {
+    let test_workspace = TestWorkspace::init();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    std::fs::write(
+        test_workspace
+            .repo_path()
+            .join("workspace_store")
+            .join("bad"),
+        b"\xff\xff\xff",
+    )
+    .unwrap();
+    assert_matches!(
+        store.get_path(WorkspaceName::new("bad")),
+        Err(WorkspaceStoreError::Corrupt { name, .. }) if name == "bad"
+    );
+
+    // An entry without a path is corrupt, too
+    std::fs::write(
+        test_workspace
+            .repo_path()
+            .join("workspace_store")
+            .join("no-path"),
+        b"\x0a\x07no-path",
+    )
+    .unwrap();
+    assert_matches!(
+        store.get_path(WorkspaceName::new("no-path")),
+        Err(WorkspaceStoreError::Corrupt { name, .. }) if name == "no-path"
+    );
+
+    // Machine consumers can tell errors apart
+    let err = store.get_path(WorkspaceName::new("bad")).unwrap_err();
+    assert_eq!(err.code(), "corrupt");
+    assert_eq!(err.workspace_name(), Some(WorkspaceName::new("bad")));
+    let err = store.get_path(WorkspaceName::new("missing")).unwrap_err();
+    assert_eq!(err.code(), "not_found");
+}
+
+#[test]
+fn test_checksum() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let options = SimpleWorkspaceStoreOptions {
+        checksum: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let plain_store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let name = WorkspaceName::new("new");
+    let file = repo_path.join("workspace_store").join("new");
+
+    // Entries without a checksum are still read
+    assert_eq!(
+        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+        workspace_root
+    );
+
+    store.set_path(name, workspace_root).unwrap();
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+    assert_eq!(plain_store.get_path(name).unwrap(), workspace_root);
+    let buf = std::fs::read(&file).unwrap();
+
+    // A flipped byte in the path is detected
+    let mut bad_buf = buf.clone();
+    let pos = bad_buf.len() - 8;
+    bad_buf[pos] ^= 1;
+    std::fs::write(&file, &bad_buf).unwrap();
+    assert_matches!(
+        store.get_path(name),
+        Err(WorkspaceStoreError::Corrupt { name: n, .. }) if n == name
+    );
+
+    // Likewise in the checksum
+    let mut bad_buf = buf.clone();
+    *bad_buf.last_mut().unwrap() ^= 1;
+    std::fs::write(&file, &bad_buf).unwrap();
+    assert_matches!(
+        store.get_path(name),
+        Err(WorkspaceStoreError::Corrupt { name: n, .. }) if n == name
+    );
+
+    // A missing checksum is detected
+    std::fs::write(&file, &buf[..buf.len() - 4]).unwrap();
+    assert_matches!(
+        store.get_path(name),
+        Err(WorkspaceStoreError::Corrupt { name: n, .. }) if n == name
+    );
+
+    // Entries written without the option have no checksum
+    plain_store.set_path(name, workspace_root).unwrap();
+    assert_eq!(std::fs::read(&file).unwrap().len(), buf.len() - 6);
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+}
+
+#[test]
+fn test_compress() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let options = SimpleWorkspaceStoreOptions {
+        compress: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let plain_store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let name = WorkspaceName::new("new");
+    let file = repo_path.join("workspace_store").join("new");
+
+    // Uncompressed entries are still read
+    assert_eq!(
+        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+        workspace_root
+    );
+
+    // Compressed entries are recognized by their magic bytes
+    store.set_path(name, workspace_root).unwrap();
+    let buf = std::fs::read(&file).unwrap();
+    assert_eq!(buf[..2], [0x1f, 0x8b]);
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+    assert_eq!(plain_store.get_path(name).unwrap(), workspace_root);
+
+    // Truncated or mangled data is detected
+    for bad_buf in [buf[..buf.len() - 1].to_vec(), buf[..2].to_vec(), {
+        let mut bad_buf = buf.clone();
+        bad_buf[buf.len() / 2] ^= 0xff;
+        bad_buf
+    }] {
+        std::fs::write(&file, &bad_buf).unwrap();
+        assert_matches!(
+            store.get_path(name),
+            Err(WorkspaceStoreError::Corrupt { name: n, .. }) if n == name
+        );
+    }
+
+    // Entries written without the option aren't compressed
+    plain_store.set_path(name, workspace_root).unwrap();
+    assert_ne!(std::fs::read(&file).unwrap()[..2], [0x1f, 0x8b]);
+    assert_eq!(store.get_path(name).unwrap(), workspace_root);
+
+    // The JSON format can be compressed too
+    std::fs::remove_dir_all(repo_path.join("workspace_store")).unwrap();
+    let options = SimpleWorkspaceStoreOptions {
+        compress: true,
+        ..Default::default()
+    };
+    let json_store = SimpleWorkspaceStore::init_json(repo_path, options).unwrap();
+    json_store.set_path(name, workspace_root).unwrap();
+    let buf = std::fs::read(json_store.store_dir().join("new.json")).unwrap();
+    assert_eq!(buf[..2], [0x1f, 0x8b]);
+    let json_store = SimpleWorkspaceStore::load_json(repo_path, Default::default()).unwrap();
+    assert_eq!(json_store.get_path(name).unwrap(), workspace_root);
+}
+
+/// Generates entry contents: arbitrary bytes, an entry claiming a huge path,
+/// and truncated or mutated copies of the `valid` entry.
+fn arb_entry(valid: Vec<u8>) -> impl Strategy<Value = Vec<u8>> {
+    let mutations = prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..4);
+    prop_oneof![
+        prop::collection::vec(any::<u8>(), 0..64),
+        // Field 2 (path) with a length prefix far beyond the end of the entry
+        Just(b"\x12\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01".to_vec()),
+        (0..=valid.len(), mutations).prop_map(move |(len, mutations)| {
+            let mut buf = valid[..len].to_vec();
+            for (index, byte) in mutations {
+                if !buf.is_empty() {
+                    let pos = index.index(buf.len());
+                    buf[pos] ^= byte;
+                }
+            }
+            buf
+        }),
+    ]
+}
+
+/// Reads the entry of `name` in every possible way. Malformed entries must be
+/// reported as errors rather than panic.
+fn check_read_entry(store: &dyn WorkspaceStore, name: &WorkspaceName) -> Result<(), TestCaseError> {
+    fn check<T: std::fmt::Debug>(
+        what: &str,
+        result: Result<T, WorkspaceStoreError>,
+    ) -> Result<(), TestCaseError> {
+        prop_assert!(
+            matches!(
+                result,
+                Ok(_)
+                    | Err(WorkspaceStoreError::Corrupt { .. })
+                    // Empty entries are treated as missing
+                    | Err(WorkspaceStoreError::NotFound(_))
+            ),
+            "unexpected result of {what}: {result:?}"
+        );
+        Ok(())
+    }
+    check("get_path", store.get_path(name))?;
+    check("get_display_path", store.get_display_path(name))?;
+    check("created_by_version", store.created_by_version(name))?;
+    check("created_by_uid", store.created_by_uid(name))?;
+    check("get_many", store.get_many(&[name.to_owned()]))?;
+    check("get_all_paths", store.get_all_paths())?;
+    Ok(())
+}
+
+/// Feeds arbitrary entries to the decoders through the read path of the
+/// stores.
+///
+/// Use `PROPTEST_CASES=10000` to increase the number of test cases to run.
+#[test]
+fn test_decode_arbitrary_entry() {
+    let name = WorkspaceName::new("fuzz");
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        checksum: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    store
+        .set_path(name, test_workspace.workspace.workspace_root())
+        .unwrap();
+    let file = repo_path.join("workspace_store").join("fuzz");
+    let valid = std::fs::read(&file).unwrap();
+    proptest!(|(buf in arb_entry(valid))| {
+        std::fs::write(&file, &buf).unwrap();
+        check_read_entry(&store, name)?;
+    });
+
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    SimpleWorkspaceStore::load(repo_path)
+        .unwrap()
+        .remove_path(WorkspaceName::DEFAULT)
+        .unwrap();
+    let store = SimpleWorkspaceStore::init_json(repo_path, Default::default()).unwrap();
+    store
+        .set_path(name, test_workspace.workspace.workspace_root())
+        .unwrap();
+    let file = repo_path.join("workspace_store").join("fuzz.json");
+    let valid = std::fs::read(&file).unwrap();
+    proptest!(|(buf in arb_entry(valid))| {
+        std::fs::write(&file, &buf).unwrap();
+        check_read_entry(&store, name)?;
+    });
+}
+
+#[test]
+fn test_list_sorted() {
+    // In byte order, so uppercase letters come first and non-ASCII last
+    let names = ["10", "9", "B", "a", "a-c", "c", "x/y", "é"].map(WorkspaceNameBuf::from);
+
+    let test_workspace = TestWorkspace::init();
+    let root = test_workspace.workspace.workspace_root();
+    let store = SimpleWorkspaceStore::load(test_workspace.repo_path()).unwrap();
+    store.remove_path(WorkspaceName::DEFAULT).unwrap();
+    proptest!(ProptestConfig::with_cases(16), |(shuffled in Just(names.to_vec()).prop_shuffle())| {
+        for name in &shuffled {
+            store.set_path(name, root).unwrap();
+        }
+        prop_assert_eq!(store.list().unwrap(), &names);
+        let paths = store.get_all_paths().unwrap();
+        prop_assert!(paths.iter().map(|(name, _)| name).eq(&names));
+        for name in &shuffled {
+            store.remove_path(name).unwrap();
+        }
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_load_dangling_symlink() {
+    let test_workspace = TestWorkspace::init();
+    let store_dir = test_workspace.repo_path().join("workspace_store");
+    let missing_dir = test_workspace.root_dir().join("missing");
+    std::fs::remove_dir_all(&store_dir).unwrap();
+    std::os::unix::fs::symlink(&missing_dir, &store_dir).unwrap();
+
+    assert_matches!(
+        SimpleWorkspaceStore::load(test_workspace.repo_path()),
+        Err(WorkspaceStoreError::NotADirectory(path)) if path == store_dir
+    );
+    // Nothing was created at the symlink target
+    assert!(!missing_dir.exists());
+}
+
+#[test]
+fn test_empty_entry() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store_dir = repo_path.join("workspace_store");
+    let name = WorkspaceName::new("empty");
+    std::fs::write(store_dir.join("empty"), b"").unwrap();
+
+    // By default, an empty entry is treated as missing
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    assert_matches!(
+        store.get_path(name),
+        Err(WorkspaceStoreError::NotFound(n)) if n == name
+    );
+    assert!(!store.exists(name).unwrap());
+
+    let options = SimpleWorkspaceStoreOptions {
+        empty_entry_is_missing: false,
+        ..Default::default()
+    };
+    let strict_store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    assert_matches!(
+        strict_store.get_path(name),
+        Err(WorkspaceStoreError::Corrupt { name: n, .. }) if n == name
+    );
+    assert!(strict_store.exists(name).unwrap());
+
+    // Unreadable entries are moved out of the way
+    std::fs::write(store_dir.join("bad"), b"\xff\xff\xff").unwrap();
+    assert_eq!(
+        sorted_names(&store),
+        ["bad", "default", "empty"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        store.repair().unwrap().into_iter().sorted().collect_vec(),
+        ["bad", "empty"].map(WorkspaceNameBuf::from)
+    );
+    assert_eq!(
+        sorted_names(&store),
+        ["default"].map(WorkspaceNameBuf::from)
+    );
+    assert!(store_dir.join(".corrupt").join("bad").exists());
+    assert!(store_dir.join(".corrupt").join("empty").exists());
+    assert!(store.repair().unwrap().is_empty());
+}
+
+#[test]
+fn test_tombstones() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        tombstones: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let store_dir = store.store_dir().to_owned();
+    let foo = WorkspaceName::new("foo");
+    let foo_root = test_workspace.env.root().join("foo");
+    let other_root = test_workspace.env.root().join("other");
+    std::fs::create_dir(&foo_root).unwrap();
+    std::fs::create_dir(&other_root).unwrap();
+
+    // Nothing to restore
+    assert!(!store.untombstone(foo).unwrap());
+
+    store.set_path(foo, &foo_root).unwrap();
+    store.remove_path(foo).unwrap();
+    assert!(!store.exists(foo).unwrap());
+    assert!(store_dir.join(".tombstones").join("foo").exists());
+    assert_eq!(store.list().unwrap(), [WorkspaceName::DEFAULT.to_owned()]);
+
+    assert!(store.untombstone(foo).unwrap());
+    assert_eq!(store.get_path(foo).unwrap(), foo_root);
+    assert!(!store_dir.join(".tombstones").join("foo").exists());
+
+    // A workspace recorded again after being removed isn't overwritten
+    store.remove_path(foo).unwrap();
+    store.set_path(foo, &other_root).unwrap();
+    assert!(!store.untombstone(foo).unwrap());
+    assert_eq!(store.get_path(foo).unwrap(), other_root);
+
+    // Only tombstones older than the cutoff are purged
+    store.remove_path(foo).unwrap();
+    let past = SystemTime::now() - Duration::from_secs(3600);
+    assert_eq!(store.purge_tombstones(past).unwrap(), 0);
+    let future = SystemTime::now() + Duration::from_secs(3600);
+    assert_eq!(store.purge_tombstones(future).unwrap(), 1);
+    assert!(!store.untombstone(foo).unwrap());
+
+    // Without the option, entries are deleted right away
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    store.set_path(foo, &foo_root).unwrap();
+    store.remove_path(foo).unwrap();
+    assert!(!store.untombstone(foo).unwrap());
+}
+
+#[test]
+fn test_forget_undo_root() {
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let options = SimpleWorkspaceStoreOptions {
+        tombstones: true,
+        ..Default::default()
+    };
+    let store = SimpleWorkspaceStore::load_with_options(repo_path, options).unwrap();
+    let name = WorkspaceName::new("second");
+    let second_root = test_workspace.env.root().join("second");
+    std::fs::create_dir(&second_root).unwrap();
+
+    // Adding the workspace sets its working-copy commit and records its root
+    let wc_commit_id = test_workspace
+        .repo
+        .view()
+        .get_wc_commit_id(WorkspaceName::DEFAULT)
+        .unwrap()
+        .clone();
+    let mut tx = test_workspace.repo.start_transaction();
+    tx.repo_mut()
+        .set_wc_commit(name.to_owned(), wc_commit_id)
+        .unwrap();
+    let added_repo = tx.commit("add workspace").unwrap();
+    store.set_path(name, &second_root).unwrap();
+
+    // Forgetting it removes both
+    let mut tx = added_repo.start_transaction();
+    tx.repo_mut().remove_wc_commit(name).unwrap();
+    let forgotten_repo = tx.commit("forget workspace").unwrap();
+    store.remove_path(name).unwrap();
+    let workspaces = Workspaces::new(&store, forgotten_repo.view());
+    assert_eq!(workspaces.get(name).unwrap(), None);
+
+    // Undoing the forget brings the view back, after which the root is restored
+    let mut tx = forgotten_repo.start_transaction();
+    tx.repo_mut()
+        .set_view(added_repo.view().store_view().clone());
+    let undone_repo = tx.commit("undo").unwrap();
+    assert_eq!(
+        restore_entries(&store, forgotten_repo.view(), undone_repo.view()).unwrap(),
+        [name.to_owned()]
+    );
+    let workspaces = Workspaces::new(&store, undone_repo.view());
+    assert_eq!(workspaces.resolve_root(name).unwrap(), second_root);
+    assert_eq!(workspaces.path_status(name).unwrap(), PathStatus::Exists);
+    assert!(
+        restore_entries(&store, forgotten_repo.view(), undone_repo.view())
+            .unwrap()
+            .is_empty()
+    );
+
+    // Without tombstones, the workspace comes back without its root
+    let plain_store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    plain_store.remove_path(name).unwrap();
+    assert!(
+        restore_entries(&plain_store, forgotten_repo.view(), undone_repo.view())
+            .unwrap()
+            .is_empty()
+    );
+    let workspaces = Workspaces::new(&plain_store, undone_repo.view());
+    assert_matches!(
+        workspaces.resolve_root(name),
+        Err(WorkspaceStoreError::NotFound(n)) if n == name
+    );
+    assert_eq!(workspaces.path_status(name).unwrap(), PathStatus::Unknown);
+}
@@ -0,0 +1,221 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::max;
+use std::thread;
+
+use assert_matches::assert_matches;
+use itertools::Itertools as _;
+use jj_lib::ref_name::WorkspaceName;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::workspace_store::SimpleWorkspaceStore;
+use jj_lib::workspace_store::WorkspaceStore as _;
+use jj_lib::workspace_store::WorkspaceStoreError;
+use testutils::TestWorkspace;
+
+const SHARED_NAMES: [&str; 3] = ["shared0", "shared1", "shared2"];
+
+#[test]
+fn test_workspace_store_parallel() {
+    // Many threads mutate overlapping and distinct entries of the same store
+    // instance. Every operation must either succeed or fail cleanly, and the
+    // store must be consistent once all threads are done.
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let workspace_dir = test_workspace.root_dir();
+
+    let num_threads = max(num_cpus::get(), 4);
+    thread::scope(|s| {
+        for i in 0..num_threads {
+            let store = &store;
+            let workspace_dir = &workspace_dir;
+            s.spawn(move || {
+                let own_name = WorkspaceNameBuf::from(format!("thread{i}"));
+                let renamed_name = WorkspaceNameBuf::from(format!("thread{i}-renamed"));
+                for j in 0..20 {
+                    let shared_name = WorkspaceName::new(SHARED_NAMES[(i + j) % 3]);
+                    let other_shared_name = WorkspaceName::new(SHARED_NAMES[(i + j + 1) % 3]);
+                    store.set_path(shared_name, workspace_dir).unwrap();
+                    // Other threads may have removed or renamed the entry in the
+                    // meantime, but it must never be observed half-written.
+                    assert_matches!(
+                        store.get_path(shared_name),
+                        Ok(_) | Err(WorkspaceStoreError::NotFound(_))
+                    );
+                    assert_matches!(
+                        store.rename(shared_name, other_shared_name),
+                        Ok(())
+                            | Err(WorkspaceStoreError::NotFound(_)
+                                | WorkspaceStoreError::AlreadyExists(_))
+                    );
+                    store.remove_path(other_shared_name).unwrap();
+
+                    store.set_path(&own_name, workspace_dir).unwrap();
+                    store.rename(&own_name, &renamed_name).unwrap();
+                    assert!(!store.exists(&own_name).unwrap());
+                    store.rename(&renamed_name, &own_name).unwrap();
+                    assert_eq!(
+                        store.get_path(&own_name).unwrap(),
+                        dunce::canonicalize(workspace_dir).unwrap()
+                    );
+                }
+            });
+        }
+    });
+
+    // Every entry that survived decodes.
+    let names = store.list().unwrap();
+    for name in &names {
+        store.get_path(name).unwrap();
+    }
+    // Each thread left its own entry behind, besides the default workspace and
+    // possibly some of the shared entries.
+    let expected_names = (0..num_threads)
+        .map(|i| format!("thread{i}"))
+        .chain(["default".to_owned()])
+        .sorted()
+        .collect_vec();
+    let actual_names = names
+        .iter()
+        .map(|name| name.as_str().to_owned())
+        .filter(|name| !SHARED_NAMES.contains(&name.as_str()))
+        .sorted()
+        .collect_vec();
+    assert_eq!(actual_names, expected_names);
+    // No temporary files or lock files were left behind.
+    let file_names = std::fs::read_dir(repo_path.join("workspace_store"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .sorted()
+        .collect_vec();
+    assert_eq!(
+        file_names,
+        names
+            .iter()
+            .map(|name| name.as_str())
+            .sorted()
+            .collect_vec()
+    );
+}
+
+#[test]
+fn test_workspace_store_parallel_instances() {
+    // Like the test above but loads a separate store instance for every thread,
+    // which makes it behave very similar to separate processes.
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let workspace_dir = test_workspace.root_dir();
+
+    let num_threads = max(num_cpus::get(), 4);
+    thread::scope(|s| {
+        for i in 0..num_threads {
+            let workspace_dir = &workspace_dir;
+            s.spawn(move || {
+                let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+                let name = WorkspaceName::new(SHARED_NAMES[i % 3]);
+                for _ in 0..20 {
+                    store.set_path(name, workspace_dir).unwrap();
+                    assert_matches!(
+                        store.get_path(name),
+                        Ok(_) | Err(WorkspaceStoreError::NotFound(_))
+                    );
+                    store.remove_path(name).unwrap();
+                }
+                store.set_path(name, workspace_dir).unwrap();
+            });
+        }
+    });
+
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    let names = store.list().unwrap().into_iter().sorted().collect_vec();
+    assert_eq!(
+        names,
+        ["default", "shared0", "shared1", "shared2"].map(WorkspaceNameBuf::from)
+    );
+    for name in &names {
+        store.get_path(name).unwrap();
+    }
+}
+
+#[test]
+fn test_workspace_store_replace_all_parallel() {
+    // One thread keeps replacing all entries while others read and write. The
+    // store directory is swapped by each replacement, but readers must never
+    // find it missing, and writers must still be serialized with it.
+    let test_workspace = TestWorkspace::init();
+    let repo_path = test_workspace.repo_path();
+    let workspace_root = test_workspace.workspace.workspace_root();
+    let num_threads = max(num_cpus::get(), 4);
+    let workspace_dirs = (0..=num_threads)
+        .map(|i| {
+            let dir = test_workspace.root_dir().join(format!("dir{i}"));
+            std::fs::create_dir(&dir).unwrap();
+            dir
+        })
+        .collect_vec();
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+            let entries = [
+                (WorkspaceName::DEFAULT.to_owned(), workspace_root),
+                ("replaced".into(), &workspace_dirs[num_threads]),
+            ];
+            for _ in 0..50 {
+                store.replace_all(&entries).unwrap();
+            }
+        });
+        for (i, workspace_dir) in workspace_dirs[..num_threads].iter().enumerate() {
+            s.spawn(move || {
+                let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+                let name = WorkspaceNameBuf::from(format!("thread{i}"));
+                for _ in 0..50 {
+                    // The default workspace is kept by every replacement.
+                    assert!(
+                        store
+                            .list()
+                            .unwrap()
+                            .iter()
+                            .any(|name| name == WorkspaceName::DEFAULT)
+                    );
+                    assert_eq!(
+                        store.get_path(WorkspaceName::DEFAULT).unwrap(),
+                        workspace_root
+                    );
+                    // The entry may be dropped by a replacement, but it must
+                    // never be observed half-written.
+                    store.set_path(&name, workspace_dir).unwrap();
+                    assert_matches!(
+                        store.get_path(&name),
+                        Ok(_) | Err(WorkspaceStoreError::NotFound(_))
+                    );
+                }
+            });
+        }
+    });
+
+    let store = SimpleWorkspaceStore::load(repo_path).unwrap();
+    for name in store.list().unwrap() {
+        store.get_path(&name).unwrap();
+    }
+    // Neither the lock file nor the directories of the replacements were left
+    // behind.
+    let file_names = std::fs::read_dir(repo_path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("workspace_store"))
+        .collect_vec();
+    assert_eq!(file_names, ["workspace_store"]);
+}